@@ -11,10 +11,14 @@ import { Construct } from "constructs";
 import * as acm from "aws-cdk-lib/aws-certificatemanager";
 import * as cloudfront from "aws-cdk-lib/aws-cloudfront";
 import * as origins from "aws-cdk-lib/aws-cloudfront-origins";
+import * as dynamodb from "aws-cdk-lib/aws-dynamodb";
+import * as events from "aws-cdk-lib/aws-events";
+import * as eventsTargets from "aws-cdk-lib/aws-events-targets";
 import * as lambda from "aws-cdk-lib/aws-lambda";
 import * as route53 from "aws-cdk-lib/aws-route53";
 import * as targets from "aws-cdk-lib/aws-route53-targets";
 import * as s3 from "aws-cdk-lib/aws-s3";
+import * as secretsmanager from "aws-cdk-lib/aws-secretsmanager";
 
 export class InfraStack extends Stack {
   constructor(scope: Construct, id: string, props?: StackProps) {
@@ -73,34 +77,128 @@ export class InfraStack extends Stack {
 
     const graphqlOriginSecret =
       process.env.GRAPHQL_ORIGIN_SECRET ?? "replace-me";
+    // During a rotation the API keeps accepting the previous secret until
+    // CloudFront has switched to the new one.
+    const graphqlOriginSecrets = [
+      graphqlOriginSecret,
+      process.env.GRAPHQL_ORIGIN_SECRET_PREVIOUS,
+    ].filter((secret): secret is string => Boolean(secret));
 
+    // Browser CORS is handled by the API itself so the Lambda and local server
+    // answer preflights the same way.
+    const corsAllowedOrigins = [
+      "http://localhost:3000",
+      `https://${canonicalDomain}`,
+      `https://www.${canonicalDomain}`,
+      `https://${apiDomain}`,
+      ...alternateDomains.flatMap((domain) => [
+        `https://${domain}`,
+        `https://www.${domain}`,
+      ]),
+    ];
+
+    // Credentials for admin fields, passed through from the deploy
+    // environment when set.
+    const authEnvironment = Object.fromEntries(
+      [
+        "AUTH_API_TOKENS",
+        "AUTH_JWT_HS256_SECRET",
+        "AUTH_JWT_ED25519_PUBLIC_KEY",
+        "AUTH_JWT_ISSUER",
+        "AUTH_JWT_AUDIENCE",
+      ].flatMap((name) => {
+        const value = process.env[name];
+        return value ? [[name, value]] : [];
+      }),
+    );
+
+    // Keys the API signs and hashes with. Every instance has to use the same
+    // ones, so they are generated once and resolved into the environment at
+    // deploy time.
+    const generatedSecret = (id: string) =>
+      new secretsmanager.Secret(this, id, {
+        generateSecretString: { excludePunctuation: true, passwordLength: 48 },
+      }).secretValue.unsafeUnwrap();
+
+    // Queued contact email has to outlive the Lambda instance that took it.
+    const outboxTable = new dynamodb.Table(this, "OutboxTable", {
+      partitionKey: { name: "id", type: dynamodb.AttributeType.STRING },
+      billingMode: dynamodb.BillingMode.PAY_PER_REQUEST,
+    });
+    outboxTable.addGlobalSecondaryIndex({
+      indexName: "due",
+      partitionKey: { name: "status", type: dynamodb.AttributeType.STRING },
+      sortKey: { name: "next_attempt_at", type: dynamodb.AttributeType.NUMBER },
+      projectionType: dynamodb.ProjectionType.KEYS_ONLY,
+    });
+    const outboxEnvironment = {
+      CONTACT_OUTBOX: "dynamodb",
+      CONTACT_OUTBOX_TABLE: outboxTable.tableName,
+    };
+
+    // Contact submissions for the admin API, shared by every instance.
+    const inboxTable = new dynamodb.Table(this, "InboxTable", {
+      partitionKey: { name: "id", type: dynamodb.AttributeType.STRING },
+      billingMode: dynamodb.BillingMode.PAY_PER_REQUEST,
+    });
+    // Every submission shares one `inbox` value, so the admin API pages
+    // through them newest first.
+    inboxTable.addGlobalSecondaryIndex({
+      indexName: "received",
+      partitionKey: { name: "inbox", type: dynamodb.AttributeType.STRING },
+      sortKey: { name: "received_at", type: dynamodb.AttributeType.NUMBER },
+    });
+
+    // Content edited through the API: one item every instance reads, so an
+    // edit is not confined to the instance that made it.
+    const contentTable = new dynamodb.Table(this, "ContentTable", {
+      partitionKey: { name: "id", type: dynamodb.AttributeType.STRING },
+      billingMode: dynamodb.BillingMode.PAY_PER_REQUEST,
+    });
+
+    const apiLambdaCode = lambda.Code.fromAsset(apiLambdaZip);
     const apiLambda = new lambda.Function(this, "ApiLambda", {
       runtime: lambda.Runtime.PROVIDED_AL2023,
       handler: "bootstrap",
-      code: lambda.Code.fromAsset(apiLambdaZip),
+      code: apiLambdaCode,
       memorySize: 512,
       timeout: Duration.seconds(15),
       environment: {
-        GRAPHQL_ORIGIN_SECRET: graphqlOriginSecret,
+        GRAPHQL_ORIGIN_SECRET: graphqlOriginSecrets.join(","),
+        CORS_ALLOWED_ORIGINS: corsAllowedOrigins.join(","),
+        ...outboxEnvironment,
+        CONTACT_INBOX: "dynamodb",
+        CONTACT_INBOX_TABLE: inboxTable.tableName,
+        CONTACT_FORM_SECRET: generatedSecret("ContactFormSecret"),
+        CONTACT_IP_HASH_SECRET: generatedSecret("ContactIpHashSecret"),
+        CONTENT_STORE: "dynamodb",
+        CONTENT_STORE_TABLE: contentTable.tableName,
+        // Unset leaves the admin API disabled.
+        ...authEnvironment,
       },
     });
 
+    // Lambda freezes idle instances, so retries run from a schedule: the
+    // same package with the `outbox` handler sends whatever is due.
+    const outboxWorker = new lambda.Function(this, "OutboxWorker", {
+      runtime: lambda.Runtime.PROVIDED_AL2023,
+      handler: "outbox",
+      code: apiLambdaCode,
+      memorySize: 256,
+      timeout: Duration.seconds(60),
+      environment: outboxEnvironment,
+    });
+    outboxTable.grantReadWriteData(apiLambda);
+    outboxTable.grantReadWriteData(outboxWorker);
+    inboxTable.grantReadWriteData(apiLambda);
+    contentTable.grantReadWriteData(apiLambda);
+    new events.Rule(this, "OutboxSchedule", {
+      schedule: events.Schedule.rate(Duration.minutes(1)),
+      targets: [new eventsTargets.LambdaFunction(outboxWorker)],
+    });
+
     const apiFunctionUrl = apiLambda.addFunctionUrl({
       authType: lambda.FunctionUrlAuthType.NONE,
-      cors: {
-        allowedHeaders: ["content-type", "authorization"],
-        allowedMethods: [lambda.HttpMethod.ALL],
-        allowedOrigins: [
-          "http://localhost:3000",
-          `https://${canonicalDomain}`,
-          `https://www.${canonicalDomain}`,
-          `https://${apiDomain}`,
-          ...alternateDomains.flatMap((domain) => [
-            `https://${domain}`,
-            `https://www.${domain}`,
-          ]),
-        ],
-      },
     });
 
     const apiFunctionUrlDomain = Fn.select(2, Fn.split("/", apiFunctionUrl.url));
@@ -110,7 +208,29 @@ export class InfraStack extends Stack {
       },
     });
 
-    const graphqlCachePolicy = cloudfront.CachePolicy.CACHING_DISABLED;
+    // GET /graphql responses carry Cache-Control from the API's cache hints;
+    // the zero default TTL keeps anything unhinted (and all POSTs) uncached.
+    const graphqlCachePolicy = new cloudfront.CachePolicy(
+      this,
+      "GraphqlCachePolicy",
+      {
+        defaultTtl: Duration.seconds(0),
+        minTtl: Duration.seconds(0),
+        maxTtl: Duration.hours(24),
+        cookieBehavior: cloudfront.CacheCookieBehavior.none(),
+        // CORS response headers depend on the requesting origin. CloudFront
+        // only forwards Authorization when it is part of the cache key; admin
+        // fields are never cached, so this costs no hits.
+        headerBehavior: cloudfront.CacheHeaderBehavior.allowList(
+          "Origin",
+          "Authorization",
+        ),
+        queryStringBehavior: cloudfront.CacheQueryStringBehavior.all(),
+        // The API compresses responses itself; cache each encoding separately.
+        enableAcceptEncodingGzip: true,
+        enableAcceptEncodingBrotli: true,
+      },
+    );
 
     const graphqlOriginRequestPolicy = new cloudfront.OriginRequestPolicy(
       this,
@@ -119,9 +239,13 @@ export class InfraStack extends Stack {
         cookieBehavior: cloudfront.OriginRequestCookieBehavior.none(),
         headerBehavior: cloudfront.OriginRequestHeaderBehavior.allowList(
           "Content-Type",
-          "Origin",
           "Access-Control-Request-Method",
           "Access-Control-Request-Headers",
+          // Client address for contactMe rate limiting.
+          "CloudFront-Viewer-Address",
+          // Shown in contact notifications.
+          "User-Agent",
+          "Referer",
         ),
         queryStringBehavior: cloudfront.OriginRequestQueryStringBehavior.all(),
       },
@@ -135,8 +259,11 @@ export class InfraStack extends Stack {
         maxTtl: Duration.hours(24),
         minTtl: Duration.hours(24),
         cookieBehavior: cloudfront.CacheCookieBehavior.none(),
-        headerBehavior: cloudfront.CacheHeaderBehavior.none(),
+        // CORS response headers depend on the requesting origin.
+        headerBehavior: cloudfront.CacheHeaderBehavior.allowList("Origin"),
         queryStringBehavior: cloudfront.CacheQueryStringBehavior.none(),
+        enableAcceptEncodingGzip: true,
+        enableAcceptEncodingBrotli: true,
       },
     );
 
@@ -207,8 +334,8 @@ function handler(event) {
           },
           "llms.txt": {
             origin: graphqlOrigin,
-            allowedMethods: cloudfront.AllowedMethods.ALLOW_GET_HEAD,
             cachePolicy: llmsTxtCachePolicy,
+            allowedMethods: cloudfront.AllowedMethods.ALLOW_GET_HEAD_OPTIONS,
             viewerProtocolPolicy:
               cloudfront.ViewerProtocolPolicy.REDIRECT_TO_HTTPS,
           },
@@ -0,0 +1,428 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use async_graphql::{Context, ErrorExtensions, Guard};
+use axum::http::HeaderMap;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use ring::signature::{UnparsedPublicKey, ED25519};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use crate::signing::{constant_time_eq, sha256, Signer};
+
+/// Shorter HS256 secrets can be brute-forced from a single token.
+const MIN_SECRET_LEN: usize = 32;
+/// Allowed clock difference between the token issuer and us.
+const LEEWAY_SECS: u64 = 60;
+
+/// What a caller may do. Admins may do everything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Admin,
+    /// Site content, but not the contact inbox.
+    Editor,
+}
+
+impl Role {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Admin => "admin",
+            Self::Editor => "editor",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "admin" => Ok(Self::Admin),
+            "editor" => Ok(Self::Editor),
+            other => Err(format!("unknown role {other:?}")),
+        }
+    }
+}
+
+/// Who made a request. Attached to the GraphQL context when the
+/// `Authorization` header checks out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Principal {
+    /// The JWT `sub`, or the API token's name.
+    pub subject: String,
+    pub roles: Vec<Role>,
+}
+
+impl Principal {
+    pub fn has_role(&self, role: Role) -> bool {
+        self.roles.contains(&Role::Admin) || self.roles.contains(&role)
+    }
+}
+
+/// Why the `Authorization` header was rejected. Attached instead of a
+/// [`Principal`] so guards can say what went wrong.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthFailure(pub String);
+
+struct ApiToken {
+    /// SHA-256 of the token; only the hash is configured.
+    hash: [u8; 32],
+    principal: Principal,
+}
+
+#[derive(Deserialize)]
+struct JwtHeader {
+    alg: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Deserialize)]
+struct Claims {
+    sub: String,
+    exp: u64,
+    nbf: Option<u64>,
+    iss: Option<String>,
+    aud: Option<Audience>,
+    #[serde(default)]
+    roles: Vec<String>,
+}
+
+/// Checks `Authorization: Bearer` credentials: JWTs signed with HS256 or
+/// EdDSA (Ed25519), or static API tokens configured by hash. Separate from
+/// `GRAPHQL_ORIGIN_SECRET`, which CloudFront adds to every request and so
+/// proves nothing about the caller.
+#[derive(Clone, Default)]
+pub struct Authenticator {
+    api_tokens: Arc<[ApiToken]>,
+    hs256: Option<Signer>,
+    /// Raw 32-byte Ed25519 public key.
+    ed25519: Option<Arc<[u8]>>,
+    issuer: Option<String>,
+    audience: Option<String>,
+}
+
+impl fmt::Debug for Authenticator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Authenticator")
+            .field("api_tokens", &self.api_tokens.len())
+            .field("hs256", &self.hs256.is_some())
+            .field("ed25519", &self.ed25519.is_some())
+            .field("issuer", &self.issuer)
+            .field("audience", &self.audience)
+            .finish()
+    }
+}
+
+fn roles(value: &str) -> Result<Vec<Role>, String> {
+    value.split('+').map(Role::parse).collect()
+}
+
+/// Parses `<name>:<role>[+<role>...]:<sha256 hex>`.
+fn api_token(entry: &str) -> Result<ApiToken, String> {
+    let mut parts = entry.trim().splitn(3, ':');
+    let (Some(name), Some(role_list), Some(hex)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(format!("expected <name>:<roles>:<sha256>, got {entry:?}"));
+    };
+    let bytes: Vec<u8> = (0..hex.len())
+        .step_by(2)
+        .map(|i| hex.get(i..i + 2).and_then(|pair| u8::from_str_radix(pair, 16).ok()))
+        .collect::<Option<_>>()
+        .ok_or_else(|| format!("token {name:?}: hash is not hex"))?;
+    let hash = <[u8; 32]>::try_from(bytes).map_err(|_| format!("token {name:?}: hash must be 64 hex digits"))?;
+    Ok(ApiToken {
+        hash,
+        principal: Principal {
+            subject: name.to_string(),
+            roles: roles(role_list).map_err(|err| format!("token {name:?}: {err}"))?,
+        },
+    })
+}
+
+fn decode_part<T: DeserializeOwned>(part: &str) -> Result<T, String> {
+    let bytes = URL_SAFE_NO_PAD.decode(part).map_err(|_| "malformed token".to_string())?;
+    serde_json::from_slice(&bytes).map_err(|err| format!("malformed token: {err}"))
+}
+
+impl Authenticator {
+    /// Accepts API tokens given as `<name>:<roles>:<sha256 hex>` entries.
+    pub fn with_api_tokens(mut self, entries: &str) -> Result<Self, String> {
+        let tokens = entries
+            .split(',')
+            .filter(|entry| !entry.trim().is_empty())
+            .map(api_token)
+            .collect::<Result<Vec<_>, _>>()?;
+        self.api_tokens = tokens.into();
+        Ok(self)
+    }
+
+    pub fn with_hs256_secret(mut self, secret: &str) -> Result<Self, String> {
+        if secret.len() < MIN_SECRET_LEN {
+            return Err(format!("HS256 secret must be at least {MIN_SECRET_LEN} characters"));
+        }
+        self.hs256 = Some(Signer::new(secret));
+        Ok(self)
+    }
+
+    /// `key` is the raw 32-byte public key in standard base64.
+    pub fn with_ed25519_key(mut self, key: &str) -> Result<Self, String> {
+        let key = STANDARD
+            .decode(key.trim())
+            .ok()
+            .filter(|key| key.len() == 32)
+            .ok_or("Ed25519 public key must be 32 bytes of base64")?;
+        self.ed25519 = Some(key.into());
+        Ok(self)
+    }
+
+    /// Reads `AUTH_API_TOKENS`, `AUTH_JWT_HS256_SECRET`,
+    /// `AUTH_JWT_ED25519_PUBLIC_KEY`, `AUTH_JWT_ISSUER` and
+    /// `AUTH_JWT_AUDIENCE`. With none of them set nobody can authenticate.
+    pub fn from_env() -> Result<Self, String> {
+        let mut auth = Self::default();
+        if let Ok(entries) = std::env::var("AUTH_API_TOKENS") {
+            auth = auth.with_api_tokens(&entries).map_err(|err| format!("AUTH_API_TOKENS: {err}"))?;
+        }
+        if let Ok(secret) = std::env::var("AUTH_JWT_HS256_SECRET") {
+            auth = auth
+                .with_hs256_secret(&secret)
+                .map_err(|err| format!("AUTH_JWT_HS256_SECRET: {err}"))?;
+        }
+        if let Ok(key) = std::env::var("AUTH_JWT_ED25519_PUBLIC_KEY") {
+            auth = auth
+                .with_ed25519_key(&key)
+                .map_err(|err| format!("AUTH_JWT_ED25519_PUBLIC_KEY: {err}"))?;
+        }
+        auth.issuer = std::env::var("AUTH_JWT_ISSUER").ok();
+        auth.audience = std::env::var("AUTH_JWT_AUDIENCE").ok();
+        Ok(auth)
+    }
+
+    /// The caller behind `headers`, `None` without credentials, or why the
+    /// credentials were rejected.
+    pub fn authenticate(&self, headers: &HeaderMap) -> Result<Option<Principal>, AuthFailure> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        self.authenticate_at(headers, now)
+    }
+
+    fn authenticate_at(&self, headers: &HeaderMap, now: u64) -> Result<Option<Principal>, AuthFailure> {
+        let Some(value) = headers.get("authorization") else {
+            return Ok(None);
+        };
+        let token = value
+            .to_str()
+            .ok()
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim)
+            .ok_or_else(|| AuthFailure("expected a Bearer token".to_string()))?;
+        let principal = if token.matches('.').count() == 2 {
+            self.verify_jwt(token, now)
+        } else {
+            self.verify_api_token(token)
+        };
+        principal.map(Some).map_err(AuthFailure)
+    }
+
+    fn verify_api_token(&self, token: &str) -> Result<Principal, String> {
+        let hash = sha256(token);
+        self.api_tokens
+            .iter()
+            .find(|known| constant_time_eq(&known.hash, &hash))
+            .map(|known| known.principal.clone())
+            .ok_or_else(|| "unknown token".to_string())
+    }
+
+    fn verify_jwt(&self, token: &str, now: u64) -> Result<Principal, String> {
+        let (signing_input, signature) = token.rsplit_once('.').ok_or("malformed token")?;
+        let (header, claims) = signing_input.split_once('.').ok_or("malformed token")?;
+        // The algorithm must be one we hold a key for, which rules out
+        // `none` and HS256 tokens signed with a public key.
+        let header: JwtHeader = decode_part(header)?;
+        let verified = match header.alg.as_str() {
+            "HS256" => {
+                let signer = self.hs256.as_ref().ok_or("HS256 tokens are not accepted")?;
+                signer.verify(token).is_some()
+            }
+            "EdDSA" => {
+                let key = self.ed25519.as_ref().ok_or("EdDSA tokens are not accepted")?;
+                let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| "malformed token")?;
+                UnparsedPublicKey::new(&ED25519, key)
+                    .verify(signing_input.as_bytes(), &signature)
+                    .is_ok()
+            }
+            other => return Err(format!("unsupported alg {other:?}")),
+        };
+        if !verified {
+            return Err("bad signature".to_string());
+        }
+
+        let claims: Claims = decode_part(claims)?;
+        if claims.exp.saturating_add(LEEWAY_SECS) <= now {
+            return Err("token expired".to_string());
+        }
+        if claims.nbf.is_some_and(|nbf| nbf > now.saturating_add(LEEWAY_SECS)) {
+            return Err("token not yet valid".to_string());
+        }
+        if let Some(issuer) = &self.issuer {
+            if claims.iss.as_ref() != Some(issuer) {
+                return Err("wrong issuer".to_string());
+            }
+        }
+        if let Some(audience) = &self.audience {
+            let matches = match &claims.aud {
+                Some(Audience::One(aud)) => aud == audience,
+                Some(Audience::Many(auds)) => auds.contains(audience),
+                None => false,
+            };
+            if !matches {
+                return Err("wrong audience".to_string());
+            }
+        }
+        // Roles meant for other services are ignored.
+        Ok(Principal {
+            subject: claims.sub,
+            roles: claims.roles.iter().filter_map(|role| Role::parse(role).ok()).collect(),
+        })
+    }
+}
+
+fn auth_error(code: &'static str, message: String) -> async_graphql::Error {
+    async_graphql::Error::new(message).extend_with(|_, extensions| extensions.set("code", code))
+}
+
+/// Lets a field run only for principals with `role`, e.g.
+/// `#[graphql(guard = "RoleGuard::new(Role::Admin)")]`.
+pub struct RoleGuard {
+    role: Role,
+}
+
+impl RoleGuard {
+    pub fn new(role: Role) -> Self {
+        Self { role }
+    }
+}
+
+impl Guard for RoleGuard {
+    async fn check(&self, ctx: &Context<'_>) -> async_graphql::Result<()> {
+        match ctx.data_opt::<Principal>() {
+            Some(principal) if principal.has_role(self.role) => Ok(()),
+            Some(_) => Err(auth_error("FORBIDDEN", format!("Requires the {} role", self.role.as_str()))),
+            None => Err(auth_error(
+                "UNAUTHENTICATED",
+                match ctx.data_opt::<AuthFailure>() {
+                    Some(failure) => format!("Invalid credentials: {}", failure.0),
+                    None => "Authentication required".to_string(),
+                },
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+
+    const NOW: u64 = 1_700_000_000;
+    const SECRET: &str = "0123456789abcdef0123456789abcdef";
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", format!("Bearer {token}").parse().unwrap());
+        headers
+    }
+
+    fn encode(value: serde_json::Value) -> String {
+        URL_SAFE_NO_PAD.encode(value.to_string())
+    }
+
+    fn hs256(claims: serde_json::Value) -> String {
+        let header = encode(serde_json::json!({ "alg": "HS256", "typ": "JWT" }));
+        Signer::new(SECRET).sign(&format!("{header}.{}", encode(claims)))
+    }
+
+    #[test]
+    fn accepts_hashed_api_tokens() {
+        let hash: String = sha256("s3cret-token").iter().map(|byte| format!("{byte:02x}")).collect();
+        let auth = Authenticator::default()
+            .with_api_tokens(&format!("ci:editor:{hash}, chris:admin+editor:{hash}"))
+            .unwrap();
+        let principal = auth.authenticate_at(&bearer("s3cret-token"), NOW).unwrap().unwrap();
+        assert_eq!(principal.subject, "ci");
+        assert!(principal.has_role(Role::Editor) && !principal.has_role(Role::Admin));
+
+        assert_eq!(auth.authenticate_at(&HeaderMap::new(), NOW), Ok(None));
+        assert!(auth.authenticate_at(&bearer("guess"), NOW).is_err());
+        assert!(Authenticator::default().with_api_tokens("ci:root:abcd").is_err());
+        assert!(Authenticator::default().with_api_tokens(&format!("ci:owner:{hash}")).is_err());
+    }
+
+    #[test]
+    fn verifies_hs256_claims() {
+        let auth = Authenticator::default().with_hs256_secret(SECRET).unwrap();
+        let token = hs256(serde_json::json!({ "sub": "chris", "exp": NOW + 60, "roles": ["admin", "billing"] }));
+        let principal = auth.authenticate_at(&bearer(&token), NOW).unwrap().unwrap();
+        assert_eq!(principal, Principal { subject: "chris".to_string(), roles: vec![Role::Admin] });
+
+        let expired = hs256(serde_json::json!({ "sub": "chris", "exp": NOW - 120 }));
+        assert_eq!(
+            auth.authenticate_at(&bearer(&expired), NOW),
+            Err(AuthFailure("token expired".to_string()))
+        );
+        let forged = Authenticator::default()
+            .with_hs256_secret("another-secret-that-is-long-enough")
+            .unwrap();
+        assert!(forged.authenticate_at(&bearer(&token), NOW).is_err());
+        let unsigned = format!(
+            "{}.{}.",
+            encode(serde_json::json!({ "alg": "none" })),
+            encode(serde_json::json!({ "sub": "chris", "exp": NOW + 60, "roles": ["admin"] }))
+        );
+        assert!(auth.authenticate_at(&bearer(&unsigned), NOW).is_err());
+        assert!(Authenticator::default().with_hs256_secret("short").is_err());
+    }
+
+    #[test]
+    fn checks_issuer_and_audience_when_configured() {
+        let auth = Authenticator {
+            issuer: Some("https://auth.example".to_string()),
+            audience: Some("chris-api".to_string()),
+            ..Authenticator::default().with_hs256_secret(SECRET).unwrap()
+        };
+        let token = |iss: &str, aud: serde_json::Value| {
+            hs256(serde_json::json!({ "sub": "chris", "exp": NOW + 60, "iss": iss, "aud": aud }))
+        };
+        let ok = token("https://auth.example", serde_json::json!(["other", "chris-api"]));
+        assert!(auth.authenticate_at(&bearer(&ok), NOW).is_ok());
+        let wrong_issuer = token("https://evil.example", serde_json::json!("chris-api"));
+        assert!(auth.authenticate_at(&bearer(&wrong_issuer), NOW).is_err());
+        let wrong_audience = token("https://auth.example", serde_json::json!("other"));
+        assert!(auth.authenticate_at(&bearer(&wrong_audience), NOW).is_err());
+    }
+
+    #[test]
+    fn verifies_eddsa_signatures() {
+        let pair = Ed25519KeyPair::from_seed_unchecked(&[7; 32]).unwrap();
+        let auth = Authenticator::default()
+            .with_ed25519_key(&STANDARD.encode(pair.public_key().as_ref()))
+            .unwrap();
+        let signing_input = format!(
+            "{}.{}",
+            encode(serde_json::json!({ "alg": "EdDSA" })),
+            encode(serde_json::json!({ "sub": "chris", "exp": NOW + 60, "roles": ["editor"] }))
+        );
+        let signature = URL_SAFE_NO_PAD.encode(pair.sign(signing_input.as_bytes()));
+        let token = format!("{signing_input}.{signature}");
+        let principal = auth.authenticate_at(&bearer(&token), NOW).unwrap().unwrap();
+        assert_eq!(principal.roles, vec![Role::Editor]);
+
+        let tampered = format!("{signing_input}x.{signature}");
+        assert!(auth.authenticate_at(&bearer(&tampered), NOW).is_err());
+        // An HS256 token is refused when only an EdDSA key is configured.
+        let hs = hs256(serde_json::json!({ "sub": "chris", "exp": NOW + 60 }));
+        assert!(auth.authenticate_at(&bearer(&hs), NOW).is_err());
+    }
+}
@@ -0,0 +1,199 @@
+use std::sync::Arc;
+
+use async_graphql::extensions::{Extension, ExtensionContext, ExtensionFactory, NextParseQuery};
+use async_graphql::parser::types::{DocumentOperations, ExecutableDocument, OperationType};
+use async_graphql::{async_trait, CacheControl, ErrorExtensionValues, ServerError, ServerResult, Variables};
+use axum::http::header::{CACHE_CONTROL, CONTENT_TYPE, ETAG, IF_NONE_MATCH};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use sha2::{Digest, Sha256};
+
+/// Error code for mutations sent over GET, which must not have side effects.
+pub const MUTATION_OVER_GET: &str = "MUTATION_OVER_GET";
+
+/// Request data marking an operation that arrived over GET.
+pub struct GetRequest {
+    pub operation_name: Option<String>,
+}
+
+/// Rejects mutations in requests marked with [`GetRequest`]. Runs on the
+/// parsed document so operations sent as a persisted-query hash are checked
+/// too.
+pub struct QueriesOnlyOverGet;
+
+impl ExtensionFactory for QueriesOnlyOverGet {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(QueriesOnlyOverGet)
+    }
+}
+
+#[async_trait::async_trait]
+impl Extension for QueriesOnlyOverGet {
+    async fn parse_query(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        query: &str,
+        variables: &Variables,
+        next: NextParseQuery<'_>,
+    ) -> ServerResult<ExecutableDocument> {
+        let doc = next.run(ctx, query, variables).await?;
+        if let Some(get) = ctx.data_opt::<GetRequest>() {
+            if selects_mutation(&doc, get.operation_name.as_deref()) {
+                let mut error = ServerError::new("Mutations must be sent with POST.", None);
+                let mut extensions = ErrorExtensionValues::default();
+                extensions.set("code", MUTATION_OVER_GET);
+                error.extensions = Some(extensions);
+                return Err(error);
+            }
+        }
+        Ok(doc)
+    }
+}
+
+/// Whether the operation the request will run is a mutation. Without an
+/// operation name any mutation in the document counts, since the server
+/// would otherwise report the ambiguity only after this check.
+fn selects_mutation(doc: &ExecutableDocument, operation_name: Option<&str>) -> bool {
+    match (&doc.operations, operation_name) {
+        (DocumentOperations::Multiple(operations), Some(name)) => operations
+            .iter()
+            .any(|(key, operation)| key.as_str() == name && operation.node.ty == OperationType::Mutation),
+        _ => doc
+            .operations
+            .iter()
+            .any(|(_, operation)| operation.node.ty == OperationType::Mutation),
+    }
+}
+
+/// `Cache-Control` for a successful GET response. Shared caches may only
+/// store responses whose hints are all public.
+pub fn cache_control_header(cache_control: &CacheControl) -> HeaderValue {
+    let value = match cache_control.value() {
+        Some(value) if cache_control.public && cache_control.max_age > 0 => format!("public, {value}"),
+        Some(value) => value,
+        // Nothing hinted: let caches keep the response but revalidate it.
+        None => "no-cache".to_string(),
+    };
+    HeaderValue::from_str(&value).expect("cache-control values are ASCII")
+}
+
+/// Strong validator for a response body.
+pub fn etag(body: &[u8]) -> HeaderValue {
+    let digest = format!("{:x}", Sha256::digest(body));
+    HeaderValue::from_str(&format!("\"{}\"", &digest[..32])).expect("hex is a valid header value")
+}
+
+/// Content codings `compression::tag_encoded_etag` may append to a tag.
+const ENCODING_SUFFIXES: [&str; 3] = ["-gzip\"", "-br\"", "-zstd\""];
+
+/// The tag in `If-None-Match` that matches `etag`, using the weak
+/// comparison RFC 9110 requires for this header. Tags of compressed variants
+/// match the uncompressed body they were derived from; the tag comes back as
+/// the client sent it, so a 304 names the variant being revalidated.
+pub fn if_none_match(headers: &HeaderMap, etag: &HeaderValue) -> Option<HeaderValue> {
+    let tag = etag.to_str().ok()?;
+    let unencoded = |candidate: &str| -> String {
+        let candidate = candidate.trim_start_matches("W/");
+        ENCODING_SUFFIXES
+            .iter()
+            .find_map(|suffix| candidate.strip_suffix(suffix))
+            .map(|tag| format!("{tag}\""))
+            .unwrap_or_else(|| candidate.to_string())
+    };
+    let candidate = headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .find(|candidate| *candidate == "*" || unencoded(candidate) == tag)?;
+    match candidate {
+        "*" => Some(etag.clone()),
+        // Our tags are strong, whatever a cache on the way made of them.
+        candidate => HeaderValue::from_str(candidate.trim_start_matches("W/")).ok(),
+    }
+}
+
+/// Serializes a GET response with caching headers, answering `304 Not
+/// Modified` when the client already holds the same body. Responses with
+/// errors are never cached.
+pub fn get_response(response: async_graphql::Response, request_headers: &HeaderMap) -> Response {
+    let status = if is_mutation_over_get(&response) {
+        StatusCode::METHOD_NOT_ALLOWED
+    } else {
+        StatusCode::OK
+    };
+    let cache_control = if response.is_ok() {
+        cache_control_header(&response.cache_control)
+    } else {
+        HeaderValue::from_static("no-store")
+    };
+    let mut headers = response.http_headers.clone();
+    let body = serde_json::to_vec(&response).expect("responses serialize");
+    let tag = etag(&body);
+    headers.insert(CACHE_CONTROL, cache_control);
+    if status == StatusCode::METHOD_NOT_ALLOWED {
+        headers.insert(axum::http::header::ALLOW, HeaderValue::from_static("POST"));
+    }
+    if response.is_ok() {
+        if let Some(held) = if_none_match(request_headers, &tag) {
+            headers.insert(ETAG, held);
+            return (StatusCode::NOT_MODIFIED, headers).into_response();
+        }
+        headers.insert(ETAG, tag);
+    }
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/graphql-response+json"));
+    (status, headers, body).into_response()
+}
+
+fn is_mutation_over_get(response: &async_graphql::Response) -> bool {
+    response.errors.iter().any(|error| {
+        error
+            .extensions
+            .as_ref()
+            .and_then(|extensions| extensions.get("code"))
+            .is_some_and(|code| *code == async_graphql::Value::from(MUTATION_OVER_GET))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_graphql::parser::parse_query;
+
+    #[test]
+    fn finds_selected_mutations() {
+        let doc = parse_query("query A { ping } mutation B { contactMe }").unwrap();
+        assert!(!selects_mutation(&doc, Some("A")));
+        assert!(selects_mutation(&doc, Some("B")));
+        assert!(selects_mutation(&doc, None));
+        assert!(!selects_mutation(&parse_query("{ ping }").unwrap(), None));
+    }
+
+    #[test]
+    fn formats_cache_control() {
+        let header = |public, max_age| cache_control_header(&CacheControl { public, max_age });
+        assert_eq!(header(true, 3600), "public, max-age=3600");
+        assert_eq!(header(false, 60), "max-age=60, private");
+        assert_eq!(header(true, -1), "no-cache");
+        assert_eq!(header(true, 0), "no-cache");
+    }
+
+    #[test]
+    fn matches_if_none_match() {
+        let tag = etag(b"{}");
+        let mut headers = HeaderMap::new();
+        assert_eq!(if_none_match(&headers, &tag), None);
+        headers.insert(IF_NONE_MATCH, HeaderValue::from_static("\"other\""));
+        assert_eq!(if_none_match(&headers, &tag), None);
+        let listed = format!("\"other\", W/{}", tag.to_str().unwrap());
+        headers.insert(IF_NONE_MATCH, HeaderValue::from_str(&listed).unwrap());
+        assert_eq!(if_none_match(&headers, &tag), Some(tag.clone()));
+        let gzip = tag.to_str().unwrap().replace("\"", "");
+        let gzip = HeaderValue::from_str(&format!("\"{gzip}-gzip\"")).unwrap();
+        headers.insert(IF_NONE_MATCH, gzip.clone());
+        assert_eq!(if_none_match(&headers, &tag), Some(gzip));
+        headers.insert(IF_NONE_MATCH, HeaderValue::from_static("*"));
+        assert_eq!(if_none_match(&headers, &tag), Some(tag));
+    }
+}
@@ -0,0 +1,152 @@
+use std::net::{IpAddr, SocketAddr};
+
+use axum::http::HeaderMap;
+
+/// Viewer address CloudFront adds to origin requests, as `ip:port`.
+const CLOUDFRONT_VIEWER_ADDRESS: &str = "cloudfront-viewer-address";
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// Address of the client that made a GraphQL request, attached to the
+/// request data when it can be determined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+/// Longest header value kept in [`ClientInfo`]; anything past it is noise.
+const MAX_INFO_LEN: usize = 512;
+
+/// Browser details recorded alongside contact messages, attached to every
+/// GraphQL request.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    /// The page the request came from.
+    pub referer: Option<String>,
+}
+
+impl ClientInfo {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name: &str| {
+            let value = headers.get(name)?.to_str().ok()?.trim();
+            let end = value
+                .char_indices()
+                .nth(MAX_INFO_LEN)
+                .map_or(value.len(), |(index, _)| index);
+            (!value.is_empty()).then(|| value[..end].to_string())
+        };
+        Self {
+            user_agent: header("user-agent"),
+            referer: header("referer"),
+        }
+    }
+}
+
+/// How to find the client address behind our proxies.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ClientConfig {
+    /// Proxies that append to `X-Forwarded-For` after the one that saw the
+    /// client. Entries left of the trusted ones can be forged by the client,
+    /// so the address is read this many entries from the right.
+    pub trusted_hops: usize,
+}
+
+impl ClientConfig {
+    pub fn from_env() -> Result<Self, String> {
+        let trusted_hops = match std::env::var("CLIENT_IP_TRUSTED_HOPS") {
+            Ok(value) => value
+                .parse()
+                .map_err(|_| format!("CLIENT_IP_TRUSTED_HOPS must be a number, got {value:?}"))?,
+            Err(_) => 0,
+        };
+        Ok(Self { trusted_hops })
+    }
+
+    /// Prefers `CloudFront-Viewer-Address`, which CloudFront sets itself, and
+    /// falls back to `X-Forwarded-For`.
+    pub fn client_ip(&self, headers: &HeaderMap) -> Option<ClientIp> {
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+        let viewer = header(CLOUDFRONT_VIEWER_ADDRESS).and_then(parse_viewer_address);
+        let forwarded = || {
+            let entries: Vec<&str> = headers
+                .get_all(X_FORWARDED_FOR)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .map(str::trim)
+                .collect();
+            entries
+                .len()
+                .checked_sub(self.trusted_hops + 1)
+                .and_then(|index| entries[index].parse().ok())
+        };
+        viewer.or_else(forwarded).map(ClientIp)
+    }
+}
+
+/// Parses `203.0.113.7:4711` or `2001:db8::1:4711`; CloudFront always
+/// appends the port and does not bracket IPv6 addresses.
+fn parse_viewer_address(value: &str) -> Option<IpAddr> {
+    let value = value.trim();
+    if let Ok(addr) = value.parse::<SocketAddr>() {
+        return Some(addr.ip());
+    }
+    let (ip, port) = value.rsplit_once(':')?;
+    port.parse::<u16>().ok()?;
+    ip.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    fn ip(value: &str) -> Option<ClientIp> {
+        Some(ClientIp(value.parse().unwrap()))
+    }
+
+    #[test]
+    fn prefers_the_cloudfront_viewer_address() {
+        let config = ClientConfig::default();
+        let both = headers(&[
+            ("x-forwarded-for", "198.51.100.1"),
+            ("cloudfront-viewer-address", "203.0.113.7:4711"),
+        ]);
+        assert_eq!(config.client_ip(&both), ip("203.0.113.7"));
+
+        let v6 = headers(&[("cloudfront-viewer-address", "2001:db8::1:4711")]);
+        assert_eq!(config.client_ip(&v6), ip("2001:db8::1"));
+    }
+
+    #[test]
+    fn reads_forwarded_for_from_the_right() {
+        let forged = headers(&[("x-forwarded-for", "10.0.0.1, 203.0.113.7"), ("x-forwarded-for", "198.51.100.1")]);
+        assert_eq!(ClientConfig { trusted_hops: 0 }.client_ip(&forged), ip("198.51.100.1"));
+        assert_eq!(ClientConfig { trusted_hops: 1 }.client_ip(&forged), ip("203.0.113.7"));
+        assert_eq!(ClientConfig { trusted_hops: 3 }.client_ip(&forged), None);
+        assert_eq!(ClientConfig::default().client_ip(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn reads_client_info() {
+        let long = "x".repeat(600);
+        let mut request = headers(&[("user-agent", "Mozilla/5.0"), ("referer", "https://example.com/#contactme")]);
+        assert_eq!(
+            ClientInfo::from_headers(&request),
+            ClientInfo {
+                user_agent: Some("Mozilla/5.0".to_string()),
+                referer: Some("https://example.com/#contactme".to_string()),
+            }
+        );
+        request.insert("user-agent", long.parse().unwrap());
+        request.insert("referer", " ".parse().unwrap());
+        let info = ClientInfo::from_headers(&request);
+        assert_eq!(info.user_agent.map(|ua| ua.len()), Some(MAX_INFO_LEN));
+        assert_eq!(info.referer, None);
+    }
+}
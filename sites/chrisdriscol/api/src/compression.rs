@@ -0,0 +1,71 @@
+use axum::http::header::{CONTENT_ENCODING, ETAG};
+use axum::http::HeaderValue;
+use axum::response::Response;
+use tower_http::compression::predicate::{NotForContentType, Predicate, SizeAbove};
+use tower_http::compression::CompressionLayer;
+
+/// Responses smaller than this many bytes are sent uncompressed.
+const MIN_SIZE_ENV: &str = "COMPRESSION_MIN_BYTES";
+
+/// Below about a kilobyte the framing overhead outweighs the savings.
+const DEFAULT_MIN_SIZE: u16 = 1024;
+
+#[derive(Debug, Clone, Copy)]
+pub struct CompressionConfig {
+    pub min_size: u16,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            min_size: DEFAULT_MIN_SIZE,
+        }
+    }
+}
+
+impl CompressionConfig {
+    pub fn from_env() -> Result<Self, String> {
+        let min_size = match std::env::var(MIN_SIZE_ENV) {
+            Ok(value) => value
+                .trim()
+                .parse()
+                .map_err(|err| format!("invalid {MIN_SIZE_ENV} {value:?}: {err}"))?,
+            Err(_) => DEFAULT_MIN_SIZE,
+        };
+        Ok(Self { min_size })
+    }
+
+    /// Negotiates gzip, brotli or zstd from `Accept-Encoding` and adds
+    /// `Vary: Accept-Encoding` to every response large enough to compress.
+    pub fn layer(&self) -> CompressionLayer<impl Predicate> {
+        let predicate = SizeAbove::new(self.min_size)
+            .and(NotForContentType::IMAGES)
+            .and(NotForContentType::SSE);
+        CompressionLayer::new()
+            .gzip(true)
+            .br(true)
+            .zstd(true)
+            .compress_when(predicate)
+    }
+}
+
+/// Gives each encoding of a response its own strong `ETag` by appending the
+/// coding to the tag, e.g. `"abc"` becomes `"abc-gzip"`. Runs outside the
+/// compression layer; `caching::if_none_match` strips the suffix again and
+/// echoes the client's tag in a 304.
+pub async fn tag_encoded_etag(mut response: Response) -> Response {
+    let encoding = response
+        .headers()
+        .get(CONTENT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let etag = response.headers().get(ETAG).and_then(|value| value.to_str().ok());
+    if let (Some(encoding), Some(etag)) = (encoding, etag) {
+        if let Some(tag) = etag.strip_suffix('"') {
+            if let Ok(value) = HeaderValue::from_str(&format!("{tag}-{encoding}\"")) {
+                response.headers_mut().insert(ETAG, value);
+            }
+        }
+    }
+    response
+}
@@ -0,0 +1,492 @@
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use async_graphql::{async_trait, ErrorExtensions, InputObject, ID};
+
+use crate::data;
+use crate::dates::DateRange;
+use crate::dynamodb::Table;
+use crate::node;
+use crate::search::SearchIndex;
+use crate::types::{About, Chris, Experience, Skills, Work};
+
+mod dynamodb;
+mod json;
+mod memory;
+
+pub use dynamodb::DynamoContentStore;
+pub use json::JsonContentStore;
+pub use memory::MemoryContentStore;
+
+/// How long a snapshot is served before the store is read again, so edits
+/// saved by other instances show up.
+const REFRESH: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContentError {
+    /// The edit was based on an older version than the one stored.
+    Conflict { current: i32 },
+    /// No item has the given ID.
+    NotFound(String),
+    /// The edit would leave the content invalid.
+    Invalid(String),
+    /// The store could not be read or written.
+    Store(String),
+}
+
+impl ContentError {
+    pub fn to_error(&self) -> async_graphql::Error {
+        match self {
+            Self::Conflict { current } => async_graphql::Error::new(format!(
+                "content has changed; current version is {current}"
+            ))
+            .extend_with(|_, extensions| {
+                extensions.set("code", "VERSION_CONFLICT");
+                extensions.set("currentVersion", *current);
+            }),
+            Self::NotFound(message) => async_graphql::Error::new(message.as_str())
+                .extend_with(|_, extensions| extensions.set("code", "NOT_FOUND")),
+            Self::Invalid(message) => async_graphql::Error::new(message.as_str())
+                .extend_with(|_, extensions| extensions.set("code", "INVALID_CONTENT")),
+            Self::Store(message) => async_graphql::Error::new(message.as_str())
+                .extend_with(|_, extensions| extensions.set("code", "CONTENT_UNAVAILABLE")),
+        }
+    }
+}
+
+/// Where editable portfolio content lives.
+#[async_trait::async_trait]
+pub trait ContentStore: Send + Sync {
+    async fn load(&self) -> Result<Chris, String>;
+
+    /// Stores `chris` if the stored version is still `expected`; `chris`
+    /// carries the new version. Implementations must check and write in one
+    /// step so concurrent edits cannot both succeed.
+    async fn save(&self, chris: &Chris, expected: i32) -> Result<(), ContentError>;
+}
+
+/// Content as served, with the search index built from it.
+pub struct Snapshot {
+    pub chris: Chris,
+    pub index: SearchIndex,
+}
+
+impl Snapshot {
+    fn new(chris: Chris) -> Arc<Self> {
+        let index = SearchIndex::new(&chris);
+        Arc::new(Self { chris, index })
+    }
+}
+
+/// The snapshot being served and when it was loaded.
+type Cached = Option<(Instant, Arc<Snapshot>)>;
+
+/// Serves portfolio content and applies edits to it. The latest snapshot is
+/// kept in memory and reloaded from the store at most every `refresh`.
+#[derive(Clone)]
+pub struct Content {
+    pub store: Arc<dyn ContentStore>,
+    current: Arc<RwLock<Cached>>,
+    refresh: Duration,
+}
+
+impl Default for Content {
+    fn default() -> Self {
+        Self::new(Arc::new(MemoryContentStore::new(data::chris())))
+    }
+}
+
+impl Content {
+    pub fn new(store: Arc<dyn ContentStore>) -> Self {
+        Self {
+            store,
+            current: Arc::default(),
+            refresh: REFRESH,
+        }
+    }
+
+    /// Picks the store named by `CONTENT_STORE`: `memory` (default; edits
+    /// last until the process exits and stay on this instance), `json` (one
+    /// file at `CONTENT_STORE_PATH`, default `content.json`) or `dynamodb`
+    /// (the table named by `CONTENT_STORE_TABLE`, shared by every instance).
+    pub fn from_env() -> Result<Self, String> {
+        let store: Arc<dyn ContentStore> = match std::env::var("CONTENT_STORE").as_deref() {
+            Err(_) | Ok("memory") => Arc::new(MemoryContentStore::new(data::chris())),
+            Ok("json") => {
+                let path = std::env::var("CONTENT_STORE_PATH").unwrap_or_else(|_| "content.json".to_string());
+                Arc::new(JsonContentStore::new(path))
+            }
+            Ok("dynamodb") => Arc::new(DynamoContentStore::new(Table::from_env("CONTENT_STORE_TABLE")?)),
+            Ok(other) => return Err(format!("unknown CONTENT_STORE {other:?}")),
+        };
+        Ok(Self::new(store))
+    }
+
+    pub async fn snapshot(&self) -> Result<Arc<Snapshot>, String> {
+        if let Some((loaded_at, snapshot)) = self.current.read().unwrap().clone() {
+            if loaded_at.elapsed() < self.refresh {
+                return Ok(snapshot);
+            }
+        }
+        self.reload().await
+    }
+
+    /// Reads the store now, whatever the age of the cached snapshot.
+    async fn reload(&self) -> Result<Arc<Snapshot>, String> {
+        let cached = self.current.read().unwrap().clone();
+        let chris = self.store.load().await?;
+        // Keep the search index when nobody has edited in the meantime.
+        let snapshot = match cached {
+            Some((_, snapshot)) if snapshot.chris.version == chris.version => snapshot,
+            _ => Snapshot::new(chris),
+        };
+        *self.current.write().unwrap() = Some((Instant::now(), snapshot.clone()));
+        Ok(snapshot)
+    }
+
+    /// Applies `edit` to the content at `version` and stores the result as
+    /// the next version.
+    pub async fn edit<F>(&self, version: i32, edit: F) -> Result<Chris, ContentError>
+    where
+        F: FnOnce(&mut Chris) -> Result<(), ContentError>,
+    {
+        let mut current = self.snapshot().await.map_err(ContentError::Store)?;
+        if current.chris.version != version {
+            // The snapshot may predate a save made by another instance; only
+            // the store can say the edit is stale.
+            current = self.reload().await.map_err(ContentError::Store)?;
+        }
+        if current.chris.version != version {
+            return Err(ContentError::Conflict {
+                current: current.chris.version,
+            });
+        }
+        let mut chris = current.chris.clone();
+        edit(&mut chris)?;
+        data::check_dates(&chris).map_err(ContentError::Invalid)?;
+        chris.version = version + 1;
+        match self.store.save(&chris, version).await {
+            Ok(()) => {
+                *self.current.write().unwrap() = Some((Instant::now(), Snapshot::new(chris.clone())));
+                Ok(chris)
+            }
+            Err(err) => {
+                // Someone else saved first; reload on the next read.
+                if let ContentError::Conflict { .. } = err {
+                    *self.current.write().unwrap() = None;
+                }
+                Err(err)
+            }
+        }
+    }
+}
+
+/// Slugs become part of global IDs and file content, so keep them plain.
+fn check_slug(slug: &str) -> Result<(), ContentError> {
+    let valid = !slug.is_empty()
+        && slug
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if !valid {
+        return Err(ContentError::Invalid(format!(
+            "slug {slug:?} must be lowercase letters, digits and dashes"
+        )));
+    }
+    Ok(())
+}
+
+/// The slug inside a `type_name` global ID.
+fn slug_of(id: &ID, type_name: &str) -> Result<String, ContentError> {
+    match node::parse_global_id(id) {
+        Some((found, slug)) if found == type_name => Ok(slug),
+        _ => Err(ContentError::NotFound(format!("no {type_name} with id {}", id.as_str()))),
+    }
+}
+
+/// A list of portfolio items edited by slug.
+pub trait Item: Clone {
+    const TYPE_NAME: &'static str;
+    fn slug(&self) -> &str;
+}
+
+impl Item for Work {
+    const TYPE_NAME: &'static str = "Work";
+    fn slug(&self) -> &str {
+        &self.slug
+    }
+}
+
+impl Item for Experience {
+    const TYPE_NAME: &'static str = "Experience";
+    fn slug(&self) -> &str {
+        &self.slug
+    }
+}
+
+/// Inserts `item` at `position` (default: the end).
+pub fn add<T: Item>(items: &mut Vec<T>, item: T, position: Option<i32>) -> Result<(), ContentError> {
+    check_slug(item.slug())?;
+    if items.iter().any(|existing| existing.slug() == item.slug()) {
+        return Err(ContentError::Invalid(format!(
+            "a {} with slug {:?} already exists",
+            T::TYPE_NAME,
+            item.slug()
+        )));
+    }
+    let index = match position {
+        None => items.len(),
+        Some(position) => usize::try_from(position)
+            .ok()
+            .filter(|index| *index <= items.len())
+            .ok_or_else(|| ContentError::Invalid(format!("position must be between 0 and {}", items.len())))?,
+    };
+    items.insert(index, item);
+    Ok(())
+}
+
+/// Replaces the item `id` refers to, keeping its slug.
+pub fn update<T: Item>(items: &mut [T], id: &ID, replace: impl FnOnce(String) -> T) -> Result<(), ContentError> {
+    let slug = slug_of(id, T::TYPE_NAME)?;
+    let item = items
+        .iter_mut()
+        .find(|item| item.slug() == slug)
+        .ok_or_else(|| ContentError::NotFound(format!("no {} with id {}", T::TYPE_NAME, id.as_str())))?;
+    *item = replace(slug);
+    Ok(())
+}
+
+pub fn remove<T: Item>(items: &mut Vec<T>, id: &ID) -> Result<(), ContentError> {
+    let slug = slug_of(id, T::TYPE_NAME)?;
+    let before = items.len();
+    items.retain(|item| item.slug() != slug);
+    if items.len() == before {
+        return Err(ContentError::NotFound(format!("no {} with id {}", T::TYPE_NAME, id.as_str())));
+    }
+    Ok(())
+}
+
+/// Puts `items` in the order of `ids`, which must name each item once.
+pub fn reorder<T: Item>(items: &mut Vec<T>, ids: &[ID]) -> Result<(), ContentError> {
+    let mut order = Vec::with_capacity(items.len());
+    for id in ids {
+        let slug = slug_of(id, T::TYPE_NAME)?;
+        match items.iter().position(|item| item.slug() == slug) {
+            Some(index) if !order.contains(&index) => order.push(index),
+            _ => {
+                return Err(ContentError::Invalid(format!(
+                    "{} is unknown or listed twice",
+                    id.as_str()
+                )))
+            }
+        }
+    }
+    if let Some(missing) = (0..items.len()).find(|index| !order.contains(index)) {
+        return Err(ContentError::Invalid(format!(
+            "ids must list every {}; missing {}",
+            T::TYPE_NAME,
+            node::global_id(T::TYPE_NAME, items[missing].slug()).as_str()
+        )));
+    }
+    *items = order.into_iter().map(|index| items[index].clone()).collect();
+    Ok(())
+}
+
+/// Fields of a `Work` item; `updateWork` replaces all of them.
+#[derive(InputObject)]
+pub struct WorkInput {
+    pub title: Option<String>,
+    pub sub_title: Option<String>,
+    #[graphql(default)]
+    pub description: Vec<String>,
+    pub location: Option<String>,
+    pub link: Option<String>,
+    pub video: Option<String>,
+    pub dates: Option<DateRange>,
+    pub image_url: Option<String>,
+    #[graphql(default)]
+    pub technologies: Vec<String>,
+}
+
+impl WorkInput {
+    pub fn into_work(self, slug: String) -> Work {
+        Work {
+            slug,
+            title: self.title,
+            sub_title: self.sub_title,
+            description: self.description,
+            location: self.location,
+            link: self.link,
+            video: self.video,
+            dates: self.dates,
+            image_url: self.image_url,
+            technologies: self.technologies,
+        }
+    }
+}
+
+/// Fields of an `Experience` item; `updateExperience` replaces all of them.
+#[derive(InputObject)]
+pub struct ExperienceInput {
+    pub dates: Option<DateRange>,
+    pub title: Option<String>,
+    pub location: Option<String>,
+    pub description: Option<String>,
+    pub image_url: Option<String>,
+}
+
+impl ExperienceInput {
+    pub fn into_experience(self, slug: String) -> Experience {
+        Experience {
+            slug,
+            dates: self.dates,
+            title: self.title,
+            location: self.location,
+            description: self.description,
+            image_url: self.image_url,
+        }
+    }
+}
+
+#[derive(InputObject)]
+pub struct AboutInput {
+    #[graphql(default)]
+    pub description: Vec<String>,
+    pub image_url: Option<String>,
+    pub image_caption: Option<String>,
+    pub image_title: Option<String>,
+    pub tag_line: Option<String>,
+}
+
+impl From<AboutInput> for About {
+    fn from(input: AboutInput) -> Self {
+        Self {
+            description: input.description,
+            image_url: input.image_url,
+            image_caption: input.image_caption,
+            image_title: input.image_title,
+            tag_line: input.tag_line,
+        }
+    }
+}
+
+#[derive(InputObject)]
+pub struct SkillsInput {
+    #[graphql(default)]
+    pub languages: Vec<String>,
+    #[graphql(default)]
+    pub technologies: Vec<String>,
+    #[graphql(default)]
+    pub tools: Vec<String>,
+    #[graphql(default)]
+    pub loves: Vec<String>,
+}
+
+impl SkillsInput {
+    pub fn into_skills(self, slug: String) -> Skills {
+        Skills {
+            slug,
+            languages: self.languages,
+            technologies: self.technologies,
+            tools: self.tools,
+            loves: self.loves,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn work(slug: &str) -> Work {
+        WorkInput {
+            title: None,
+            sub_title: None,
+            description: Vec::new(),
+            location: None,
+            link: None,
+            video: None,
+            dates: None,
+            image_url: None,
+            technologies: Vec::new(),
+        }
+        .into_work(slug.to_string())
+    }
+
+    fn slugs(items: &[Work]) -> Vec<&str> {
+        items.iter().map(|item| item.slug.as_str()).collect()
+    }
+
+    #[test]
+    fn edits_lists_by_global_id() {
+        let mut items = vec![work("a"), work("b")];
+        add(&mut items, work("c"), Some(1)).unwrap();
+        assert_eq!(slugs(&items), ["a", "c", "b"]);
+        assert!(matches!(add(&mut items, work("c"), None), Err(ContentError::Invalid(_))));
+        assert!(matches!(add(&mut items, work("Bad Slug"), None), Err(ContentError::Invalid(_))));
+        assert!(matches!(add(&mut items, work("d"), Some(9)), Err(ContentError::Invalid(_))));
+
+        let ids: Vec<ID> = ["b", "a", "c"].iter().map(|slug| node::global_id("Work", slug)).collect();
+        reorder(&mut items, &ids).unwrap();
+        assert_eq!(slugs(&items), ["b", "a", "c"]);
+        // A partial or repeated list leaves the order alone.
+        let partial = [ids[2].clone(), ids[2].clone()];
+        assert!(matches!(reorder(&mut items, &partial), Err(ContentError::Invalid(_))));
+        assert_eq!(slugs(&items), ["b", "a", "c"]);
+
+        let experience_id = node::global_id("Experience", "a");
+        assert!(matches!(remove(&mut items, &experience_id), Err(ContentError::NotFound(_))));
+        remove(&mut items, &ids[1]).unwrap();
+        assert_eq!(slugs(&items), ["b", "c"]);
+    }
+
+    #[tokio::test]
+    async fn stale_edits_conflict() {
+        let content = Content::default();
+        let edited = content
+            .edit(0, |chris| {
+                chris.about.tag_line = Some("Hi".to_string());
+                Ok(())
+            })
+            .await
+            .unwrap();
+        let stale = content.edit(0, |_| Ok(())).await;
+
+        assert_eq!(edited.version, 1);
+        assert_eq!(content.snapshot().await.unwrap().chris.about.tag_line.as_deref(), Some("Hi"));
+        assert_eq!(stale.err(), Some(ContentError::Conflict { current: 1 }));
+    }
+
+    #[tokio::test]
+    async fn snapshots_pick_up_edits_from_other_instances() {
+        let store: Arc<dyn ContentStore> = Arc::new(MemoryContentStore::new(data::chris()));
+        let editor = Content::new(store.clone());
+        let cached = Content::new(store.clone());
+        let refreshing = Content {
+            refresh: Duration::ZERO,
+            ..Content::new(store)
+        };
+        cached.snapshot().await.unwrap();
+        refreshing.snapshot().await.unwrap();
+
+        editor.edit(0, |_| Ok(())).await.unwrap();
+
+        assert_eq!(cached.snapshot().await.unwrap().chris.version, 0);
+        assert_eq!(refreshing.snapshot().await.unwrap().chris.version, 1);
+    }
+
+    #[tokio::test]
+    async fn edits_check_versions_against_the_store() {
+        let store: Arc<dyn ContentStore> = Arc::new(MemoryContentStore::new(data::chris()));
+        let first = Content::new(store.clone());
+        let second = Content::new(store);
+        second.snapshot().await.unwrap();
+        first.edit(0, |_| Ok(())).await.unwrap();
+
+        // `second` still caches version 0, but version 1 is current.
+        let edited = second.edit(1, |_| Ok(())).await;
+        let stale = first.edit(1, |_| Ok(())).await;
+
+        assert_eq!(edited.map(|chris| chris.version), Ok(2));
+        assert_eq!(stale.err(), Some(ContentError::Conflict { current: 2 }));
+    }
+}
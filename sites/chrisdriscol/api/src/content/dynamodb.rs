@@ -0,0 +1,96 @@
+use async_graphql::async_trait;
+use serde_json::{json, Value};
+
+use super::{ContentError, ContentStore};
+use crate::data;
+use crate::dynamodb::{get_number, get_string, item, number, string, DynamoError, Item, Table};
+use crate::types::Chris;
+
+/// `id` of the one item that holds the content.
+const KEY: &str = "content";
+
+/// Keeps edited content in a DynamoDB table shared by every instance: one
+/// item holding the version and the whole portfolio as a JSON document, put
+/// only if the stored version is still the expected one. Until the first
+/// save the item does not exist and content comes from `CONTENT_DIR` or the
+/// compiled-in copy.
+pub struct DynamoContentStore {
+    table: Table,
+}
+
+impl DynamoContentStore {
+    pub fn new(table: Table) -> Self {
+        Self { table }
+    }
+}
+
+fn to_item(chris: &Chris) -> Result<Value, String> {
+    Ok(json!({
+        "id": string(KEY),
+        "version": number(chris.version),
+        "content": string(&data::to_json(chris)?),
+    }))
+}
+
+fn from_item(item: &Item) -> Result<Chris, String> {
+    let mut chris = data::from_json(&get_string(item, "content")?)?;
+    chris.version = get_number(item, "version")?;
+    Ok(chris)
+}
+
+#[async_trait::async_trait]
+impl ContentStore for DynamoContentStore {
+    async fn load(&self) -> Result<Chris, String> {
+        let request = json!({
+            "Key": { "id": string(KEY) },
+            "ConsistentRead": true,
+        });
+        let response = self.table.call("GetItem", request).await?;
+        match item(&response, "Item") {
+            Some(item) => from_item(&item),
+            None => Ok(data::chris()),
+        }
+    }
+
+    async fn save(&self, chris: &Chris, expected: i32) -> Result<(), ContentError> {
+        // A missing item is the unedited content, version 0.
+        let condition = if expected == 0 {
+            "attribute_not_exists(id) OR version = :expected"
+        } else {
+            "version = :expected"
+        };
+        let request = json!({
+            "Item": to_item(chris).map_err(ContentError::Store)?,
+            "ConditionExpression": condition,
+            "ExpressionAttributeValues": { ":expected": number(expected) },
+        });
+        match self.table.call("PutItem", request).await {
+            Ok(_) => Ok(()),
+            Err(DynamoError::ConditionFailed) => {
+                let current = self.load().await.map_err(ContentError::Store)?.version;
+                Err(ContentError::Conflict { current })
+            }
+            Err(err) => Err(ContentError::Store(err.into())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_content_through_items() {
+        let mut chris = data::chris();
+        chris.version = 7;
+        chris.about.tag_line = Some("Edited".to_string());
+
+        let item = to_item(&chris).unwrap();
+        assert_eq!(item["version"], json!({ "N": "7" }));
+        let reloaded = from_item(item.as_object().unwrap()).unwrap();
+        assert_eq!(reloaded.version, 7);
+        assert_eq!(reloaded.about.tag_line.as_deref(), Some("Edited"));
+        assert_eq!(reloaded.skills.slug, chris.slug);
+        assert_eq!(reloaded.work.len(), chris.work.len());
+    }
+}
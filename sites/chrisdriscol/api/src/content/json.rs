@@ -0,0 +1,105 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+use async_graphql::async_trait;
+use tokio::sync::Mutex;
+
+use super::{ContentError, ContentStore};
+use crate::data;
+use crate::types::Chris;
+
+/// Keeps edited content, version included, in one JSON file that is
+/// replaced atomically, so a save is either fully applied or not at all.
+/// Until the first save the file does not exist and content comes from
+/// `CONTENT_DIR` or the compiled-in copy.
+pub struct JsonContentStore {
+    path: PathBuf,
+    /// Serialises saves from this process; the version check guards against
+    /// edits made from elsewhere between loads.
+    lock: Mutex<()>,
+}
+
+impl JsonContentStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn read(&self) -> Result<Chris, String> {
+        if !self.path.exists() {
+            return Ok(data::chris());
+        }
+        let text = fs::read_to_string(&self.path)
+            .map_err(|err| format!("failed to read {}: {err}", self.path.display()))?;
+        data::from_json(&text).map_err(|err| format!("{}: {err}", self.path.display()))
+    }
+
+    /// Writes to a temporary file next to `path` and renames it over,
+    /// so readers never see a half-written file.
+    fn write(&self, chris: &Chris) -> Result<(), String> {
+        let failed = |err: std::io::Error| format!("failed to write {}: {err}", self.path.display());
+        let mut text = data::to_json(chris)?;
+        text.push('\n');
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(failed)?;
+        }
+        let tmp = self.path.with_extension(format!("tmp-{}", std::process::id()));
+        let mut file = fs::File::create(&tmp).map_err(failed)?;
+        file.write_all(text.as_bytes()).map_err(failed)?;
+        file.sync_all().map_err(failed)?;
+        fs::rename(&tmp, &self.path).map_err(failed)
+    }
+}
+
+#[async_trait::async_trait]
+impl ContentStore for JsonContentStore {
+    async fn load(&self) -> Result<Chris, String> {
+        let _guard = self.lock.lock().await;
+        self.read()
+    }
+
+    async fn save(&self, chris: &Chris, expected: i32) -> Result<(), ContentError> {
+        let _guard = self.lock.lock().await;
+        let current = self.read().map_err(ContentError::Store)?.version;
+        if current != expected {
+            return Err(ContentError::Conflict { current });
+        }
+        self.write(chris).map_err(ContentError::Store)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn saves_and_reloads_versioned_content() {
+        let dir = std::env::temp_dir().join(format!("chris-content-store-{}", std::process::id()));
+        let store = JsonContentStore::new(dir.join("content.json"));
+
+        // Before the first save, content falls back to the compiled-in copy.
+        let mut chris = store.load().await.unwrap();
+        assert_eq!(chris.version, 0);
+        chris.version = 1;
+        chris.about.tag_line = Some("Edited".to_string());
+        let saved = store.save(&chris, 0).await;
+        let stale = store.save(&chris, 0).await;
+        let reloaded = store.load().await;
+        let files: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(saved, Ok(()));
+        assert_eq!(stale, Err(ContentError::Conflict { current: 1 }));
+        let reloaded = reloaded.unwrap();
+        assert_eq!(reloaded.version, 1);
+        assert_eq!(reloaded.about.tag_line.as_deref(), Some("Edited"));
+        assert_eq!(reloaded.work.len(), chris.work.len());
+        assert_eq!(files, ["content.json"]);
+    }
+}
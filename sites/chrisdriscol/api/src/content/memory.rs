@@ -0,0 +1,38 @@
+use std::sync::Mutex;
+
+use async_graphql::async_trait;
+
+use super::{ContentError, ContentStore};
+use crate::types::Chris;
+
+/// Keeps edited content in process memory, starting from the content the
+/// API was built or started with. Edits are lost on restart.
+pub struct MemoryContentStore {
+    chris: Mutex<Chris>,
+}
+
+impl MemoryContentStore {
+    pub fn new(chris: Chris) -> Self {
+        Self {
+            chris: Mutex::new(chris),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ContentStore for MemoryContentStore {
+    async fn load(&self) -> Result<Chris, String> {
+        Ok(self.chris.lock().unwrap().clone())
+    }
+
+    async fn save(&self, chris: &Chris, expected: i32) -> Result<(), ContentError> {
+        let mut stored = self.chris.lock().unwrap();
+        if stored.version != expected {
+            return Err(ContentError::Conflict {
+                current: stored.version,
+            });
+        }
+        *stored = chris.clone();
+        Ok(())
+    }
+}
@@ -0,0 +1,27 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use chris_driscol_api::{data, lint};
+
+fn main() -> ExitCode {
+    let dir = std::env::args_os().nth(1).map(PathBuf::from);
+    let chris = match data::load(dir.as_deref()) {
+        Ok(chris) => chris,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let issues = lint::lint(&chris);
+    for issue in &issues {
+        eprintln!("{issue}");
+    }
+    if issues.is_empty() {
+        println!("Content OK");
+        ExitCode::SUCCESS
+    } else {
+        eprintln!("{} content issue(s) found", issues.len());
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,123 @@
+use std::time::Duration;
+
+use axum::http::header::{AUTHORIZATION, CONTENT_TYPE};
+use axum::http::{HeaderName, HeaderValue, Method};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+/// Comma-separated list of origins allowed to call the API from a browser,
+/// e.g. `https://chrisdriscol.com,http://localhost:3000`. `*` allows any origin.
+const ALLOWED_ORIGINS_ENV: &str = "CORS_ALLOWED_ORIGINS";
+/// How long browsers may cache a preflight response, in seconds.
+const MAX_AGE_ENV: &str = "CORS_MAX_AGE_SECONDS";
+
+/// Chromium caps preflight caching at two hours, so longer values buy nothing.
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(7200);
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum AllowedOrigins {
+    /// No cross-origin access; only same-origin requests (for example through
+    /// the Vite dev proxy or CloudFront) work.
+    #[default]
+    None,
+    Any,
+    List(Vec<HeaderValue>),
+}
+
+#[derive(Debug, Clone)]
+pub struct CorsConfig {
+    pub allowed_origins: AllowedOrigins,
+    pub max_age: Duration,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: AllowedOrigins::None,
+            max_age: DEFAULT_MAX_AGE,
+        }
+    }
+}
+
+impl CorsConfig {
+    pub fn from_env() -> Result<Self, String> {
+        let allowed_origins = match std::env::var(ALLOWED_ORIGINS_ENV) {
+            Ok(value) => parse_origins(&value)?,
+            Err(_) => AllowedOrigins::None,
+        };
+        let max_age = match std::env::var(MAX_AGE_ENV) {
+            Ok(value) => value
+                .trim()
+                .parse::<u64>()
+                .map(Duration::from_secs)
+                .map_err(|err| format!("invalid {MAX_AGE_ENV} {value:?}: {err}"))?,
+            Err(_) => DEFAULT_MAX_AGE,
+        };
+        Ok(Self {
+            allowed_origins,
+            max_age,
+        })
+    }
+
+    pub fn layer(&self) -> CorsLayer {
+        let allow_origin = match &self.allowed_origins {
+            AllowedOrigins::None => AllowOrigin::list([]),
+            AllowedOrigins::Any => AllowOrigin::any(),
+            AllowedOrigins::List(origins) => AllowOrigin::list(origins.clone()),
+        };
+        CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
+            .allow_headers([
+                CONTENT_TYPE,
+                AUTHORIZATION,
+                HeaderName::from_static("x-origin-secret"),
+            ])
+            .max_age(self.max_age)
+    }
+}
+
+fn parse_origins(value: &str) -> Result<AllowedOrigins, String> {
+    let entries: Vec<&str> = value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .collect();
+    if entries.contains(&"*") {
+        return Ok(AllowedOrigins::Any);
+    }
+    if entries.is_empty() {
+        return Ok(AllowedOrigins::None);
+    }
+    entries
+        .into_iter()
+        .map(|entry| {
+            // Browsers send the origin without a trailing slash.
+            let origin = entry.trim_end_matches('/');
+            if !origin.starts_with("http://") && !origin.starts_with("https://") {
+                return Err(format!("invalid origin in {ALLOWED_ORIGINS_ENV}: {entry:?}"));
+            }
+            HeaderValue::from_str(origin)
+                .map_err(|err| format!("invalid origin in {ALLOWED_ORIGINS_ENV}: {entry:?}: {err}"))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(AllowedOrigins::List)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_origin_lists() {
+        assert_eq!(parse_origins(""), Ok(AllowedOrigins::None));
+        assert_eq!(parse_origins(" * "), Ok(AllowedOrigins::Any));
+        assert_eq!(
+            parse_origins("https://chrisdriscol.com/, http://localhost:3000"),
+            Ok(AllowedOrigins::List(vec![
+                HeaderValue::from_static("https://chrisdriscol.com"),
+                HeaderValue::from_static("http://localhost:3000"),
+            ]))
+        );
+        assert!(parse_origins("chrisdriscol.com").is_err());
+    }
+}
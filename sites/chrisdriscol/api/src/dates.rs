@@ -0,0 +1,346 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use async_graphql::{ComplexObject, Enum, InputObject, SimpleObject};
+use serde::{Deserialize, Serialize};
+
+/// Years a date may name. Month arithmetic stays far from overflowing, and
+/// anything outside is a typo.
+const YEARS: std::ops::RangeInclusive<i32> = 1900..=2200;
+
+const MONTH_NAMES: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June", "July", "August", "September",
+    "October", "November", "December",
+];
+
+#[derive(Enum, Copy, Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Season {
+    Spring,
+    Summer,
+    Fall,
+    Winter,
+}
+
+impl Season {
+    fn label(self) -> &'static str {
+        match self {
+            Season::Spring => "Spring",
+            Season::Summer => "Summer",
+            Season::Fall => "Fall",
+            Season::Winter => "Winter",
+        }
+    }
+
+    /// First and last month (1-based) the season covers. Winter runs into
+    /// February of the following year, so its last month is 14.
+    fn months(self) -> (i32, i32) {
+        match self {
+            Season::Spring => (3, 5),
+            Season::Summer => (6, 8),
+            Season::Fall => (9, 11),
+            Season::Winter => (12, 14),
+        }
+    }
+}
+
+/// A year, optionally narrowed to a season or a month.
+#[derive(SimpleObject, InputObject, Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[graphql(input_name = "PartialDateInput")]
+pub struct PartialDate {
+    pub year: i32,
+    pub season: Option<Season>,
+    /// Calendar month, 1-12.
+    pub month: Option<u32>,
+}
+
+impl PartialDate {
+    /// Months since year 0 of the first month this date covers.
+    pub fn first_month(&self) -> i64 {
+        let month = match (self.month, self.season) {
+            (Some(month), _) => i64::from(month),
+            (None, Some(season)) => i64::from(season.months().0),
+            (None, None) => 1,
+        };
+        i64::from(self.year) * 12 + month - 1
+    }
+
+    /// Months since year 0 of the last month this date covers.
+    pub fn last_month(&self) -> i64 {
+        let month = match (self.month, self.season) {
+            (Some(month), _) => i64::from(month),
+            (None, Some(season)) => i64::from(season.months().1),
+            (None, None) => 12,
+        };
+        i64::from(self.year) * 12 + month - 1
+    }
+
+    pub fn label(&self) -> String {
+        match (self.month, self.season) {
+            (Some(month), _) => {
+                let name = MONTH_NAMES.get(month as usize - 1).unwrap_or(&"?");
+                format!("{name} {}", self.year)
+            }
+            (None, Some(season)) => format!("{} {}", season.label(), self.year),
+            (None, None) => self.year.to_string(),
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if !YEARS.contains(&self.year) {
+            return Err(format!(
+                "year {} is outside {}-{}",
+                self.year,
+                YEARS.start(),
+                YEARS.end()
+            ));
+        }
+        if let Some(month) = self.month {
+            if !(1..=12).contains(&month) {
+                return Err(format!("month {month} is out of range"));
+            }
+            if self.season.is_some() {
+                return Err("month and season are both set".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A span of time such as "Fall 2015 - Present". Without an `end` the range
+/// covers just the `start` period; `isCurrent` ranges run through today.
+#[derive(SimpleObject, InputObject, Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[graphql(complex, input_name = "DateRangeInput")]
+#[serde(rename_all = "camelCase")]
+pub struct DateRange {
+    pub start: PartialDate,
+    pub end: Option<PartialDate>,
+    #[graphql(name = "isCurrent")]
+    #[serde(default)]
+    pub is_current: bool,
+}
+
+#[ComplexObject]
+impl DateRange {
+    /// Whole months covered by the range, counting both endpoints in full.
+    #[graphql(name = "durationMonths")]
+    async fn duration_months_field(&self) -> i32 {
+        self.duration_months()
+    }
+
+    /// Human-readable form, e.g. "Summer 2008 - Summer 2013".
+    async fn display(&self) -> String {
+        self.label()
+    }
+}
+
+impl DateRange {
+    pub fn label(&self) -> String {
+        match (&self.end, self.is_current) {
+            (_, true) => format!("{} - Present", self.start.label()),
+            (Some(end), false) => format!("{} - {}", self.start.label(), end.label()),
+            (None, false) => self.start.label(),
+        }
+    }
+
+    /// The text of the `date` and `duration` strings clients read before
+    /// `DateRange` existed. It matches `label`, except that a current range
+    /// from a bare year has no spaces, e.g. "2015-Present", as the content
+    /// used to say.
+    pub fn legacy_label(&self) -> String {
+        let bare_year = self.start.month.is_none() && self.start.season.is_none();
+        if self.is_current && bare_year {
+            return format!("{}-Present", self.start.label());
+        }
+        self.label()
+    }
+
+    /// Months since year 0 of the last month the range covers.
+    pub fn last_month(&self) -> i64 {
+        self.last_month_at(current_month())
+    }
+
+    pub fn duration_months(&self) -> i32 {
+        self.duration_months_at(current_month())
+    }
+
+    fn last_month_at(&self, now: i64) -> i64 {
+        match (&self.end, self.is_current) {
+            (_, true) => now,
+            (Some(end), false) => end.last_month(),
+            (None, false) => self.start.last_month(),
+        }
+    }
+
+    fn duration_months_at(&self, now: i64) -> i32 {
+        let months = (self.last_month_at(now) - self.start.first_month() + 1).max(0);
+        i32::try_from(months).unwrap_or(i32::MAX)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.start.validate()?;
+        if let Some(end) = &self.end {
+            end.validate()?;
+            if self.is_current {
+                return Err("range is current but also has an end".to_string());
+            }
+            if end.last_month() < self.start.first_month() {
+                return Err(format!("{} ends before it starts", self.label()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Months since year 0 of the current UTC month.
+fn current_month() -> i64 {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() / 86_400)
+        .unwrap_or_default() as i64;
+    let (year, month) = year_month_from_days(days);
+    i64::from(year) * 12 + i64::from(month) - 1
+}
+
+/// Converts days since 1970-01-01 to a (year, month) pair in the proleptic
+/// Gregorian calendar.
+fn year_month_from_days(days: i64) -> (i32, i32) {
+    let (year, month, _) = civil_from_days(days);
+    (year as i32, month as i32)
+}
+
+/// Converts days since 1970-01-01 to a (year, month, day) date in the
+/// proleptic Gregorian calendar (Howard Hinnant's `civil_from_days`).
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Formats seconds since the Unix epoch as RFC 3339 in UTC, e.g.
+/// `2024-05-01T09:30:00Z`.
+pub fn rfc3339(secs: u64) -> String {
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let rem = secs % 86_400;
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, season: Option<Season>) -> PartialDate {
+        PartialDate {
+            year,
+            season,
+            month: None,
+        }
+    }
+
+    #[test]
+    fn formats_legacy_labels() {
+        let range = DateRange {
+            start: date(2004, Some(Season::Fall)),
+            end: Some(date(2008, Some(Season::Spring))),
+            is_current: false,
+        };
+        assert_eq!(range.label(), "Fall 2004 - Spring 2008");
+
+        let current = DateRange {
+            start: date(2015, None),
+            end: None,
+            is_current: true,
+        };
+        assert_eq!(current.label(), "2015 - Present");
+        assert_eq!(current.legacy_label(), "2015-Present");
+        let current_season = DateRange {
+            start: date(2015, Some(Season::Fall)),
+            ..current
+        };
+        assert_eq!(current_season.legacy_label(), "Fall 2015 - Present");
+
+        let single = DateRange {
+            start: PartialDate {
+                year: 2007,
+                season: None,
+                month: Some(6),
+            },
+            end: None,
+            is_current: false,
+        };
+        assert_eq!(single.label(), "June 2007");
+    }
+
+    #[test]
+    fn counts_months_inclusively() {
+        let years = DateRange {
+            start: date(2001, None),
+            end: Some(date(2002, None)),
+            is_current: false,
+        };
+        assert_eq!(years.duration_months(), 24);
+
+        let season = DateRange {
+            start: date(2007, Some(Season::Summer)),
+            end: None,
+            is_current: false,
+        };
+        assert_eq!(season.duration_months(), 3);
+
+        let current = DateRange {
+            start: date(2015, Some(Season::Fall)),
+            end: None,
+            is_current: true,
+        };
+        let december_2016 = 2016 * 12 + 11;
+        assert_eq!(current.duration_months_at(december_2016), 16);
+    }
+
+    #[test]
+    fn validates_ranges() {
+        let backwards = DateRange {
+            start: date(2010, None),
+            end: Some(date(2009, None)),
+            is_current: false,
+        };
+        assert!(backwards.validate().is_err());
+
+        let bad_month = DateRange {
+            start: PartialDate {
+                year: 2010,
+                season: None,
+                month: Some(13),
+            },
+            end: None,
+            is_current: false,
+        };
+        assert!(bad_month.validate().is_err());
+
+        let far_future = DateRange {
+            start: date(2_000_000_000, None),
+            end: None,
+            is_current: false,
+        };
+        assert!(far_future.validate().is_err());
+        assert_eq!(far_future.start.last_month(), 2_000_000_000 * 12 + 11);
+    }
+
+    #[test]
+    fn converts_days_to_year_month() {
+        assert_eq!(year_month_from_days(0), (1970, 1));
+        assert_eq!(year_month_from_days(19_782), (2024, 2));
+        assert_eq!(year_month_from_days(-1), (1969, 12));
+        assert_eq!(rfc3339(1_709_210_096), "2024-02-29T12:34:56Z");
+    }
+}
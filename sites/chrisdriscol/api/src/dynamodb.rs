@@ -0,0 +1,249 @@
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use aws_config::{BehaviorVersion, SdkConfig};
+use aws_credential_types::provider::ProvideCredentials;
+use aws_sigv4::http_request::{sign, SignableBody, SignableRequest, SigningSettings};
+use aws_sigv4::sign::v4::SigningParams;
+use serde_json::{json, Map, Value};
+use tokio::sync::OnceCell;
+
+/// A DynamoDB item: attribute names mapped to typed values such as
+/// `{"S": "abc"}`.
+pub type Item = Map<String, Value>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DynamoError {
+    /// The request's `ConditionExpression` did not hold.
+    ConditionFailed,
+    Failed(String),
+}
+
+impl From<DynamoError> for String {
+    fn from(err: DynamoError) -> Self {
+        match err {
+            DynamoError::ConditionFailed => "conditional request failed".to_string(),
+            DynamoError::Failed(message) => message,
+        }
+    }
+}
+
+/// One DynamoDB table shared by every instance, for the stores that must
+/// outlive a Lambda instance. Requests go to the JSON API signed with the
+/// default AWS credential chain; like the SES transport, AWS config is
+/// loaded on first use so building the schema never waits on it.
+/// `AWS_ENDPOINT_URL` points it at DynamoDB Local.
+pub struct Table {
+    name: String,
+    http: reqwest::Client,
+    sdk_config: OnceCell<SdkConfig>,
+}
+
+impl Table {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            http: reqwest::Client::new(),
+            sdk_config: OnceCell::new(),
+        }
+    }
+
+    /// Reads the table name from `name`, which must be set.
+    pub fn from_env(name: &str) -> Result<Self, String> {
+        match std::env::var(name) {
+            Ok(table) if !table.is_empty() => Ok(Self::new(table)),
+            _ => Err(format!("{name} must be set")),
+        }
+    }
+
+    /// Runs `action`, e.g. `PutItem`, with `request` as its input. The
+    /// table name is filled in.
+    pub async fn call(&self, action: &str, mut request: Value) -> Result<Value, DynamoError> {
+        let failed = |err: String| DynamoError::Failed(format!("dynamodb {action} on {} failed: {err}", self.name));
+        request["TableName"] = json!(self.name);
+        let body = serde_json::to_vec(&request).map_err(|err| failed(err.to_string()))?;
+
+        let sdk_config = self
+            .sdk_config
+            .get_or_init(|| aws_config::load_defaults(BehaviorVersion::latest()))
+            .await;
+        let region = sdk_config
+            .region()
+            .ok_or_else(|| failed("no AWS region configured".to_string()))?
+            .to_string();
+        let endpoint = sdk_config
+            .endpoint_url()
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("https://dynamodb.{region}.amazonaws.com/"));
+        let identity = sdk_config
+            .credentials_provider()
+            .ok_or_else(|| failed("no AWS credentials configured".to_string()))?
+            .provide_credentials()
+            .await
+            .map_err(|err| failed(err.to_string()))?
+            .into();
+
+        let target = format!("DynamoDB_20120810.{action}");
+        let headers = [
+            ("content-type", "application/x-amz-json-1.0"),
+            ("x-amz-target", target.as_str()),
+        ];
+        let params = SigningParams::builder()
+            .identity(&identity)
+            .region(&region)
+            .name("dynamodb")
+            .time(SystemTime::now())
+            .settings(SigningSettings::default())
+            .build()
+            .map_err(|err| failed(err.to_string()))?
+            .into();
+        let signable = SignableRequest::new("POST", endpoint.as_str(), headers.into_iter(), SignableBody::Bytes(&body))
+            .map_err(|err| failed(err.to_string()))?;
+        let (signature, _) = sign(signable, &params).map_err(|err| failed(err.to_string()))?.into_parts();
+
+        let mut builder = self.http.post(&endpoint);
+        for (name, value) in headers.into_iter().chain(signature.headers()) {
+            builder = builder.header(name, value);
+        }
+        let response = builder.body(body).send().await.map_err(|err| failed(err.to_string()))?;
+        let status = response.status();
+        let payload: Value = response.json().await.map_err(|err| failed(err.to_string()))?;
+        if status.is_success() {
+            return Ok(payload);
+        }
+        // Errors name their type as "com.amazonaws.dynamodb.v20120810#Kind".
+        let kind = payload["__type"].as_str().unwrap_or_default();
+        if kind.ends_with("#ConditionalCheckFailedException") {
+            return Err(DynamoError::ConditionFailed);
+        }
+        let message = payload["message"].as_str().or(payload["Message"].as_str()).unwrap_or_default();
+        Err(failed(format!("{status} {kind} {message}")))
+    }
+
+    /// Every item a `Scan` or `Query` matches, following pagination.
+    pub async fn collect(&self, action: &str, request: Value) -> Result<Vec<Item>, DynamoError> {
+        let mut items = Vec::new();
+        let mut request = request;
+        loop {
+            let page = self.call(action, request.clone()).await?;
+            items.extend(self::items(&page, "Items")?);
+            match page.get("LastEvaluatedKey") {
+                Some(key) => request["ExclusiveStartKey"] = key.clone(),
+                None => return Ok(items),
+            }
+        }
+    }
+}
+
+/// The items in a response field such as `Items`.
+pub fn items(response: &Value, field: &str) -> Result<Vec<Item>, DynamoError> {
+    let invalid = || DynamoError::Failed(format!("dynamodb response has no {field} list"));
+    response
+        .get(field)
+        .and_then(Value::as_array)
+        .ok_or_else(invalid)?
+        .iter()
+        .map(|item| item.as_object().cloned().ok_or_else(invalid))
+        .collect()
+}
+
+/// The item in a response field such as `Item` or `Attributes`, if any.
+pub fn item(response: &Value, field: &str) -> Option<Item> {
+    response.get(field).and_then(Value::as_object).cloned()
+}
+
+pub fn string(value: &str) -> Value {
+    json!({ "S": value })
+}
+
+/// Missing values are stored as `NULL` so every item has the same
+/// attributes.
+pub fn optional_string(value: Option<&str>) -> Value {
+    value.map_or_else(|| json!({ "NULL": true }), string)
+}
+
+pub fn number(value: impl ToString) -> Value {
+    json!({ "N": value.to_string() })
+}
+
+pub fn boolean(value: bool) -> Value {
+    json!({ "BOOL": value })
+}
+
+pub fn strings(values: &[String]) -> Value {
+    json!({ "L": values.iter().map(|value| string(value)).collect::<Vec<_>>() })
+}
+
+fn attribute<'a>(item: &'a Item, name: &str, kind: &str) -> Result<Option<&'a Value>, String> {
+    match item.get(name) {
+        None => Ok(None),
+        Some(value) if value.get("NULL").is_some() => Ok(None),
+        Some(value) => value
+            .get(kind)
+            .map(Some)
+            .ok_or_else(|| format!("attribute {name} is not of type {kind}")),
+    }
+}
+
+fn required<T>(value: Option<T>, name: &str) -> Result<T, String> {
+    value.ok_or_else(|| format!("attribute {name} is missing"))
+}
+
+pub fn get_string(item: &Item, name: &str) -> Result<String, String> {
+    required(get_optional_string(item, name)?, name)
+}
+
+pub fn get_optional_string(item: &Item, name: &str) -> Result<Option<String>, String> {
+    Ok(attribute(item, name, "S")?.and_then(Value::as_str).map(str::to_string))
+}
+
+pub fn get_number<T: FromStr>(item: &Item, name: &str) -> Result<T, String> {
+    let value = required(attribute(item, name, "N")?.and_then(Value::as_str), name)?;
+    value
+        .parse()
+        .map_err(|_| format!("attribute {name} is not a valid number: {value:?}"))
+}
+
+pub fn get_boolean(item: &Item, name: &str) -> Result<bool, String> {
+    required(attribute(item, name, "BOOL")?.and_then(Value::as_bool), name)
+}
+
+pub fn get_strings(item: &Item, name: &str) -> Result<Vec<String>, String> {
+    let values = required(attribute(item, name, "L")?.and_then(Value::as_array), name)?;
+    values
+        .iter()
+        .map(|value| {
+            value
+                .get("S")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .ok_or_else(|| format!("attribute {name} holds a non-string"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_back_typed_attributes() {
+        let item = json!({
+            "id": string("abc"),
+            "error": optional_string(None),
+            "attempts": number(3),
+            "read": boolean(true),
+            "signals": strings(&["links".to_string()]),
+        });
+        let item = item.as_object().unwrap();
+
+        assert_eq!(get_string(item, "id"), Ok("abc".to_string()));
+        assert_eq!(get_optional_string(item, "error"), Ok(None));
+        assert_eq!(get_optional_string(item, "missing"), Ok(None));
+        assert_eq!(get_number::<u32>(item, "attempts"), Ok(3));
+        assert_eq!(get_boolean(item, "read"), Ok(true));
+        assert_eq!(get_strings(item, "signals"), Ok(vec!["links".to_string()]));
+        assert!(get_string(item, "attempts").is_err());
+        assert!(get_number::<u32>(item, "missing").is_err());
+    }
+}
@@ -1,65 +1,340 @@
-use aws_config::BehaviorVersion;
-use aws_sdk_sesv2::types::{Body, Content, Destination, EmailContent, Message};
-use aws_sdk_sesv2::Client;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use async_graphql::async_trait;
+use lettre::message::{Mailbox, Message, MultiPart};
+use serde::{Deserialize, Serialize};
 
 use crate::types::ContactMeInput;
 
-#[derive(Debug)]
-pub struct EmailConfig {
+mod file;
+mod memory;
+mod ses;
+mod smtp;
+mod template;
+
+pub use file::MaildirTransport;
+pub use memory::MemoryTransport;
+pub use ses::SesTransport;
+pub use smtp::SmtpTransport;
+pub use template::{format_utc, ContactMeta, EmailTemplates, Template};
+
+/// A message ready to hand to a transport.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Email {
     pub from: String,
     pub to: String,
+    pub reply_to: Option<String>,
+    pub subject: String,
+    pub text: String,
+    /// Sent as an alternative to `text` when present.
+    pub html: Option<String>,
+}
+
+impl Email {
+    /// Renders the message as RFC 5322 for transports that speak MIME.
+    pub fn to_message(&self) -> Result<Message, String> {
+        let mailbox = |address: &str| {
+            address
+                .parse::<Mailbox>()
+                .map_err(|err| format!("invalid address {address:?}: {err}"))
+        };
+        let mut builder = Message::builder()
+            .from(mailbox(&self.from)?)
+            .to(mailbox(&self.to)?)
+            .subject(&self.subject);
+        if let Some(reply_to) = &self.reply_to {
+            builder = builder.reply_to(mailbox(reply_to)?);
+        }
+        match &self.html {
+            Some(html) => builder.multipart(MultiPart::alternative_plain_html(self.text.clone(), html.clone())),
+            None => builder.body(self.text.clone()),
+        }
+        .map_err(|err| format!("failed to build message: {err}"))
+    }
+}
+
+/// Delivers outgoing email. The schema holds one behind a [`Mailer`].
+#[async_trait::async_trait]
+pub trait EmailTransport: Send + Sync {
+    async fn send(&self, email: &Email) -> Result<(), String>;
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EmailConfig {
+    pub from: Option<String>,
+    pub to: Option<String>,
 }
 
 impl EmailConfig {
+    /// Reads `EMAIL_FROM` and `EMAIL_TO`, falling back to the older `SES_FROM`
+    /// and `SES_TO`. Missing addresses only fail when a message is built.
+    pub fn from_env() -> Self {
+        let var = |name: &str, legacy: &str| {
+            std::env::var(name).or_else(|_| std::env::var(legacy)).ok()
+        };
+        Self {
+            from: var("EMAIL_FROM", "SES_FROM"),
+            to: var("EMAIL_TO", "SES_TO"),
+        }
+    }
+}
+
+const DEFAULT_AUTO_REPLY_SUBJECT: &str = "Thanks for your message";
+
+/// Confirmation emailed back to people who use the contact form.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AutoReplyConfig {
+    pub enabled: bool,
+    /// Fixed rather than echoing the sender's subject, so the reply cannot
+    /// carry a stranger's headline to whatever address they typed in.
+    pub subject: String,
+}
+
+impl Default for AutoReplyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            subject: DEFAULT_AUTO_REPLY_SUBJECT.to_string(),
+        }
+    }
+}
+
+impl AutoReplyConfig {
+    /// Reads `CONTACT_AUTO_REPLY` (`on` or `off`, the default) and
+    /// `CONTACT_AUTO_REPLY_SUBJECT`.
     pub fn from_env() -> Result<Self, String> {
-        let from = std::env::var("SES_FROM").map_err(|_| "SES_FROM is required")?;
-        let to = std::env::var("SES_TO").map_err(|_| "SES_TO is required")?;
-        Ok(Self { from, to })
+        let enabled = match std::env::var("CONTACT_AUTO_REPLY").as_deref() {
+            Err(_) | Ok("off") => false,
+            Ok("on") => true,
+            Ok(other) => return Err(format!("CONTACT_AUTO_REPLY must be on or off, got {other:?}")),
+        };
+        Ok(Self {
+            enabled,
+            subject: std::env::var("CONTACT_AUTO_REPLY_SUBJECT")
+                .unwrap_or_else(|_| DEFAULT_AUTO_REPLY_SUBJECT.to_string()),
+        })
+    }
+}
+
+/// Sends contact-form email through the configured transport.
+#[derive(Clone)]
+pub struct Mailer {
+    pub transport: Arc<dyn EmailTransport>,
+    pub config: EmailConfig,
+    pub templates: EmailTemplates,
+    pub auto_reply: AutoReplyConfig,
+}
+
+impl Default for Mailer {
+    fn default() -> Self {
+        Self {
+            transport: Arc::new(MemoryTransport::default()),
+            config: EmailConfig::default(),
+            templates: EmailTemplates::default(),
+            auto_reply: AutoReplyConfig::default(),
+        }
     }
 }
 
-pub async fn send_contact_email(input: &ContactMeInput) -> Result<(), String> {
-    let config = EmailConfig::from_env()?;
-    let sdk_config = aws_config::load_defaults(BehaviorVersion::latest()).await;
-    let client = Client::new(&sdk_config);
-
-    let subject = Content::builder()
-        .data(input.subject.clone())
-        .charset("UTF-8")
-        .build()
-        .map_err(|err| format!("failed to build subject: {err}"))?;
-
-    let body_text = format!("{}\n\nFrom: {}\nEmail: {}", input.body, input.name, input.from);
-    let body = Body::builder()
-        .text(
-            Content::builder()
-                .data(body_text)
-                .charset("UTF-8")
-                .build()
-                .map_err(|err| format!("failed to build body: {err}"))?,
-        )
-        .build();
-
-    let message = Message::builder()
-        .subject(subject)
-        .body(body)
-        .build();
-
-    let destination = Destination::builder()
-        .to_addresses(config.to)
-        .build();
-
-    let email = EmailContent::builder().simple(message).build();
-
-    client
-        .send_email()
-        .from_email_address(config.from)
-        .destination(destination)
-        .reply_to_addresses(input.from.clone())
-        .content(email)
-        .send()
-        .await
-        .map_err(|err| format!("ses send failed: {err}"))?;
-
-    Ok(())
+impl Mailer {
+    /// Picks the transport named by `EMAIL_TRANSPORT`: `ses` (default),
+    /// `smtp`, `file` or `memory`, and loads templates from
+    /// `EMAIL_TEMPLATE_DIR`. See [`AutoReplyConfig::from_env`] for the
+    /// auto-reply.
+    pub fn from_env() -> Result<Self, String> {
+        let transport: Arc<dyn EmailTransport> = match std::env::var("EMAIL_TRANSPORT").as_deref() {
+            Err(_) | Ok("ses") => Arc::new(SesTransport::default()),
+            Ok("smtp") => Arc::new(SmtpTransport::from_env()?),
+            Ok("file") => Arc::new(MaildirTransport::from_env()),
+            Ok("memory") => Arc::new(MemoryTransport::default()),
+            Ok(other) => return Err(format!("unknown EMAIL_TRANSPORT {other:?}")),
+        };
+        Ok(Self {
+            transport,
+            config: EmailConfig::from_env(),
+            templates: EmailTemplates::from_env()?,
+            auto_reply: AutoReplyConfig::from_env()?,
+        })
+    }
+
+    pub fn contact_email(&self, input: &ContactMeInput, meta: &ContactMeta) -> Result<Email, String> {
+        let from = self.config.from.clone().ok_or("EMAIL_FROM is required")?;
+        let to = self.config.to.clone().ok_or("EMAIL_TO is required")?;
+        let meta = meta.values();
+        let mut values = vec![
+            ("name", input.name.as_str()),
+            ("email", input.from.as_str()),
+            ("subject", input.subject.as_str()),
+            ("body", input.body.as_str()),
+        ];
+        values.extend(meta.iter().map(|(name, value)| (*name, value.as_str())));
+        let (text, html) = self.templates.contact.render(&values, &[]);
+        Ok(Email {
+            from,
+            to,
+            reply_to: Some(input.from.clone()),
+            subject: input.subject.clone(),
+            text,
+            html: Some(html),
+        })
+    }
+
+    /// The confirmation for `input`'s sender, copying their message when
+    /// they set `sendCopy`. Replies go to `EMAIL_TO`. Callers must only pass
+    /// input that has already been validated, checked for spam and
+    /// rate-limited per recipient.
+    pub fn auto_reply_email(&self, input: &ContactMeInput, received_at: SystemTime) -> Result<Email, String> {
+        let from = self.config.from.clone().ok_or("EMAIL_FROM is required")?;
+        let received_at = format_utc(received_at);
+        let values = [
+            ("name", input.name.as_str()),
+            ("email", input.from.as_str()),
+            ("subject", input.subject.as_str()),
+            ("body", input.body.as_str()),
+            ("received_at", received_at.as_str()),
+        ];
+        let sections: &[&str] = if input.send_copy.unwrap_or(false) { &["copy"] } else { &[] };
+        let (text, html) = self.templates.auto_reply.render(&values, sections);
+        Ok(Email {
+            from,
+            to: input.from.clone(),
+            reply_to: self.config.to.clone(),
+            subject: self.auto_reply.subject.clone(),
+            text,
+            html: Some(html),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input() -> ContactMeInput {
+        ContactMeInput {
+            from: "visitor@example.com".to_string(),
+            name: "Visitor".to_string(),
+            subject: "Hello".to_string(),
+            body: "Nice site.".to_string(),
+            verification_token: None,
+            website: None,
+            form_token: None,
+            send_copy: None,
+        }
+    }
+
+    fn meta() -> ContactMeta {
+        ContactMeta {
+            received_at: std::time::UNIX_EPOCH,
+            user_agent: None,
+            referer: None,
+        }
+    }
+
+    #[test]
+    fn builds_contact_email() {
+        let mailer = Mailer {
+            config: EmailConfig {
+                from: Some("site@example.com".to_string()),
+                to: Some("me@example.com".to_string()),
+            },
+            ..Mailer::default()
+        };
+        let meta = ContactMeta {
+            user_agent: Some("Mozilla/5.0".to_string()),
+            ..meta()
+        };
+        let email = mailer.contact_email(&input(), &meta).unwrap();
+
+        assert_eq!(email.to, "me@example.com");
+        assert_eq!(email.reply_to.as_deref(), Some("visitor@example.com"));
+        assert!(email.text.starts_with("Nice site.\n\n--\nFrom: Visitor\nEmail: visitor@example.com\n"));
+        assert!(email.text.contains("Page: unknown\nBrowser: Mozilla/5.0"), "{}", email.text);
+    }
+
+    #[test]
+    fn escapes_user_input_in_the_html_part() {
+        let mailer = Mailer {
+            config: EmailConfig {
+                from: Some("site@example.com".to_string()),
+                to: Some("me@example.com".to_string()),
+            },
+            ..Mailer::default()
+        };
+        let input = ContactMeInput {
+            name: "<script>alert(1)</script>".to_string(),
+            body: "Line one\nLine <two>".to_string(),
+            ..input()
+        };
+        let meta = ContactMeta {
+            referer: Some("https://example.com/?a=1&b=2".to_string()),
+            ..meta()
+        };
+        let html = mailer.contact_email(&input, &meta).unwrap().html.unwrap();
+        assert!(!html.contains("<script>"), "{html}");
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;</td>"), "{html}");
+        assert!(html.contains("Line one<br>\nLine &lt;two&gt;"), "{html}");
+        assert!(html.contains("Received 1970-01-01 00:00:00 UTC"), "{html}");
+        assert!(html.contains("https://example.com/?a=1&amp;b=2"), "{html}");
+    }
+
+    #[test]
+    fn auto_reply_copies_the_message_only_on_request() {
+        let mailer = Mailer {
+            config: EmailConfig {
+                from: Some("site@example.com".to_string()),
+                to: Some("me@example.com".to_string()),
+            },
+            ..Mailer::default()
+        };
+        let input = ContactMeInput {
+            subject: "<Buy now>".to_string(),
+            ..input()
+        };
+        let reply = mailer.auto_reply_email(&input, std::time::UNIX_EPOCH).unwrap();
+        assert_eq!(reply.to, "visitor@example.com");
+        assert_eq!(reply.reply_to.as_deref(), Some("me@example.com"));
+        assert_eq!(reply.subject, "Thanks for your message");
+        assert!(reply.text.starts_with("Hi Visitor,"), "{}", reply.text);
+        assert!(!reply.text.contains("Nice site."), "{}", reply.text);
+
+        let copy = ContactMeInput {
+            send_copy: Some(true),
+            ..input
+        };
+        let reply = mailer.auto_reply_email(&copy, std::time::UNIX_EPOCH).unwrap();
+        assert!(reply.text.contains("Subject: <Buy now>\n\nNice site.\n"), "{}", reply.text);
+        assert!(reply.html.unwrap().contains("&lt;Buy now&gt;"));
+    }
+
+    #[test]
+    fn missing_addresses_fail_when_building_email() {
+        let err = Mailer::default().contact_email(&input(), &meta()).unwrap_err();
+        assert_eq!(err, "EMAIL_FROM is required");
+    }
+
+    #[test]
+    fn renders_mime_message() {
+        let email = Mailer {
+            config: EmailConfig {
+                from: Some("Site <site@example.com>".to_string()),
+                to: Some("me@example.com".to_string()),
+            },
+            ..Mailer::default()
+        }
+        .contact_email(&input(), &meta())
+        .unwrap();
+        let formatted = String::from_utf8(email.to_message().unwrap().formatted()).unwrap();
+        assert!(formatted.contains("Reply-To: visitor@example.com"), "{formatted}");
+        assert!(formatted.contains("Subject: Hello"), "{formatted}");
+        assert!(formatted.contains("Content-Type: multipart/alternative"), "{formatted}");
+        assert!(formatted.contains("Content-Type: text/html"), "{formatted}");
+
+        let invalid = Email {
+            to: "not an address".to_string(),
+            ..email
+        };
+        assert!(invalid.to_message().is_err());
+    }
 }
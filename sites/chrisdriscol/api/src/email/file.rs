@@ -0,0 +1,96 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use async_graphql::async_trait;
+
+use super::{Email, EmailTransport};
+
+const DEFAULT_DIR: &str = "mail";
+
+/// Writes each message into a Maildir (`tmp/`, `new/`, `cur/`) so local
+/// mail clients can open the output directly.
+pub struct MaildirTransport {
+    dir: PathBuf,
+    counter: AtomicU64,
+}
+
+impl MaildirTransport {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            counter: AtomicU64::new(0),
+        }
+    }
+
+    /// Uses `EMAIL_DIR`, defaulting to `mail/` in the working directory.
+    pub fn from_env() -> Self {
+        Self::new(std::env::var_os("EMAIL_DIR").map(PathBuf::from).unwrap_or_else(|| DEFAULT_DIR.into()))
+    }
+
+    /// Unique Maildir file name: `<seconds>.<micros>_<pid>_<counter>.<host>`.
+    fn file_name(&self) -> String {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let count = self.counter.fetch_add(1, Ordering::Relaxed);
+        format!(
+            "{}.M{}P{}Q{}.chris-driscol-api",
+            now.as_secs(),
+            now.subsec_micros(),
+            std::process::id(),
+            count
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailTransport for MaildirTransport {
+    async fn send(&self, email: &Email) -> Result<(), String> {
+        let message = email.to_message()?.formatted();
+        for sub in ["tmp", "new", "cur"] {
+            let path = self.dir.join(sub);
+            tokio::fs::create_dir_all(&path)
+                .await
+                .map_err(|err| format!("failed to create {}: {err}", path.display()))?;
+        }
+        // Write to tmp/ and rename into new/ so readers never see partial files.
+        let name = self.file_name();
+        let tmp = self.dir.join("tmp").join(&name);
+        let new = self.dir.join("new").join(&name);
+        tokio::fs::write(&tmp, message)
+            .await
+            .map_err(|err| format!("failed to write {}: {err}", tmp.display()))?;
+        tokio::fs::rename(&tmp, &new)
+            .await
+            .map_err(|err| format!("failed to move {} into new/: {err}", tmp.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn writes_messages_into_new() {
+        let dir = std::env::temp_dir().join(format!("chris-maildir-{}", std::process::id()));
+        let transport = MaildirTransport::new(&dir);
+        let email = Email {
+            from: "site@example.com".to_string(),
+            to: "me@example.com".to_string(),
+            reply_to: None,
+            subject: "Hello".to_string(),
+            text: "Nice site.".to_string(),
+            html: None,
+        };
+        transport.send(&email).await.unwrap();
+        transport.send(&email).await.unwrap();
+
+        let entries: Vec<_> = std::fs::read_dir(dir.join("new")).unwrap().collect();
+        let tmp_entries = std::fs::read_dir(dir.join("tmp")).unwrap().count();
+        let first = std::fs::read_to_string(entries[0].as_ref().unwrap().path()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(tmp_entries, 0);
+        assert!(first.contains("Subject: Hello"), "{first}");
+    }
+}
@@ -0,0 +1,27 @@
+use std::sync::Mutex;
+
+use async_graphql::async_trait;
+
+use super::{Email, EmailTransport};
+
+/// Keeps sent messages in memory instead of delivering them. Used by tests
+/// and for running the API without any mail setup.
+#[derive(Default)]
+pub struct MemoryTransport {
+    sent: Mutex<Vec<Email>>,
+}
+
+impl MemoryTransport {
+    /// Messages sent so far, oldest first.
+    pub fn sent(&self) -> Vec<Email> {
+        self.sent.lock().unwrap().clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailTransport for MemoryTransport {
+    async fn send(&self, email: &Email) -> Result<(), String> {
+        self.sent.lock().unwrap().push(email.clone());
+        Ok(())
+    }
+}
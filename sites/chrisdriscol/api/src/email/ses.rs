@@ -0,0 +1,62 @@
+use async_graphql::async_trait;
+use aws_config::BehaviorVersion;
+use aws_sdk_sesv2::types::{Body, Content, Destination, EmailContent, Message};
+use aws_sdk_sesv2::Client;
+use tokio::sync::OnceCell;
+
+use super::{Email, EmailTransport};
+
+/// Sends through Amazon SES v2 using the default AWS credential chain. The
+/// client is created on first use so startup never waits on AWS config.
+#[derive(Default)]
+pub struct SesTransport {
+    client: OnceCell<Client>,
+}
+
+impl SesTransport {
+    async fn client(&self) -> &Client {
+        self.client
+            .get_or_init(|| async {
+                let sdk_config = aws_config::load_defaults(BehaviorVersion::latest()).await;
+                Client::new(&sdk_config)
+            })
+            .await
+    }
+}
+
+fn content(data: &str, what: &str) -> Result<Content, String> {
+    Content::builder()
+        .data(data)
+        .charset("UTF-8")
+        .build()
+        .map_err(|err| format!("failed to build {what}: {err}"))
+}
+
+#[async_trait::async_trait]
+impl EmailTransport for SesTransport {
+    async fn send(&self, email: &Email) -> Result<(), String> {
+        let mut body = Body::builder().text(content(&email.text, "body")?);
+        if let Some(html) = &email.html {
+            body = body.html(content(html, "HTML body")?);
+        }
+        let message = Message::builder()
+            .subject(content(&email.subject, "subject")?)
+            .body(body.build())
+            .build();
+
+        let destination = Destination::builder().to_addresses(&email.to).build();
+
+        self.client()
+            .await
+            .send_email()
+            .from_email_address(&email.from)
+            .destination(destination)
+            .set_reply_to_addresses(email.reply_to.clone().map(|reply_to| vec![reply_to]))
+            .content(EmailContent::builder().simple(message).build())
+            .send()
+            .await
+            .map_err(|err| format!("ses send failed: {err}"))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,287 @@
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::client::ClientInfo;
+use crate::dates;
+
+const CONTACT_TEXT: &str = include_str!("templates/contact.txt");
+const CONTACT_HTML: &str = include_str!("templates/contact.html");
+const AUTO_REPLY_TEXT: &str = include_str!("templates/auto_reply.txt");
+const AUTO_REPLY_HTML: &str = include_str!("templates/auto_reply.html");
+
+/// Placeholders every contact template may use, as `{{ name }}`.
+pub const CONTACT_FIELDS: &[&str] = &[
+    "name",
+    "email",
+    "subject",
+    "body",
+    "received_at",
+    "user_agent",
+    "referer",
+];
+
+/// Placeholders and sections the auto-reply templates may use. `copy` is a
+/// section, `{{#copy}}...{{/copy}}`, kept only when the sender asked for a
+/// copy of their message.
+pub const AUTO_REPLY_FIELDS: &[&str] = &["name", "email", "subject", "body", "received_at", "copy"];
+
+/// Shown for metadata the request did not carry.
+const UNKNOWN: &str = "unknown";
+
+/// Text and HTML bodies for one kind of email. The built-in templates can be
+/// replaced from files, see [`EmailTemplates::from_dir`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    pub text: String,
+    pub html: String,
+}
+
+impl Template {
+    /// Fills in both parts, keeping only the `sections` listed. Values are
+    /// HTML-escaped in the HTML part, where line breaks also become `<br>`.
+    pub fn render(&self, values: &[(&str, &str)], sections: &[&str]) -> (String, String) {
+        (
+            render(&self.text, values, sections, str::to_string),
+            render(&self.html, values, sections, escape_html),
+        )
+    }
+}
+
+/// All templates the mailer renders.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmailTemplates {
+    pub contact: Template,
+    pub auto_reply: Template,
+}
+
+impl Default for EmailTemplates {
+    fn default() -> Self {
+        Self {
+            contact: Template {
+                text: CONTACT_TEXT.to_string(),
+                html: CONTACT_HTML.to_string(),
+            },
+            auto_reply: Template {
+                text: AUTO_REPLY_TEXT.to_string(),
+                html: AUTO_REPLY_HTML.to_string(),
+            },
+        }
+    }
+}
+
+impl EmailTemplates {
+    /// Reads `EMAIL_TEMPLATE_DIR` when set.
+    pub fn from_env() -> Result<Self, String> {
+        match std::env::var_os("EMAIL_TEMPLATE_DIR") {
+            Some(dir) => Self::from_dir(Path::new(&dir)),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Overrides the built-in templates with `contact.txt`, `contact.html`,
+    /// `auto_reply.txt` and `auto_reply.html` from `dir`; missing files keep
+    /// the built-in ones. Unknown placeholders are rejected so typos fail at
+    /// startup.
+    pub fn from_dir(dir: &Path) -> Result<Self, String> {
+        let mut templates = Self::default();
+        load(dir, "contact.txt", CONTACT_FIELDS, &mut templates.contact.text)?;
+        load(dir, "contact.html", CONTACT_FIELDS, &mut templates.contact.html)?;
+        load(dir, "auto_reply.txt", AUTO_REPLY_FIELDS, &mut templates.auto_reply.text)?;
+        load(dir, "auto_reply.html", AUTO_REPLY_FIELDS, &mut templates.auto_reply.html)?;
+        Ok(templates)
+    }
+}
+
+fn load(dir: &Path, name: &str, fields: &[&str], template: &mut String) -> Result<(), String> {
+    let path = dir.join(name);
+    let source = match std::fs::read_to_string(&path) {
+        Ok(source) => source,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(format!("failed to read {}: {err}", path.display())),
+    };
+    if let Some(unknown) = placeholders(&source).find(|key| !fields.contains(key)) {
+        return Err(format!("{}: unknown placeholder {{{{ {unknown} }}}}", path.display()));
+    }
+    *template = source;
+    Ok(())
+}
+
+enum Piece<'a> {
+    Text(&'a str),
+    /// A `{{ ... }}` tag: its source and trimmed key.
+    Tag(&'a str, &'a str),
+}
+
+/// Splits `template` into text and tags. An unclosed `{{` is left as text.
+fn pieces(template: &str) -> Vec<Piece<'_>> {
+    let mut pieces = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start..].find("}}") else {
+            break;
+        };
+        pieces.push(Piece::Text(&rest[..start]));
+        let tag = &rest[start..start + len + 2];
+        pieces.push(Piece::Tag(tag, tag[2..tag.len() - 2].trim()));
+        rest = &rest[start + len + 2..];
+    }
+    pieces.push(Piece::Text(rest));
+    pieces
+}
+
+/// Names of the placeholders and sections in `template`, in order.
+fn placeholders(template: &str) -> impl Iterator<Item = &str> {
+    pieces(template).into_iter().filter_map(|piece| match piece {
+        Piece::Tag(_, key) => Some(key.trim_start_matches(['#', '/']).trim()),
+        Piece::Text(_) => None,
+    })
+}
+
+fn render(
+    template: &str,
+    values: &[(&str, &str)],
+    sections: &[&str],
+    encode: fn(&str) -> String,
+) -> String {
+    let mut out = String::with_capacity(template.len());
+    // The section being left out, if any.
+    let mut skipping: Option<&str> = None;
+    for piece in pieces(template) {
+        match piece {
+            Piece::Tag(_, key) if key.starts_with('#') => {
+                let name = key[1..].trim();
+                if skipping.is_none() && !sections.contains(&name) {
+                    skipping = Some(name);
+                }
+            }
+            Piece::Tag(_, key) if key.starts_with('/') => {
+                if skipping == Some(key[1..].trim()) {
+                    skipping = None;
+                }
+            }
+            _ if skipping.is_some() => {}
+            Piece::Text(text) => out.push_str(text),
+            Piece::Tag(tag, key) => match values.iter().find(|(name, _)| *name == key) {
+                Some((_, value)) => out.push_str(&encode(value)),
+                None => out.push_str(tag),
+            },
+        }
+    }
+    out
+}
+
+fn escape_html(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            '\n' => out.push_str("<br>\n"),
+            '\r' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Request details shown under a contact message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContactMeta {
+    pub received_at: SystemTime,
+    pub user_agent: Option<String>,
+    pub referer: Option<String>,
+}
+
+impl ContactMeta {
+    pub fn new(received_at: SystemTime, client: &ClientInfo) -> Self {
+        Self {
+            received_at,
+            user_agent: client.user_agent.clone(),
+            referer: client.referer.clone(),
+        }
+    }
+
+    pub fn values(&self) -> [(&'static str, String); 3] {
+        [
+            ("received_at", format_utc(self.received_at)),
+            ("user_agent", self.user_agent.clone().unwrap_or_else(|| UNKNOWN.to_string())),
+            ("referer", self.referer.clone().unwrap_or_else(|| UNKNOWN.to_string())),
+        ]
+    }
+}
+
+/// `2024-05-01 09:30:00 UTC`.
+pub fn format_utc(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    // Mail clients show this to people, so trade RFC 3339's `T` and `Z` for
+    // something easier to read.
+    dates::rfc3339(secs).replacen('T', " ", 1).replace('Z', " UTC")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn escapes_values_only_in_html() {
+        let template = Template {
+            text: "{{ body }} from {{name}}".to_string(),
+            html: "<p>{{ body }}</p>{{ missing }}".to_string(),
+        };
+        let (text, html) = template.render(&[("body", "<b>hi</b> & \"bye\"\nnext"), ("name", "Ann")], &[]);
+        assert_eq!(text, "<b>hi</b> & \"bye\"\nnext from Ann");
+        assert_eq!(
+            html,
+            "<p>&lt;b&gt;hi&lt;/b&gt; &amp; &quot;bye&quot;<br>\nnext</p>{{ missing }}"
+        );
+    }
+
+    #[test]
+    fn keeps_only_enabled_sections() {
+        let template = Template {
+            text: "Thanks{{#copy}}, you wrote: {{ body }}{{/copy}}. {{ unclosed".to_string(),
+            html: String::new(),
+        };
+        let values = [("body", "hi")];
+        assert_eq!(template.render(&values, &["copy"]).0, "Thanks, you wrote: hi. {{ unclosed");
+        assert_eq!(template.render(&values, &[]).0, "Thanks. {{ unclosed");
+    }
+
+    #[test]
+    fn formats_timestamps_in_utc() {
+        assert_eq!(format_utc(UNIX_EPOCH), "1970-01-01 00:00:00 UTC");
+        let leap_day = UNIX_EPOCH + Duration::from_secs(1_709_210_096);
+        assert_eq!(format_utc(leap_day), "2024-02-29 12:34:56 UTC");
+    }
+
+    #[test]
+    fn overrides_templates_from_files() {
+        let dir = std::env::temp_dir().join(format!("chris-templates-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("contact.txt"), "{{ name }} wrote: {{ body }}").unwrap();
+        let templates = EmailTemplates::from_dir(&dir);
+        std::fs::write(dir.join("contact.html"), "{{ nmae }}").unwrap();
+        let typo = EmailTemplates::from_dir(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let templates = templates.unwrap();
+        assert_eq!(templates.contact.text, "{{ name }} wrote: {{ body }}");
+        assert_eq!(templates.contact.html, CONTACT_HTML);
+        assert!(typo.unwrap_err().ends_with("unknown placeholder {{ nmae }}"));
+    }
+
+    #[test]
+    fn built_in_templates_only_use_known_placeholders() {
+        let templates = EmailTemplates::default();
+        for source in [&templates.contact.text, &templates.contact.html] {
+            assert!(placeholders(source).all(|key| CONTACT_FIELDS.contains(&key)));
+        }
+        for source in [&templates.auto_reply.text, &templates.auto_reply.html] {
+            assert!(placeholders(source).all(|key| AUTO_REPLY_FIELDS.contains(&key)));
+        }
+    }
+}
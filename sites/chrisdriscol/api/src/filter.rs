@@ -0,0 +1,235 @@
+use std::cmp::Ordering;
+
+use async_graphql::{Enum, InputObject};
+
+use crate::dates::DateRange;
+use crate::lint;
+use crate::types::{Experience, Work};
+
+/// Narrows `Chris.work`. All set fields must match.
+#[derive(InputObject, Default)]
+pub struct WorkFilter {
+    /// Only projects listing this technology. Case, spaces and dashes are
+    /// ignored, so "react-native" matches "React Native".
+    pub technology: Option<String>,
+    /// Case-insensitive substring of the project's location.
+    pub location: Option<String>,
+    /// Only projects active in or after this year.
+    pub from: Option<i32>,
+    /// Only projects active in or before this year.
+    pub to: Option<i32>,
+    /// Case-insensitive text searched in titles, descriptions and technologies.
+    pub text: Option<String>,
+}
+
+/// Narrows `Chris.experience`. All set fields must match.
+#[derive(InputObject, Default)]
+pub struct ExperienceFilter {
+    /// Case-insensitive substring of the experience's location.
+    pub location: Option<String>,
+    /// Only roles held in or after this year.
+    pub from: Option<i32>,
+    /// Only roles held in or before this year.
+    pub to: Option<i32>,
+    /// Case-insensitive text searched in titles, locations and descriptions.
+    pub text: Option<String>,
+}
+
+#[derive(Enum, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PortfolioSort {
+    /// Oldest start date first.
+    DateAsc,
+    /// Most recent start date first.
+    DateDesc,
+    TitleAsc,
+    TitleDesc,
+}
+
+pub fn work(items: &[Work], filter: &WorkFilter, sort: Option<PortfolioSort>) -> Vec<Work> {
+    let mut items: Vec<Work> = items
+        .iter()
+        .filter(|work| {
+            let technology = filter.technology.as_deref().is_none_or(|wanted| {
+                work.technologies.iter().any(|name| same_name(name, wanted))
+            });
+            let fields = [work.title.as_deref(), work.sub_title.as_deref(), work.location.as_deref()];
+            let text = filter.text.as_deref().is_none_or(|needle| {
+                fields.into_iter().flatten().any(|field| contains(field, needle))
+                    || work.description.iter().any(|paragraph| contains(paragraph, needle))
+                    || work.technologies.iter().any(|name| contains(name, needle))
+            });
+            technology
+                && text
+                && matches_location(work.location.as_deref(), filter.location.as_deref())
+                && in_years(work.dates.as_ref(), filter.from, filter.to)
+        })
+        .cloned()
+        .collect();
+    if let Some(sort) = sort {
+        items.sort_by(|a, b| compare(sort, (&a.dates, a.title.as_deref()), (&b.dates, b.title.as_deref())));
+    }
+    items
+}
+
+pub fn experience(
+    items: &[Experience],
+    filter: &ExperienceFilter,
+    sort: Option<PortfolioSort>,
+) -> Vec<Experience> {
+    let mut items: Vec<Experience> = items
+        .iter()
+        .filter(|exp| {
+            let fields = [exp.title.as_deref(), exp.location.as_deref(), exp.description.as_deref()];
+            let text = filter.text.as_deref().is_none_or(|needle| {
+                fields.into_iter().flatten().any(|field| contains(field, needle))
+            });
+            text && matches_location(exp.location.as_deref(), filter.location.as_deref())
+                && in_years(exp.dates.as_ref(), filter.from, filter.to)
+        })
+        .cloned()
+        .collect();
+    if let Some(sort) = sort {
+        // Untitled experience entries sort by where they happened instead.
+        let key = |exp: &Experience| exp.title.clone().or_else(|| exp.location.clone());
+        items.sort_by(|a, b| {
+            compare(sort, (&a.dates, key(a).as_deref()), (&b.dates, key(b).as_deref()))
+        });
+    }
+    items
+}
+
+fn compare(
+    sort: PortfolioSort,
+    (a_dates, a_title): (&Option<DateRange>, Option<&str>),
+    (b_dates, b_title): (&Option<DateRange>, Option<&str>),
+) -> Ordering {
+    // Entries without dates or titles always go last, whatever the direction.
+    fn by<T: Ord>(a: Option<T>, b: Option<T>, descending: bool) -> Ordering {
+        match (a, b) {
+            (Some(a), Some(b)) if descending => b.cmp(&a),
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+    let start = |dates: &Option<DateRange>| {
+        dates.as_ref().map(|dates| (dates.start.first_month(), dates.last_month()))
+    };
+    let title = |title: Option<&str>| title.map(str::to_lowercase);
+    match sort {
+        PortfolioSort::DateAsc => by(start(a_dates), start(b_dates), false),
+        PortfolioSort::DateDesc => by(start(a_dates), start(b_dates), true),
+        PortfolioSort::TitleAsc => by(title(a_title), title(b_title), false),
+        PortfolioSort::TitleDesc => by(title(a_title), title(b_title), true),
+    }
+}
+
+fn contains(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+fn matches_location(location: Option<&str>, wanted: Option<&str>) -> bool {
+    match wanted {
+        Some(wanted) => location.is_some_and(|location| contains(location, wanted)),
+        None => true,
+    }
+}
+
+/// Whether the date range overlaps the inclusive `from..=to` year window.
+fn in_years(dates: Option<&DateRange>, from: Option<i32>, to: Option<i32>) -> bool {
+    if from.is_none() && to.is_none() {
+        return true;
+    }
+    let Some(dates) = dates else {
+        return false;
+    };
+    // Years come straight from the query; in months they still fit an i64.
+    let after_from = from.is_none_or(|year| dates.last_month() >= i64::from(year) * 12);
+    let before_to = to.is_none_or(|year| dates.start.first_month() <= i64::from(year) * 12 + 11);
+    after_from && before_to
+}
+
+fn same_name(a: &str, b: &str) -> bool {
+    lint::normalize_name(a) == lint::normalize_name(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data;
+
+    fn titles(items: &[Work]) -> Vec<&str> {
+        items.iter().filter_map(|work| work.title.as_deref()).collect()
+    }
+
+    #[test]
+    fn filters_work_by_technology_and_years() {
+        let chris = data::load(None).unwrap();
+        let filter = WorkFilter {
+            technology: Some("graphql".to_string()),
+            from: Some(2015),
+            ..WorkFilter::default()
+        };
+        assert_eq!(titles(&work(&chris.work, &filter, None)), vec!["CommercialTribe"]);
+
+        let filter = WorkFilter {
+            technology: Some("react-native".to_string()),
+            ..WorkFilter::default()
+        };
+        assert_eq!(titles(&work(&chris.work, &filter, None)), vec!["CommercialTribe"]);
+
+        let filter = WorkFilter {
+            to: Some(2010),
+            ..WorkFilter::default()
+        };
+        assert_eq!(
+            titles(&work(&chris.work, &filter, Some(PortfolioSort::TitleAsc))),
+            vec!["D-SPECT QC GUI/Service", "SPECT Subtract"]
+        );
+
+        let filter = WorkFilter {
+            from: Some(i32::MAX),
+            ..WorkFilter::default()
+        };
+        assert!(work(&chris.work, &filter, None).is_empty());
+        let filter = WorkFilter {
+            from: Some(i32::MIN),
+            to: Some(i32::MAX),
+            ..WorkFilter::default()
+        };
+        assert_eq!(work(&chris.work, &filter, None).len(), chris.work.len());
+    }
+
+    #[test]
+    fn filters_experience_by_location_and_text() {
+        let chris = data::load(None).unwrap();
+        let filter = ExperienceFilter {
+            location: Some("mayo clinic".to_string()),
+            ..ExperienceFilter::default()
+        };
+        let found = experience(&chris.experience, &filter, Some(PortfolioSort::DateDesc));
+        let slugs: Vec<&str> = found.iter().map(|exp| exp.slug.as_str()).collect();
+        assert_eq!(slugs, vec!["mayo-clinic", "mayo-clinic-internship"]);
+
+        let filter = ExperienceFilter {
+            text: Some("DRUPAL".to_string()),
+            ..ExperienceFilter::default()
+        };
+        let found = experience(&chris.experience, &filter, None);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].slug, "rally-software");
+    }
+
+    #[test]
+    fn sorts_by_date() {
+        let chris = data::load(None).unwrap();
+        let sorted = work(&chris.work, &WorkFilter::default(), Some(PortfolioSort::DateAsc));
+        let starts: Vec<i32> = sorted
+            .iter()
+            .filter_map(|work| work.dates.as_ref().map(|dates| dates.start.year))
+            .collect();
+        assert!(starts.windows(2).all(|pair| pair[0] <= pair[1]), "{starts:?}");
+        assert_eq!(sorted.len(), chris.work.len());
+    }
+}
@@ -0,0 +1,390 @@
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
+use async_graphql::connection::CursorType;
+use async_graphql::{async_trait, ComplexObject, Context, Enum, InputObject, Object, SimpleObject, ID};
+
+use crate::dates;
+use crate::dynamodb::Table;
+use crate::email::ContactMeta;
+use crate::outbox::{Outbox, OutboxStatus};
+use crate::signing::Signer;
+use crate::sqlite::{from_millis, millis};
+use crate::spam::SpamVerdict;
+use crate::types::ContactMeInput;
+
+mod dynamodb;
+mod memory;
+mod sqlite;
+
+pub use dynamodb::DynamoInboxStore;
+pub use memory::MemoryInboxStore;
+pub use sqlite::SqliteInboxStore;
+
+const DEFAULT_PATH: &str = "inbox.sqlite3";
+
+/// A message sent through the contact form, including ones quarantined as
+/// spam.
+#[derive(SimpleObject, Debug, Clone, PartialEq)]
+#[graphql(complex)]
+pub struct ContactSubmission {
+    /// The `submissionId` returned by `contactMe`.
+    pub id: ID,
+    /// Since the Unix epoch.
+    #[graphql(skip)]
+    pub received_at: Duration,
+    pub name: String,
+    pub email: String,
+    pub subject: String,
+    pub body: String,
+    /// Keyed hash of the sender's IP address. Equal hashes mean the same
+    /// address, but the address itself is not kept.
+    pub ip_hash: Option<String>,
+    pub user_agent: Option<String>,
+    /// The page the form was sent from.
+    pub referer: Option<String>,
+    pub spam_score: i32,
+    /// Why the message scored what it did, e.g. `links` or `too_fast`.
+    pub spam_signals: Vec<String>,
+    /// Scored as spam and never emailed.
+    pub quarantined: bool,
+    pub read: bool,
+}
+
+#[derive(Enum, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DeliveryStatus {
+    /// Held as spam; no email was queued.
+    Quarantined,
+    /// Queued or being retried.
+    Pending,
+    Sent,
+    /// Every delivery attempt failed.
+    Failed,
+}
+
+#[ComplexObject]
+impl ContactSubmission {
+    /// When the message arrived, as an RFC 3339 UTC timestamp.
+    async fn received_at(&self) -> String {
+        dates::rfc3339(self.received_at.as_secs())
+    }
+
+    /// Where the notification email stands, or null once the outbox no
+    /// longer knows the message.
+    async fn delivery_status(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<DeliveryStatus>> {
+        if self.quarantined {
+            return Ok(Some(DeliveryStatus::Quarantined));
+        }
+        let message = ctx.data::<Outbox>()?.store.get(&self.id).await?;
+        Ok(message.map(|message| match message.status {
+            OutboxStatus::Pending => DeliveryStatus::Pending,
+            OutboxStatus::Sent => DeliveryStatus::Sent,
+            OutboxStatus::Dead => DeliveryStatus::Failed,
+        }))
+    }
+}
+
+impl ContactSubmission {
+    pub fn new(
+        id: String,
+        input: &ContactMeInput,
+        meta: &ContactMeta,
+        verdict: &SpamVerdict,
+        quarantined: bool,
+    ) -> Self {
+        Self {
+            id: ID(id),
+            received_at: meta.received_at.duration_since(UNIX_EPOCH).unwrap_or_default(),
+            name: input.name.clone(),
+            email: input.from.clone(),
+            subject: input.subject.clone(),
+            body: input.body.clone(),
+            ip_hash: None,
+            user_agent: meta.user_agent.clone(),
+            referer: meta.referer.clone(),
+            spam_score: i32::try_from(verdict.score).unwrap_or(i32::MAX),
+            spam_signals: verdict.signals.iter().map(|signal| signal.as_str().to_string()).collect(),
+            quarantined,
+            read: false,
+        }
+    }
+}
+
+/// Narrows `contactSubmissions`. All set fields must match.
+#[derive(InputObject, Debug, Default, Clone)]
+pub struct ContactSubmissionFilter {
+    pub read: Option<bool>,
+    pub quarantined: Option<bool>,
+    /// Case-insensitive text searched in the sender's name and address, the
+    /// subject and the body.
+    pub text: Option<String>,
+}
+
+impl ContactSubmissionFilter {
+    pub fn matches(&self, submission: &ContactSubmission) -> bool {
+        let text = self.text.as_deref().map(str::to_lowercase).is_none_or(|needle| {
+            [&submission.name, &submission.email, &submission.subject, &submission.body]
+                .into_iter()
+                .any(|field| field.to_lowercase().contains(&needle))
+        });
+        self.read.is_none_or(|read| submission.read == read)
+            && self.quarantined.is_none_or(|quarantined| submission.quarantined == quarantined)
+            && text
+    }
+}
+
+/// Position in the newest-first inbox: pages continue with the submissions
+/// ordered after this one. Encoded as `<received_at millis>:<id>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InboxCursor {
+    pub received_at: Duration,
+    pub id: String,
+}
+
+impl InboxCursor {
+    pub fn of(submission: &ContactSubmission) -> Self {
+        Self {
+            received_at: submission.received_at,
+            id: submission.id.to_string(),
+        }
+    }
+
+    /// Whether `submission` comes after the cursor, newest first.
+    pub fn precedes(&self, submission: &ContactSubmission) -> bool {
+        (submission.received_at, submission.id.as_str()) < (self.received_at, self.id.as_str())
+    }
+}
+
+impl CursorType for InboxCursor {
+    type Error = String;
+
+    fn decode_cursor(s: &str) -> Result<Self, Self::Error> {
+        let (received_at, id) = s.split_once(':').ok_or("invalid cursor")?;
+        let received_at = received_at.parse().map_err(|_| "invalid cursor")?;
+        Ok(Self {
+            received_at: from_millis(received_at),
+            id: id.to_string(),
+        })
+    }
+
+    fn encode_cursor(&self) -> String {
+        format!("{}:{}", millis(self.received_at), self.id)
+    }
+}
+
+/// One page of `InboxStore::list`.
+#[derive(Debug, Default, PartialEq)]
+pub struct InboxPage {
+    pub submissions: Vec<ContactSubmission>,
+    /// More submissions match after the last one.
+    pub has_more: bool,
+}
+
+impl InboxPage {
+    /// Keeps the first `limit` of `matching`, which stores fetch one past
+    /// the limit to know whether more follow.
+    pub fn new(mut matching: Vec<ContactSubmission>, limit: usize) -> Self {
+        let has_more = matching.len() > limit;
+        matching.truncate(limit);
+        Self {
+            submissions: matching,
+            has_more,
+        }
+    }
+}
+
+/// `totalCount` of `contactSubmissions`. It is only counted when asked for,
+/// since counting reads every matching submission.
+pub struct SubmissionCount {
+    pub filter: ContactSubmissionFilter,
+}
+
+#[Object]
+impl SubmissionCount {
+    async fn total_count(&self, ctx: &Context<'_>) -> async_graphql::Result<usize> {
+        Ok(ctx.data::<Inbox>()?.store.count(&self.filter).await?)
+    }
+}
+
+/// Where the inbox keeps submissions.
+#[async_trait::async_trait]
+pub trait InboxStore: Send + Sync {
+    async fn insert(&self, submission: &ContactSubmission) -> Result<(), String>;
+
+    /// Up to `limit` submissions matching `filter`, newest first, starting
+    /// after `after`. Stores read only as far as the page needs.
+    async fn list(
+        &self,
+        filter: &ContactSubmissionFilter,
+        after: Option<&InboxCursor>,
+        limit: usize,
+    ) -> Result<InboxPage, String>;
+
+    /// How many submissions match `filter`.
+    async fn count(&self, filter: &ContactSubmissionFilter) -> Result<usize, String>;
+
+    /// Returns the updated submission, or `None` if there is no such ID.
+    async fn set_read(&self, id: &str, read: bool) -> Result<Option<ContactSubmission>, String>;
+
+    /// Whether there was a submission to delete.
+    async fn delete(&self, id: &str) -> Result<bool, String>;
+}
+
+/// Keeps every `contactMe` submission for the admin API, whether it was
+/// emailed or quarantined.
+#[derive(Clone)]
+pub struct Inbox {
+    pub store: Arc<dyn InboxStore>,
+    pub ip_hasher: Signer,
+}
+
+impl Default for Inbox {
+    fn default() -> Self {
+        Self {
+            store: Arc::new(MemoryInboxStore::default()),
+            ip_hasher: Signer::random(),
+        }
+    }
+}
+
+impl Inbox {
+    /// Picks the store named by `CONTACT_INBOX`: `sqlite` (default, a file
+    /// at `CONTACT_INBOX_PATH`), `dynamodb` (the table named by
+    /// `CONTACT_INBOX_TABLE`) or `memory`. IP addresses are hashed with
+    /// `CONTACT_IP_HASH_SECRET`.
+    pub fn from_env() -> Result<Self, String> {
+        let store: Arc<dyn InboxStore> = match std::env::var("CONTACT_INBOX").as_deref() {
+            Err(_) | Ok("sqlite") => {
+                let path = std::env::var("CONTACT_INBOX_PATH").unwrap_or_else(|_| DEFAULT_PATH.to_string());
+                Arc::new(SqliteInboxStore::new(path))
+            }
+            Ok("dynamodb") => Arc::new(DynamoInboxStore::new(Table::from_env("CONTACT_INBOX_TABLE")?)),
+            Ok("memory") => Arc::new(MemoryInboxStore::default()),
+            Ok(other) => return Err(format!("unknown CONTACT_INBOX {other:?}")),
+        };
+        Ok(Self {
+            store,
+            ip_hasher: Signer::from_env("CONTACT_IP_HASH_SECRET")?,
+        })
+    }
+
+    /// Stores `submission`, hashing the sender's address into it.
+    pub async fn record(&self, submission: ContactSubmission, ip: Option<IpAddr>) -> Result<(), String> {
+        let submission = ContactSubmission {
+            ip_hash: ip.map(|ip| self.ip_hasher.digest(&ip.to_string())),
+            ..submission
+        };
+        self.store.insert(&submission).await
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    pub(crate) fn submission(id: &str, received_secs: u64) -> ContactSubmission {
+        ContactSubmission {
+            id: ID(id.to_string()),
+            received_at: Duration::from_secs(received_secs),
+            name: "Visitor".to_string(),
+            email: "visitor@example.com".to_string(),
+            subject: "Hello".to_string(),
+            body: "Nice site.".to_string(),
+            ip_hash: None,
+            user_agent: None,
+            referer: None,
+            spam_score: 0,
+            spam_signals: Vec::new(),
+            quarantined: false,
+            read: false,
+        }
+    }
+
+    #[test]
+    fn filters_on_every_set_field() {
+        let unread = submission("a", 1);
+        let spam = ContactSubmission {
+            subject: "Cheap SEO".to_string(),
+            quarantined: true,
+            ..submission("b", 2)
+        };
+        let filter = |read, quarantined, text: Option<&str>| ContactSubmissionFilter {
+            read,
+            quarantined,
+            text: text.map(str::to_string),
+        };
+        assert!(filter(None, None, None).matches(&unread));
+        assert!(filter(Some(false), Some(false), None).matches(&unread));
+        assert!(!filter(Some(true), None, None).matches(&unread));
+        assert!(filter(None, Some(true), Some("seo")).matches(&spam));
+        assert!(!filter(None, Some(false), Some("seo")).matches(&spam));
+        assert!(!filter(None, None, Some("seo")).matches(&unread));
+    }
+
+    #[test]
+    fn cursors_round_trip_and_order_newest_first() {
+        let cursor = InboxCursor::of(&submission("b:c", 2));
+        assert_eq!(InboxCursor::decode_cursor(&cursor.encode_cursor()), Ok(cursor.clone()));
+        assert_eq!(cursor.encode_cursor(), "2000:b:c");
+        assert!(InboxCursor::decode_cursor("nope").is_err());
+
+        assert!(cursor.precedes(&submission("z", 1)));
+        assert!(cursor.precedes(&submission("a", 2)));
+        assert!(!cursor.precedes(&submission("b:c", 2)));
+        assert!(!cursor.precedes(&submission("a", 3)));
+    }
+
+    /// Pages through every store the same way.
+    pub(crate) async fn pages_newest_first(store: &dyn InboxStore) {
+        for (id, secs) in [("a", 1), ("b", 2), ("c", 2), ("d", 3)] {
+            let submission = ContactSubmission {
+                read: id == "b",
+                ..submission(id, secs)
+            };
+            store.insert(&submission).await.unwrap();
+        }
+        let all = ContactSubmissionFilter::default();
+        let ids = |page: &InboxPage| page.submissions.iter().map(|s| s.id.to_string()).collect::<Vec<_>>();
+
+        let first = store.list(&all, None, 2).await.unwrap();
+        assert_eq!(ids(&first), ["d", "c"]);
+        assert!(first.has_more);
+        let cursor = InboxCursor::of(first.submissions.last().unwrap());
+        let second = store.list(&all, Some(&cursor), 2).await.unwrap();
+        assert_eq!(ids(&second), ["b", "a"]);
+        assert!(!second.has_more);
+
+        let unread = ContactSubmissionFilter {
+            read: Some(false),
+            ..ContactSubmissionFilter::default()
+        };
+        let page = store.list(&unread, Some(&cursor), 5).await.unwrap();
+        assert_eq!(ids(&page), ["a"]);
+        assert_eq!(store.count(&unread).await, Ok(3));
+        assert_eq!(store.count(&all).await, Ok(4));
+    }
+
+    #[tokio::test]
+    async fn memory_store_pages_newest_first() {
+        pages_newest_first(&MemoryInboxStore::default()).await;
+    }
+
+    #[tokio::test]
+    async fn hashes_ip_addresses_before_storing() {
+        let inbox = Inbox {
+            ip_hasher: Signer::new("secret"),
+            ..Inbox::default()
+        };
+        let ip: IpAddr = "203.0.113.7".parse().unwrap();
+        inbox.record(submission("a", 1), Some(ip)).await.unwrap();
+        inbox.record(submission("b", 2), Some(ip)).await.unwrap();
+        inbox.record(submission("c", 3), None).await.unwrap();
+
+        let stored = inbox.store.list(&ContactSubmissionFilter::default(), None, 10).await.unwrap();
+        let hashes: Vec<Option<&str>> = stored.submissions.iter().map(|s| s.ip_hash.as_deref()).collect();
+        assert_eq!(hashes[0], None);
+        assert_eq!(hashes[1], hashes[2]);
+        assert!(!hashes[1].unwrap().contains("203.0.113.7"));
+    }
+}
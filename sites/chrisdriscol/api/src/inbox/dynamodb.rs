@@ -0,0 +1,199 @@
+use async_graphql::{async_trait, ID};
+use serde_json::{json, Value};
+
+use super::{ContactSubmission, ContactSubmissionFilter, InboxCursor, InboxPage, InboxStore};
+use crate::dynamodb::{
+    boolean, get_boolean, get_number, get_optional_string, get_string, get_strings, item, items, number,
+    optional_string, string, strings, DynamoError, Item, Table,
+};
+use crate::sqlite::{from_millis, millis};
+
+/// Index holding every submission under one `inbox` key, sorted by
+/// `received_at`, so pages are read newest first without a scan. Index reads
+/// are eventually consistent, so a new submission can take a moment to show.
+const RECEIVED_INDEX: &str = "received";
+
+/// The `inbox` value every submission carries.
+const INBOX: &str = "all";
+
+/// Keeps the inbox in a DynamoDB table keyed by `id`, shared by every
+/// instance. Times are stored as Unix milliseconds.
+pub struct DynamoInboxStore {
+    table: Table,
+}
+
+impl DynamoInboxStore {
+    pub fn new(table: Table) -> Self {
+        Self { table }
+    }
+}
+
+fn to_item(submission: &ContactSubmission) -> Value {
+    json!({
+        "id": string(submission.id.as_str()),
+        "inbox": string(INBOX),
+        "received_at": number(millis(submission.received_at)),
+        "name": string(&submission.name),
+        "email": string(&submission.email),
+        "subject": string(&submission.subject),
+        "body": string(&submission.body),
+        "ip_hash": optional_string(submission.ip_hash.as_deref()),
+        "user_agent": optional_string(submission.user_agent.as_deref()),
+        "referer": optional_string(submission.referer.as_deref()),
+        "spam_score": number(submission.spam_score),
+        "spam_signals": strings(&submission.spam_signals),
+        "quarantined": boolean(submission.quarantined),
+        "is_read": boolean(submission.read),
+    })
+}
+
+fn from_item(item: &Item) -> Result<ContactSubmission, String> {
+    Ok(ContactSubmission {
+        id: ID(get_string(item, "id")?),
+        received_at: from_millis(get_number(item, "received_at")?),
+        name: get_string(item, "name")?,
+        email: get_string(item, "email")?,
+        subject: get_string(item, "subject")?,
+        body: get_string(item, "body")?,
+        ip_hash: get_optional_string(item, "ip_hash")?,
+        user_agent: get_optional_string(item, "user_agent")?,
+        referer: get_optional_string(item, "referer")?,
+        spam_score: get_number(item, "spam_score")?,
+        spam_signals: get_strings(item, "spam_signals")?,
+        quarantined: get_boolean(item, "quarantined")?,
+        read: get_boolean(item, "is_read")?,
+    })
+}
+
+/// Queries the received index newest first, with the read and quarantine
+/// filters applied by DynamoDB. Text is matched by the caller.
+fn query(filter: &ContactSubmissionFilter) -> Value {
+    let mut conditions = Vec::new();
+    let mut values = json!({ ":inbox": string(INBOX) });
+    if let Some(read) = filter.read {
+        conditions.push("is_read = :read");
+        values[":read"] = boolean(read);
+    }
+    if let Some(quarantined) = filter.quarantined {
+        conditions.push("quarantined = :quarantined");
+        values[":quarantined"] = boolean(quarantined);
+    }
+    let mut request = json!({
+        "IndexName": RECEIVED_INDEX,
+        "KeyConditionExpression": "inbox = :inbox",
+        "ExpressionAttributeValues": values,
+        "ScanIndexForward": false,
+    });
+    if !conditions.is_empty() {
+        request["FilterExpression"] = json!(conditions.join(" AND "));
+    }
+    request
+}
+
+#[async_trait::async_trait]
+impl InboxStore for DynamoInboxStore {
+    async fn insert(&self, submission: &ContactSubmission) -> Result<(), String> {
+        let request = json!({
+            "Item": to_item(submission),
+            "ConditionExpression": "attribute_not_exists(id)",
+        });
+        self.table.call("PutItem", request).await?;
+        Ok(())
+    }
+
+    async fn list(
+        &self,
+        filter: &ContactSubmissionFilter,
+        after: Option<&InboxCursor>,
+        limit: usize,
+    ) -> Result<InboxPage, String> {
+        let mut request = query(filter);
+        if let Some(after) = after {
+            request["ExclusiveStartKey"] = json!({
+                "id": string(&after.id),
+                "inbox": string(INBOX),
+                "received_at": number(millis(after.received_at)),
+            });
+        }
+        // Read a page past the limit to learn whether more follow. Filters
+        // can leave a read short, so keep going until enough match.
+        let wanted = limit.saturating_add(1);
+        let mut matching = Vec::new();
+        while matching.len() < wanted {
+            request["Limit"] = json!(wanted.max(25));
+            let page = self.table.call("Query", request.clone()).await?;
+            for item in items(&page, "Items")? {
+                let submission = from_item(&item)?;
+                if matching.len() < wanted && filter.matches(&submission) {
+                    matching.push(submission);
+                }
+            }
+            match page.get("LastEvaluatedKey") {
+                Some(key) => request["ExclusiveStartKey"] = key.clone(),
+                None => break,
+            }
+        }
+        Ok(InboxPage::new(matching, limit))
+    }
+
+    async fn count(&self, filter: &ContactSubmissionFilter) -> Result<usize, String> {
+        let items = self.table.collect("Query", query(filter)).await?;
+        let mut count = 0;
+        for item in &items {
+            if filter.matches(&from_item(item)?) {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    async fn set_read(&self, id: &str, read: bool) -> Result<Option<ContactSubmission>, String> {
+        let request = json!({
+            "Key": { "id": string(id) },
+            "UpdateExpression": "SET is_read = :read",
+            "ConditionExpression": "attribute_exists(id)",
+            "ExpressionAttributeValues": { ":read": boolean(read) },
+            "ReturnValues": "ALL_NEW",
+        });
+        match self.table.call("UpdateItem", request).await {
+            Ok(response) => item(&response, "Attributes").as_ref().map(from_item).transpose(),
+            Err(DynamoError::ConditionFailed) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn delete(&self, id: &str) -> Result<bool, String> {
+        let request = json!({
+            "Key": { "id": string(id) },
+            "ReturnValues": "ALL_OLD",
+        });
+        let response = self.table.call("DeleteItem", request).await?;
+        Ok(item(&response, "Attributes").is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inbox::tests::submission as sample;
+
+    #[test]
+    fn round_trips_submissions_through_items() {
+        let quarantined = ContactSubmission {
+            ip_hash: Some("hash".to_string()),
+            user_agent: Some("Mozilla/5.0".to_string()),
+            spam_score: 60,
+            spam_signals: vec!["links".to_string(), "too_fast".to_string()],
+            quarantined: true,
+            read: true,
+            ..sample("a", 1_700_000_000)
+        };
+        let plain = sample("b", 1_700_000_060);
+
+        for submission in [quarantined, plain] {
+            let item = to_item(&submission);
+            assert_eq!(item["inbox"], string(INBOX));
+            assert_eq!(from_item(item.as_object().unwrap()), Ok(submission));
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use std::sync::Mutex;
+
+use async_graphql::async_trait;
+
+use super::{ContactSubmission, ContactSubmissionFilter, InboxCursor, InboxPage, InboxStore};
+
+/// Keeps the inbox in process memory. Nothing survives a restart, so this
+/// suits tests and local runs only.
+#[derive(Default)]
+pub struct MemoryInboxStore {
+    submissions: Mutex<Vec<ContactSubmission>>,
+}
+
+#[async_trait::async_trait]
+impl InboxStore for MemoryInboxStore {
+    async fn insert(&self, submission: &ContactSubmission) -> Result<(), String> {
+        self.submissions.lock().unwrap().push(submission.clone());
+        Ok(())
+    }
+
+    async fn list(
+        &self,
+        filter: &ContactSubmissionFilter,
+        after: Option<&InboxCursor>,
+        limit: usize,
+    ) -> Result<InboxPage, String> {
+        let mut matching: Vec<ContactSubmission> = self
+            .submissions
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|submission| filter.matches(submission))
+            .filter(|submission| after.is_none_or(|after| after.precedes(submission)))
+            .cloned()
+            .collect();
+        matching.sort_by(|a, b| b.received_at.cmp(&a.received_at).then_with(|| b.id.cmp(&a.id)));
+        matching.truncate(limit.saturating_add(1));
+        Ok(InboxPage::new(matching, limit))
+    }
+
+    async fn count(&self, filter: &ContactSubmissionFilter) -> Result<usize, String> {
+        Ok(self.submissions.lock().unwrap().iter().filter(|submission| filter.matches(submission)).count())
+    }
+
+    async fn set_read(&self, id: &str, read: bool) -> Result<Option<ContactSubmission>, String> {
+        let mut submissions = self.submissions.lock().unwrap();
+        Ok(submissions.iter_mut().find(|submission| submission.id == id).map(|submission| {
+            submission.read = read;
+            submission.clone()
+        }))
+    }
+
+    async fn delete(&self, id: &str) -> Result<bool, String> {
+        let mut submissions = self.submissions.lock().unwrap();
+        let before = submissions.len();
+        submissions.retain(|submission| submission.id != id);
+        Ok(submissions.len() < before)
+    }
+}
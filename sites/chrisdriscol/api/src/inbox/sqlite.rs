@@ -0,0 +1,244 @@
+use std::path::PathBuf;
+
+use async_graphql::{async_trait, ID};
+use rusqlite::{params, Connection, Row};
+
+use super::{ContactSubmission, ContactSubmissionFilter, InboxCursor, InboxPage, InboxStore};
+use crate::sqlite::{from_millis, millis, Database};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS inbox (
+        id TEXT PRIMARY KEY,
+        received_at INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        email TEXT NOT NULL,
+        subject TEXT NOT NULL,
+        body TEXT NOT NULL,
+        ip_hash TEXT,
+        user_agent TEXT,
+        referer TEXT,
+        spam_score INTEGER NOT NULL,
+        spam_signals TEXT NOT NULL,
+        quarantined INTEGER NOT NULL,
+        is_read INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS inbox_received ON inbox (received_at);
+";
+
+/// Newest first, from just after `?1`/`?2` when they are set, with the read
+/// and quarantine filters as `?3` and `?4`.
+const LIST: &str = "WHERE (?1 IS NULL OR received_at < ?1 OR (received_at = ?1 AND id < ?2))
+                      AND (?3 IS NULL OR is_read = ?3)
+                      AND (?4 IS NULL OR quarantined = ?4)
+                    ORDER BY received_at DESC, id DESC";
+
+const COLUMNS: &str = "id, received_at, name, email, subject, body, ip_hash, user_agent, referer, \
+                       spam_score, spam_signals, quarantined, is_read";
+
+/// Keeps the inbox in a SQLite file. Times are stored as Unix milliseconds
+/// and spam signals comma separated.
+pub struct SqliteInboxStore {
+    db: Database,
+}
+
+fn submission(row: &Row<'_>) -> rusqlite::Result<ContactSubmission> {
+    let signals: String = row.get(10)?;
+    Ok(ContactSubmission {
+        id: ID(row.get(0)?),
+        received_at: from_millis(row.get(1)?),
+        name: row.get(2)?,
+        email: row.get(3)?,
+        subject: row.get(4)?,
+        body: row.get(5)?,
+        ip_hash: row.get(6)?,
+        user_agent: row.get(7)?,
+        referer: row.get(8)?,
+        spam_score: row.get(9)?,
+        spam_signals: signals.split(',').filter(|s| !s.is_empty()).map(str::to_string).collect(),
+        quarantined: row.get(11)?,
+        read: row.get(12)?,
+    })
+}
+
+/// Up to `limit` submissions matching `filter` after `after`. Text is matched
+/// here, with the same rules as the memory store; rows are read only until
+/// `limit` of them match.
+fn matching(
+    conn: &Connection,
+    filter: &ContactSubmissionFilter,
+    after: Option<&InboxCursor>,
+    limit: usize,
+) -> rusqlite::Result<Vec<ContactSubmission>> {
+    let mut statement = conn.prepare(&format!("SELECT {COLUMNS} FROM inbox {LIST}"))?;
+    let rows = statement.query_map(
+        params![
+            after.map(|after| millis(after.received_at)),
+            after.map(|after| after.id.as_str()),
+            filter.read,
+            filter.quarantined,
+        ],
+        submission,
+    )?;
+    let mut matching = Vec::new();
+    for row in rows {
+        if matching.len() == limit {
+            break;
+        }
+        let submission = row?;
+        if filter.matches(&submission) {
+            matching.push(submission);
+        }
+    }
+    Ok(matching)
+}
+
+impl SqliteInboxStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            db: Database::new(path, SCHEMA),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl InboxStore for SqliteInboxStore {
+    async fn insert(&self, submission: &ContactSubmission) -> Result<(), String> {
+        let submission = submission.clone();
+        self.db
+            .with(move |conn| {
+                conn.execute(
+                    &format!(
+                        "INSERT INTO inbox ({COLUMNS})
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)"
+                    ),
+                    params![
+                        submission.id.as_str(),
+                        millis(submission.received_at),
+                        submission.name,
+                        submission.email,
+                        submission.subject,
+                        submission.body,
+                        submission.ip_hash,
+                        submission.user_agent,
+                        submission.referer,
+                        submission.spam_score,
+                        submission.spam_signals.join(","),
+                        submission.quarantined,
+                        submission.read,
+                    ],
+                )
+                .map(drop)
+            })
+            .await
+    }
+
+    async fn list(
+        &self,
+        filter: &ContactSubmissionFilter,
+        after: Option<&InboxCursor>,
+        limit: usize,
+    ) -> Result<InboxPage, String> {
+        let filter = filter.clone();
+        let after = after.cloned();
+        self.db
+            .with(move |conn| {
+                let matching = matching(conn, &filter, after.as_ref(), limit.saturating_add(1))?;
+                Ok(InboxPage::new(matching, limit))
+            })
+            .await
+    }
+
+    async fn count(&self, filter: &ContactSubmissionFilter) -> Result<usize, String> {
+        let filter = filter.clone();
+        self.db
+            .with(move |conn| matching(conn, &filter, None, usize::MAX).map(|matching| matching.len()))
+            .await
+    }
+
+    async fn set_read(&self, id: &str, read: bool) -> Result<Option<ContactSubmission>, String> {
+        let id = id.to_string();
+        self.db
+            .with(move |conn| {
+                let updated = conn.execute("UPDATE inbox SET is_read = ?1 WHERE id = ?2", params![read, id])?;
+                if updated == 0 {
+                    return Ok(None);
+                }
+                conn.query_row(&format!("SELECT {COLUMNS} FROM inbox WHERE id = ?1"), [id], submission)
+                    .map(Some)
+            })
+            .await
+    }
+
+    async fn delete(&self, id: &str) -> Result<bool, String> {
+        let id = id.to_string();
+        self.db
+            .with(move |conn| conn.execute("DELETE FROM inbox WHERE id = ?1", [id]).map(|deleted| deleted > 0))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inbox::tests::submission as sample;
+
+    #[tokio::test]
+    async fn keeps_submissions_across_reopens() {
+        let path = std::env::temp_dir().join(format!("chris-inbox-{}.sqlite3", std::process::id()));
+        let older = ContactSubmission {
+            ip_hash: Some("hash".to_string()),
+            user_agent: Some("Mozilla/5.0".to_string()),
+            spam_score: 60,
+            spam_signals: vec!["links".to_string(), "too_fast".to_string()],
+            quarantined: true,
+            ..sample("a", 1_700_000_000)
+        };
+        let newer = sample("b", 1_700_000_060);
+
+        let store = SqliteInboxStore::new(&path);
+        store.insert(&older).await.unwrap();
+        store.insert(&newer).await.unwrap();
+        drop(store);
+
+        let reopened = SqliteInboxStore::new(&path);
+        let listed = reopened.list(&ContactSubmissionFilter::default(), None, 10).await.unwrap().submissions;
+        let read = reopened.set_read("a", true).await.unwrap();
+        let unread = reopened
+            .list(
+                &ContactSubmissionFilter {
+                    read: Some(false),
+                    ..ContactSubmissionFilter::default()
+                },
+                None,
+                10,
+            )
+            .await
+            .unwrap()
+            .submissions;
+        let missing = reopened.set_read("nope", true).await.unwrap();
+        let deleted = reopened.delete("b").await.unwrap();
+        let deleted_again = reopened.delete("b").await.unwrap();
+        let remaining = reopened.list(&ContactSubmissionFilter::default(), None, 10).await.unwrap().submissions;
+        drop(reopened);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+        }
+
+        assert_eq!(listed, vec![newer.clone(), older.clone()]);
+        let older = ContactSubmission { read: true, ..older };
+        assert_eq!(read, Some(older.clone()));
+        assert_eq!(unread, vec![newer]);
+        assert_eq!(missing, None);
+        assert!(deleted && !deleted_again);
+        assert_eq!(remaining, vec![older]);
+    }
+
+    #[tokio::test]
+    async fn pages_newest_first() {
+        let path = std::env::temp_dir().join(format!("chris-inbox-pages-{}.sqlite3", std::process::id()));
+        crate::inbox::tests::pages_newest_first(&SqliteInboxStore::new(&path)).await;
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+        }
+    }
+}
@@ -1,102 +1,35 @@
-use async_graphql::{ErrorExtensionValues, Request as GraphqlRequest, Response, ServerError, Variables};
-use lambda_http::{service_fn, Body, Error, Request, Response as LambdaResponse};
-use serde::Deserialize;
+use chris_driscol_api::email::Mailer;
+use chris_driscol_api::outbox::Outbox;
+use chris_driscol_api::{router, schema};
+use lambda_http::{lambda_runtime, service_fn, Error, LambdaEvent};
 
-mod schema;
-mod types;
-mod data;
-mod email;
-mod llms;
-
-use schema::AppSchema;
-
-#[derive(Deserialize)]
-struct GraphqlPayload {
-    query: String,
-    #[serde(default)]
-    variables: Option<serde_json::Value>,
-    #[serde(rename = "operationName")]
-    operation_name: Option<String>,
-}
-
-fn forbidden_response() -> Response {
-    let mut error = ServerError::new("Forbidden", None);
-    let mut extensions = ErrorExtensionValues::default();
-    extensions.set("code", "FORBIDDEN");
-    error.extensions = Some(extensions);
-    Response::from_errors(vec![error])
-}
-
-fn graphql_json_response(response: Response) -> Result<LambdaResponse<Body>, Error> {
-    let body = serde_json::to_string(&response)?;
-    let response = LambdaResponse::builder()
-        .status(200)
-        .header("content-type", "application/json")
-        .body(Body::Text(body))?;
-    Ok(response)
-}
-
-async fn handler(schema: AppSchema, request: Request) -> Result<LambdaResponse<Body>, Error> {
-    // Handle /llms.txt route
-    if request.uri().path() == "/llms.txt" {
-        if request.method() == http::Method::GET {
-            let content = llms::generate_llms_txt();
-            return Ok(LambdaResponse::builder()
-                .status(200)
-                .header("content-type", "text/markdown; charset=utf-8")
-                .body(Body::Text(content))?);
-        } else {
-            return Ok(LambdaResponse::builder()
-                .status(405)
-                .body(Body::Text("Method Not Allowed".into()))?);
-        }
-    }
-
-    // GraphQL endpoint handling
-    if let Ok(expected) = std::env::var("GRAPHQL_ORIGIN_SECRET") {
-        let provided = request
-            .headers()
-            .get("x-origin-secret")
-            .and_then(|value| value.to_str().ok());
-        if provided != Some(expected.as_str()) {
-            return graphql_json_response(forbidden_response());
-        }
-    }
-
-    if request.method() == http::Method::OPTIONS {
-        return Ok(LambdaResponse::builder()
-            .status(204)
-            .body(Body::Empty)?);
-    }
-
-    if request.method() != http::Method::POST {
-        return Ok(LambdaResponse::builder()
-            .status(405)
-            .body(Body::Text("Method Not Allowed".into()))?);
-    }
-
-    let body = match request.body() {
-        Body::Text(text) => text.as_bytes().to_vec(),
-        Body::Binary(bytes) => bytes.clone(),
-        Body::Empty => Vec::new(),
-        _ => Vec::new(),
-    };
-
-    let payload: GraphqlPayload = serde_json::from_slice(&body)?;
-    let mut gql_request = GraphqlRequest::new(payload.query);
-    if let Some(variables) = payload.variables {
-        gql_request = gql_request.variables(Variables::from_json(variables));
-    }
-    if let Some(operation_name) = payload.operation_name {
-        gql_request = gql_request.operation_name(operation_name);
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    // The stack deploys this package twice: behind the function URL, and
+    // with the `outbox` handler on a schedule. Lambda freezes idle
+    // instances, so queued email is retried by the schedule rather than a
+    // background task.
+    if std::env::var("_HANDLER").as_deref() == Ok("outbox") {
+        return run_outbox_worker().await;
     }
-
-    let response = schema.execute(gql_request).await;
-    graphql_json_response(response)
+    let config = schema::SchemaConfig::from_env()?;
+    let content = config.content.clone();
+    let schema = schema::build_schema_with(config);
+    let config = router::RouterConfig::from_env()?;
+    lambda_http::run(router::router(schema, content, &config)).await
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Error> {
-    let schema = schema::build_schema();
-    lambda_http::run(service_fn(|request| handler(schema.clone(), request))).await
+/// Sends whatever is due each time the schedule fires.
+async fn run_outbox_worker() -> Result<(), Error> {
+    let outbox = Outbox::from_env()?;
+    let transport = Mailer::from_env()?.transport;
+    lambda_runtime::run(service_fn(move |_: LambdaEvent<serde_json::Value>| {
+        let outbox = outbox.clone();
+        let transport = transport.clone();
+        async move {
+            let sent = outbox.dispatch_due(transport.as_ref()).await?;
+            Ok::<_, Error>(serde_json::json!({ "sent": sent }))
+        }
+    }))
+    .await
 }
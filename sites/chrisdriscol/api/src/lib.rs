@@ -0,0 +1,33 @@
+//! GraphQL API for chrisdriscol.com, shared by the local axum server, the
+//! Lambda bootstrap and the tooling binaries.
+
+pub mod auth;
+pub mod caching;
+pub mod client;
+pub mod compression;
+pub mod content;
+pub mod cors;
+pub mod data;
+pub mod dates;
+pub mod dynamodb;
+pub mod email;
+pub mod filter;
+pub mod inbox;
+pub mod limits;
+pub mod lint;
+pub mod llms;
+pub mod node;
+pub mod origin;
+pub mod outbox;
+pub mod pagination;
+pub mod persisted;
+pub mod ratelimit;
+pub mod router;
+pub mod schema;
+pub mod search;
+pub mod signing;
+pub mod spam;
+pub mod sqlite;
+pub mod types;
+pub mod validation;
+pub mod verification;
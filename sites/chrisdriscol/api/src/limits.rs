@@ -0,0 +1,298 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use async_graphql::extensions::{
+    Extension, ExtensionContext, ExtensionFactory, NextParseQuery, NextValidation,
+};
+use async_graphql::parser::types::{ExecutableDocument, Selection, SelectionSet};
+use async_graphql::{
+    async_trait, ErrorExtensionValues, ObjectType, Positioned, SchemaBuilder, ServerError,
+    ServerResult, SubscriptionType, ValidationResult, Variables,
+};
+
+/// Assumed length of list fields that take no page size, such as `Chris.work`,
+/// when estimating query complexity.
+pub const UNBOUNDED_LIST_SIZE: usize = 20;
+/// Page size of connections and `search` when `first`/`last` are omitted,
+/// and the most a single page may hold.
+pub const DEFAULT_PAGE_SIZE: usize = 10;
+pub const MAX_PAGE_SIZE: usize = 50;
+
+/// Limits applied to every operation before it executes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QueryLimits {
+    /// Deepest field nesting allowed, checked during validation.
+    pub depth: usize,
+    /// Highest total complexity allowed; list fields multiply the cost of
+    /// their children by the number of items they may return.
+    pub complexity: usize,
+    /// Deepest selection-set nesting (including fragment spreads) the parser
+    /// accepts before validation runs.
+    pub recursive_depth: usize,
+    /// Most aliased fields allowed in one document.
+    pub aliases: usize,
+    /// Most field selections allowed in one document, counting fragments once
+    /// per spread.
+    pub fields: usize,
+}
+
+impl Default for QueryLimits {
+    fn default() -> Self {
+        Self {
+            // The standard introspection query nests about 13 levels deep.
+            depth: 15,
+            complexity: 1000,
+            recursive_depth: 32,
+            aliases: 20,
+            fields: 500,
+        }
+    }
+}
+
+impl QueryLimits {
+    /// Reads overrides from `GRAPHQL_MAX_DEPTH`, `GRAPHQL_MAX_COMPLEXITY`,
+    /// `GRAPHQL_MAX_RECURSIVE_DEPTH`, `GRAPHQL_MAX_ALIASES` and
+    /// `GRAPHQL_MAX_FIELDS`.
+    pub fn from_env() -> Result<Self, String> {
+        let defaults = Self::default();
+        Ok(Self {
+            depth: env_usize("GRAPHQL_MAX_DEPTH", defaults.depth)?,
+            complexity: env_usize("GRAPHQL_MAX_COMPLEXITY", defaults.complexity)?,
+            recursive_depth: env_usize("GRAPHQL_MAX_RECURSIVE_DEPTH", defaults.recursive_depth)?,
+            aliases: env_usize("GRAPHQL_MAX_ALIASES", defaults.aliases)?,
+            fields: env_usize("GRAPHQL_MAX_FIELDS", defaults.fields)?,
+        })
+    }
+
+    /// Installs the limits on a schema under construction.
+    pub fn apply<Query, Mutation, Subscription>(
+        self,
+        builder: SchemaBuilder<Query, Mutation, Subscription>,
+    ) -> SchemaBuilder<Query, Mutation, Subscription>
+    where
+        Query: ObjectType + 'static,
+        Mutation: ObjectType + 'static,
+        Subscription: SubscriptionType + 'static,
+    {
+        builder
+            .limit_depth(self.depth)
+            .limit_complexity(self.complexity)
+            .limit_recursive_depth(self.recursive_depth)
+            .extension(self)
+    }
+}
+
+fn env_usize(name: &str, default: usize) -> Result<usize, String> {
+    match std::env::var(name) {
+        Ok(value) => value
+            .trim()
+            .parse()
+            .map_err(|err| format!("invalid {name} {value:?}: {err}")),
+        Err(_) => Ok(default),
+    }
+}
+
+/// Complexity of a list field paged with `first`/`last`: the children are
+/// charged once per item the page may hold.
+pub fn page_complexity(first: Option<i32>, last: Option<i32>, child_complexity: usize) -> usize {
+    let size = first
+        .or(last)
+        .and_then(|size| usize::try_from(size).ok())
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .min(MAX_PAGE_SIZE);
+    size.max(1) * child_complexity
+}
+
+fn limit_error(limit: &str, max: usize, actual: Option<usize>, message: String) -> ServerError {
+    let mut error = ServerError::new(message, None);
+    let mut extensions = ErrorExtensionValues::default();
+    extensions.set("code", "QUERY_LIMIT_EXCEEDED");
+    extensions.set("limit", limit);
+    extensions.set("max", max);
+    if let Some(actual) = actual {
+        extensions.set("actual", actual);
+    }
+    error.extensions = Some(extensions);
+    error
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Counts {
+    aliases: usize,
+    fields: usize,
+}
+
+impl Counts {
+    /// Saturates, since fragments spread many times can multiply the counts
+    /// past `usize`.
+    fn add(&mut self, other: Counts) {
+        self.aliases = self.aliases.saturating_add(other.aliases);
+        self.fields = self.fields.saturating_add(other.fields);
+    }
+}
+
+/// Walks the fragments of one document, counting each fragment's selections
+/// once however often it is spread.
+struct Counter<'a> {
+    doc: &'a ExecutableDocument,
+    fragments: HashMap<&'a str, Counts>,
+    /// Fragments being expanded. Spreads of these are skipped; cyclic
+    /// fragments are rejected by validation anyway.
+    active: HashSet<&'a str>,
+}
+
+impl<'a> Counter<'a> {
+    /// Counts field selections and aliases, expanding fragment spreads.
+    fn count(&mut self, selection_set: &'a Positioned<SelectionSet>) -> Counts {
+        let mut counts = Counts::default();
+        for selection in &selection_set.node.items {
+            match &selection.node {
+                Selection::Field(field) => {
+                    counts.add(Counts {
+                        aliases: usize::from(field.node.alias.is_some()),
+                        fields: 1,
+                    });
+                    counts.add(self.count(&field.node.selection_set));
+                }
+                Selection::FragmentSpread(spread) => counts.add(self.count_fragment(&spread.node.fragment_name.node)),
+                Selection::InlineFragment(fragment) => counts.add(self.count(&fragment.node.selection_set)),
+            }
+        }
+        counts
+    }
+
+    fn count_fragment(&mut self, name: &'a str) -> Counts {
+        if let Some(counts) = self.fragments.get(name) {
+            return *counts;
+        }
+        let Some(fragment) = self.doc.fragments.get(name) else {
+            return Counts::default();
+        };
+        if !self.active.insert(name) {
+            return Counts::default();
+        }
+        let counts = self.count(&fragment.node.selection_set);
+        self.active.remove(name);
+        self.fragments.insert(name, counts);
+        counts
+    }
+}
+
+fn count_document(doc: &ExecutableDocument) -> Counts {
+    let mut counter = Counter {
+        doc,
+        fragments: HashMap::new(),
+        active: HashSet::new(),
+    };
+    let mut counts = Counts::default();
+    for (_, operation) in doc.operations.iter() {
+        counts.add(counter.count(&operation.node.selection_set));
+    }
+    counts
+}
+
+impl ExtensionFactory for QueryLimits {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(*self)
+    }
+}
+
+/// Adds the alias and field-count checks and gives the built-in depth,
+/// complexity and recursion errors a machine-readable `code`.
+#[async_trait::async_trait]
+impl Extension for QueryLimits {
+    async fn parse_query(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        query: &str,
+        variables: &Variables,
+        next: NextParseQuery<'_>,
+    ) -> ServerResult<ExecutableDocument> {
+        let doc = next.run(ctx, query, variables).await.map_err(|err| {
+            if err.message.starts_with("The recursion depth of the query") {
+                let mut limited = limit_error("recursiveDepth", self.recursive_depth, None, err.message);
+                limited.locations = err.locations;
+                limited
+            } else {
+                err
+            }
+        })?;
+
+        let counts = count_document(&doc);
+        if counts.aliases > self.aliases {
+            return Err(limit_error(
+                "aliases",
+                self.aliases,
+                Some(counts.aliases),
+                format!("Query uses {} aliases; at most {} are allowed.", counts.aliases, self.aliases),
+            ));
+        }
+        if counts.fields > self.fields {
+            return Err(limit_error(
+                "fields",
+                self.fields,
+                Some(counts.fields),
+                format!("Query selects {} fields; at most {} are allowed.", counts.fields, self.fields),
+            ));
+        }
+        Ok(doc)
+    }
+
+    async fn validation(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        next: NextValidation<'_>,
+    ) -> Result<ValidationResult, Vec<ServerError>> {
+        next.run(ctx).await.map_err(|errors| {
+            errors
+                .into_iter()
+                .map(|err| match err.message.as_str() {
+                    "Query is too complex." => {
+                        limit_error("complexity", self.complexity, None, err.message)
+                    }
+                    "Query is nested too deep." => limit_error("depth", self.depth, None, err.message),
+                    _ => err,
+                })
+                .collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_graphql::parser::parse_query;
+
+    #[test]
+    fn counts_fields_through_fragments() {
+        let doc = parse_query(
+            "{ a: chris { title ...F } b: chris { ...F } } fragment F on Chris { id work { title } }",
+        )
+        .unwrap();
+        let counts = count_document(&doc);
+        assert_eq!(counts.aliases, 2);
+        assert_eq!(counts.fields, 9);
+    }
+
+    #[test]
+    fn counts_repeated_fragment_spreads_without_expanding_them() {
+        // Each fragment spreads the previous one twice, doubling the count.
+        let mut query = "{ chris { ...F30 } } fragment F0 on Chris { title }".to_string();
+        for level in 1..=30 {
+            let previous = level - 1;
+            query.push_str(&format!(" fragment F{level} on Chris {{ ...F{previous} ...F{previous} }}"));
+        }
+        let doc = parse_query(&query).unwrap();
+        let counts = count_document(&doc);
+        assert_eq!(counts.fields, 1 + (1 << 30));
+        assert_eq!(counts.aliases, 0);
+    }
+
+    #[test]
+    fn pages_are_charged_per_item() {
+        assert_eq!(page_complexity(Some(3), None, 4), 12);
+        assert_eq!(page_complexity(None, Some(500), 2), MAX_PAGE_SIZE * 2);
+        assert_eq!(page_complexity(None, None, 1), DEFAULT_PAGE_SIZE);
+        assert_eq!(page_complexity(Some(-1), None, 1), DEFAULT_PAGE_SIZE);
+    }
+}
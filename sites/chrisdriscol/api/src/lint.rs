@@ -0,0 +1,364 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use crate::dates::DateRange;
+use crate::types::Chris;
+
+/// Tags the web client knows how to render inside description strings.
+const ALLOWED_TAGS: &[&str] = &["strong", "i", "em", "b", "br"];
+
+/// Tags that never have a closing counterpart.
+const VOID_TAGS: &[&str] = &["br"];
+
+/// Work entries whose repeated description paragraphs are known and waiting
+/// on the content owner, so the lint stays usable in the meantime.
+const ALLOWED_DUPLICATE_PARAGRAPHS: &[&str] = &[
+    // Copy of the cisternogram description; the real text is still to come.
+    "d-spect-qc",
+];
+
+/// Characters that show up when UTF-8 punctuation is decoded as Windows-1252.
+const MOJIBAKE_TRAILERS: &str = "€‚ƒ„…†‡ˆ‰Š‹ŒŽ‘’“”•–—˜™š›œžŸ";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+#[derive(Default)]
+struct Linter {
+    issues: Vec<Issue>,
+    /// Normalized technology name -> (spelling, first path it was seen at).
+    technologies: BTreeMap<String, Vec<(String, String)>>,
+}
+
+impl Linter {
+    fn issue(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.issues.push(Issue {
+            path: path.into(),
+            message: message.into(),
+        });
+    }
+
+    fn required(&mut self, path: &str, value: Option<&str>) {
+        match value {
+            Some(value) if !value.trim().is_empty() => self.text(path, value),
+            _ => self.issue(path, "required field is empty"),
+        }
+    }
+
+    fn optional(&mut self, path: &str, value: Option<&str>) {
+        if let Some(value) = value {
+            if value.trim().is_empty() {
+                self.issue(path, "field is present but empty");
+            } else {
+                self.text(path, value);
+            }
+        }
+    }
+
+    fn paragraphs(&mut self, path: &str, values: &[String]) {
+        if values.is_empty() {
+            self.issue(path, "required list is empty");
+        }
+        for (index, value) in values.iter().enumerate() {
+            self.required(&format!("{path}[{index}]"), Some(value));
+        }
+    }
+
+    fn dates(&mut self, path: &str, value: Option<&DateRange>) {
+        match value.map(DateRange::validate) {
+            Some(Ok(())) => {}
+            Some(Err(err)) => self.issue(path, err),
+            None => self.issue(path, "required field is empty"),
+        }
+    }
+
+    /// Slugs feed global IDs, so they must be unique per type and stay
+    /// URL-friendly.
+    fn slug<'a>(&mut self, path: &str, value: &'a str, seen: &mut HashSet<&'a str>) {
+        if value.is_empty() {
+            self.issue(path, "required field is empty");
+        } else if !value.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-') {
+            self.issue(path, format!("slug \"{value}\" must be lowercase letters, digits and dashes"));
+        } else if !seen.insert(value) {
+            self.issue(path, format!("duplicate slug \"{value}\""));
+        }
+    }
+
+    fn text(&mut self, path: &str, value: &str) {
+        if let Some(found) = find_mojibake(value) {
+            self.issue(path, format!("mis-decoded UTF-8 near \"{found}\""));
+        }
+        for problem in check_html(value) {
+            self.issue(path, problem);
+        }
+    }
+
+    fn names(&mut self, path: &str, values: &[String]) {
+        let mut seen = HashSet::new();
+        for (index, value) in values.iter().enumerate() {
+            let item_path = format!("{path}[{index}]");
+            self.required(&item_path, Some(value));
+            if !seen.insert(value.to_lowercase()) {
+                self.issue(&item_path, format!("duplicate entry \"{value}\""));
+            }
+            self.technologies
+                .entry(normalize_name(value))
+                .or_default()
+                .push((value.clone(), item_path));
+        }
+    }
+
+    fn near_duplicates(&mut self) {
+        let technologies = std::mem::take(&mut self.technologies);
+        for spellings in technologies.values() {
+            let mut distinct: Vec<&(String, String)> = Vec::new();
+            for entry in spellings {
+                if !distinct.iter().any(|seen| seen.0 == entry.0) {
+                    distinct.push(entry);
+                }
+            }
+            if distinct.len() > 1 {
+                let names = distinct
+                    .iter()
+                    .map(|(name, path)| format!("\"{name}\" ({path})"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                self.issue(&distinct[1].1, format!("inconsistent naming: {names}"));
+            }
+        }
+    }
+}
+
+/// Walks the whole portfolio and reports every content problem found.
+pub fn lint(chris: &Chris) -> Vec<Issue> {
+    let mut linter = Linter::default();
+
+    linter.required("id", Some(&chris.slug));
+    linter.required("title", Some(&chris.title));
+    linter.required("description", Some(&chris.description));
+
+    linter.paragraphs("about.description", &chris.about.description);
+    linter.optional("about.imageUrl", chris.about.image_url.as_deref());
+    linter.optional("about.imageCaption", chris.about.image_caption.as_deref());
+    linter.optional("about.imageTitle", chris.about.image_title.as_deref());
+    linter.optional("about.tagLine", chris.about.tag_line.as_deref());
+
+    linter.names("skills.languages", &chris.skills.languages);
+    linter.names("skills.technologies", &chris.skills.technologies);
+    linter.names("skills.tools", &chris.skills.tools);
+    linter.names("skills.loves", &chris.skills.loves);
+
+    let mut experiences = HashSet::new();
+    let mut slugs = HashSet::new();
+    for (index, exp) in chris.experience.iter().enumerate() {
+        let path = format!("experience[{index}]");
+        linter.slug(&format!("{path}.slug"), &exp.slug, &mut slugs);
+        linter.dates(&format!("{path}.dates"), exp.dates.as_ref());
+        linter.optional(&format!("{path}.title"), exp.title.as_deref());
+        linter.required(&format!("{path}.location"), exp.location.as_deref());
+        linter.required(&format!("{path}.description"), exp.description.as_deref());
+        linter.optional(&format!("{path}.imageUrl"), exp.image_url.as_deref());
+        if !experiences.insert((&exp.title, &exp.location, &exp.dates)) {
+            linter.issue(&path, "duplicate experience entry");
+        }
+    }
+
+    let mut titles = HashSet::new();
+    let mut paragraphs: BTreeMap<&str, String> = BTreeMap::new();
+    let mut slugs = HashSet::new();
+    for (index, work) in chris.work.iter().enumerate() {
+        let path = format!("work[{index}]");
+        linter.slug(&format!("{path}.slug"), &work.slug, &mut slugs);
+        linter.required(&format!("{path}.title"), work.title.as_deref());
+        linter.optional(&format!("{path}.subTitle"), work.sub_title.as_deref());
+        linter.dates(&format!("{path}.dates"), work.dates.as_ref());
+        linter.optional(&format!("{path}.location"), work.location.as_deref());
+        linter.optional(&format!("{path}.link"), work.link.as_deref());
+        linter.optional(&format!("{path}.video"), work.video.as_deref());
+        linter.optional(&format!("{path}.imageUrl"), work.image_url.as_deref());
+        linter.paragraphs(&format!("{path}.description"), &work.description);
+        linter.names(&format!("{path}.technologies"), &work.technologies);
+
+        if let Some(title) = &work.title {
+            if !titles.insert(title.to_lowercase()) {
+                linter.issue(&path, format!("duplicate work entry \"{title}\""));
+            }
+        }
+        for (paragraph_index, paragraph) in work.description.iter().enumerate() {
+            let paragraph_path = format!("{path}.description[{paragraph_index}]");
+            match paragraphs.get(paragraph.as_str()) {
+                Some(_) if ALLOWED_DUPLICATE_PARAGRAPHS.contains(&work.slug.as_str()) => {}
+                Some(first) => {
+                    let message = format!("paragraph duplicates {first}");
+                    linter.issue(paragraph_path, message);
+                }
+                None => {
+                    paragraphs.insert(paragraph, paragraph_path);
+                }
+            }
+        }
+    }
+
+    linter.required("social.linkedIn", Some(&chris.social.linked_in));
+    linter.required("social.github", Some(&chris.social.github));
+    linter.required("social.email", Some(&chris.social.email));
+
+    linter.near_duplicates();
+    linter.issues
+}
+
+/// Collapses case, whitespace and punctuation so "React-Native" and
+/// "React Native" compare equal. The technology filter matches names the
+/// same way.
+pub(crate) fn normalize_name(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == '#' || *c == '+')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn find_mojibake(value: &str) -> Option<String> {
+    if value.contains('\u{FFFD}') {
+        return Some("\u{FFFD}".to_string());
+    }
+    let chars: Vec<char> = value.chars().collect();
+    chars.windows(2).find_map(|pair| {
+        let lead = matches!(pair[0], 'â' | 'Ã' | 'Â');
+        let trail = ('\u{80}'..='\u{BF}').contains(&pair[1]) || MOJIBAKE_TRAILERS.contains(pair[1]);
+        (lead && trail).then(|| pair.iter().collect())
+    })
+}
+
+fn check_html(value: &str) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut open: Vec<String> = Vec::new();
+    let mut rest = value;
+
+    while let Some(start) = rest.find('<') {
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('>') else {
+            problems.push("unterminated tag".to_string());
+            break;
+        };
+        let tag = after[..end].trim();
+        rest = &after[end + 1..];
+
+        let closing = tag.starts_with('/');
+        let self_closing = tag.ends_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .trim_end_matches('/')
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+
+        if !ALLOWED_TAGS.contains(&name.as_str()) {
+            problems.push(format!("unknown tag <{tag}>"));
+            continue;
+        }
+        if self_closing || VOID_TAGS.contains(&name.as_str()) {
+            continue;
+        }
+        if closing {
+            match open.pop() {
+                Some(expected) if expected == name => {}
+                Some(expected) => {
+                    problems.push(format!("</{name}> closes <{expected}>"));
+                }
+                None => problems.push(format!("</{name}> has no opening tag")),
+            }
+        } else {
+            open.push(name);
+        }
+    }
+
+    for name in open {
+        problems.push(format!("<{name}> is never closed"));
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data;
+
+    #[test]
+    fn shipped_content_is_clean() {
+        let chris = data::load(None).expect("compiled-in content");
+        let issues = lint(&chris);
+        assert!(
+            issues.is_empty(),
+            "content issues:\n{}",
+            issues.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n")
+        );
+    }
+
+    #[test]
+    fn detects_mojibake() {
+        assert_eq!(find_mojibake("Fall 2004 â€“ Spring 2008").as_deref(), Some("â€"));
+        assert_eq!(find_mojibake("SalesForceâ€™s"), Some("â€".to_string()));
+        assert_eq!(find_mojibake("Fall 2004 – Spring 2008"), None);
+        assert_eq!(find_mojibake("crème brûlée"), None);
+    }
+
+    #[test]
+    fn checks_html_tags() {
+        assert!(check_html("I <i class=\"fa fa-heart\"></i> <strong>Agile</strong>").is_empty());
+        assert_eq!(check_html("<strong>bold"), vec!["<strong> is never closed"]);
+        assert_eq!(check_html("<strong><i>x</strong></i>"), vec![
+            "</strong> closes <i>",
+            "</i> closes <strong>",
+        ]);
+        assert_eq!(check_html("<script>x</script>"), vec![
+            "unknown tag <script>",
+            "unknown tag </script>",
+        ]);
+        assert_eq!(check_html("a < b"), vec!["unterminated tag"]);
+    }
+
+    #[test]
+    fn flags_empty_duplicate_and_inconsistent_entries() {
+        let mut chris = data::load(None).unwrap();
+        chris.title = " ".to_string();
+        chris.skills.tools.push("Github".to_string());
+        chris.work[0].technologies.push("React-Native".to_string());
+        chris.experience.push(chris.experience[0].clone());
+        let copied = chris.work[0].description[0].clone();
+        chris.work[1].description.push(copied);
+
+        let issues = lint(&chris);
+        let has = |path: &str, needle: &str| {
+            issues
+                .iter()
+                .any(|issue| issue.path == path && issue.message.contains(needle))
+        };
+
+        let tools = chris.skills.tools.len() - 1;
+        let techs = chris.work[0].technologies.len() - 1;
+        let last = chris.experience.len() - 1;
+        let paragraph = chris.work[1].description.len() - 1;
+        assert!(has("title", "required field is empty"), "{issues:?}");
+        assert!(has(&format!("skills.tools[{tools}]"), "duplicate entry"), "{issues:?}");
+        assert!(has(&format!("experience[{last}]"), "duplicate experience"), "{issues:?}");
+        assert!(
+            has(&format!("work[1].description[{paragraph}]"), "paragraph duplicates work[0]"),
+            "{issues:?}"
+        );
+        assert!(issues.iter().any(|issue| {
+            issue.message.contains("inconsistent naming")
+                && issue.message.contains(&format!("work[0].technologies[{techs}]"))
+        }), "{issues:?}");
+    }
+}
@@ -1,4 +1,5 @@
-use crate::data;
+use crate::dates::DateRange;
+use crate::types::Chris;
 
 /// Converts HTML tags to markdown
 fn html_to_markdown(text: &str) -> String {
@@ -10,8 +11,7 @@ fn html_to_markdown(text: &str) -> String {
 }
 
 /// Generates the llms.txt content from portfolio data
-pub fn generate_llms_txt() -> String {
-    let chris = data::chris();
+pub fn generate_llms_txt(chris: &Chris) -> String {
     let mut output = String::new();
 
     // H1 Title
@@ -48,8 +48,9 @@ pub fn generate_llms_txt() -> String {
     // Experience Section
     output.push_str("## Experience\n\n");
     for exp in &chris.experience {
+        let duration = exp.dates.as_ref().map(DateRange::legacy_label);
         if let (Some(title), Some(location), Some(duration)) =
-            (&exp.title, &exp.location, &exp.duration) {
+            (&exp.title, &exp.location, &duration) {
             output.push_str(&format!(
                 "### {} at {}\n\n",
                 html_to_markdown(title),
@@ -82,9 +83,10 @@ pub fn generate_llms_txt() -> String {
             }
 
             // Date and location
-            if let (Some(date), Some(location)) = (&project.date, &project.location) {
+            let date = project.dates.as_ref().map(DateRange::legacy_label);
+            if let (Some(date), Some(location)) = (&date, &project.location) {
                 output.push_str(&format!("**{}** | {}\n\n", html_to_markdown(date), html_to_markdown(location)));
-            } else if let Some(date) = &project.date {
+            } else if let Some(date) = &date {
                 output.push_str(&format!("**{}**\n\n", html_to_markdown(date)));
             }
 
@@ -99,7 +101,7 @@ pub fn generate_llms_txt() -> String {
             }
         }
     }
-    output.push_str("\n");
+    output.push('\n');
 
     // Links Section
     output.push_str("## Links\n\n");
@@ -125,7 +127,7 @@ mod tests {
 
     #[test]
     fn test_generate_llms_txt() {
-        let output = generate_llms_txt();
+        let output = generate_llms_txt(&crate::data::chris());
 
         // Check for required sections
         assert!(output.starts_with("# Chris Driscol"));
@@ -1,52 +1,15 @@
-use async_graphql::{ErrorExtensionValues, Response, ServerError};
-use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
-use axum::{http::HeaderMap, response::IntoResponse, routing::{get, post}, Extension, Router};
 use std::net::SocketAddr;
 
-mod schema;
-mod types;
-mod data;
-mod email;
-mod llms;
-
-use schema::AppSchema;
-
-async fn graphql_handler(
-    Extension(schema): Extension<AppSchema>,
-    headers: HeaderMap,
-    request: GraphQLRequest,
-) -> GraphQLResponse {
-    if let Ok(expected) = std::env::var("GRAPHQL_ORIGIN_SECRET") {
-        let provided = headers
-            .get("x-origin-secret")
-            .and_then(|value| value.to_str().ok());
-        if provided != Some(expected.as_str()) {
-            let mut error = ServerError::new("Forbidden", None);
-            let mut extensions = ErrorExtensionValues::default();
-            extensions.set("code", "FORBIDDEN");
-            error.extensions = Some(extensions);
-            return Response::from_errors(vec![error]).into();
-        }
-    }
-    schema.execute(request.into_inner()).await.into()
-}
-
-async fn llms_handler() -> impl IntoResponse {
-    let content = llms::generate_llms_txt();
-    (
-        [("content-type", "text/markdown; charset=utf-8")],
-        content,
-    )
-}
+use chris_driscol_api::{router, schema};
 
 #[tokio::main]
 async fn main() {
-    let schema = schema::build_schema();
-
-    let app = Router::new()
-        .route("/graphql", post(graphql_handler))
-        .route("/llms.txt", get(llms_handler))
-        .layer(Extension(schema));
+    let config = schema::SchemaConfig::from_env().expect("invalid schema config");
+    tokio::spawn(config.outbox.clone().run(config.mailer.transport.clone()));
+    let content = config.content.clone();
+    let schema = schema::build_schema_with(config);
+    let config = router::RouterConfig::from_env().expect("invalid router config");
+    let app = router::router(schema, content, &config);
 
     let port = std::env::var("PORT")
         .ok()
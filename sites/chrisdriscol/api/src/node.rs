@@ -0,0 +1,66 @@
+use async_graphql::{Interface, ID};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use crate::types::{Chris, Experience, Skills, Work};
+
+/// Objects that can be refetched by global ID through `node(id:)`.
+#[derive(Interface)]
+#[graphql(field(name = "id", ty = "ID"))]
+pub enum Node {
+    Chris(Chris),
+    Skills(Skills),
+    Experience(Experience),
+    Work(Work),
+}
+
+/// Builds an opaque Relay global ID from a GraphQL type name and the
+/// entity's content slug.
+pub fn global_id(type_name: &str, slug: &str) -> ID {
+    ID(STANDARD.encode(format!("{type_name}:{slug}")))
+}
+
+/// Splits a global ID back into its type name and slug.
+pub fn parse_global_id(id: &str) -> Option<(String, String)> {
+    let decoded = String::from_utf8(STANDARD.decode(id).ok()?).ok()?;
+    let (type_name, slug) = decoded.split_once(':')?;
+    Some((type_name.to_string(), slug.to_string()))
+}
+
+/// Looks up the entity a global ID refers to in the portfolio.
+pub fn fetch(chris: &Chris, id: &str) -> Option<Node> {
+    let (type_name, slug) = parse_global_id(id)?;
+    match type_name.as_str() {
+        "Chris" if slug == chris.slug => Some(Node::Chris(chris.clone())),
+        "Skills" if slug == chris.skills.slug => Some(Node::Skills(chris.skills.clone())),
+        "Experience" => chris
+            .experience
+            .iter()
+            .find(|exp| exp.slug == slug)
+            .cloned()
+            .map(Node::Experience),
+        "Work" => chris
+            .work
+            .iter()
+            .find(|work| work.slug == slug)
+            .cloned()
+            .map(Node::Work),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn global_ids_round_trip() {
+        let id = global_id("Work", "nuke-notes");
+        assert_eq!(
+            parse_global_id(&id),
+            Some(("Work".to_string(), "nuke-notes".to_string()))
+        );
+        assert_eq!(parse_global_id("not base64!"), None);
+        assert_eq!(parse_global_id(&STANDARD.encode("no-separator")), None);
+    }
+}
@@ -0,0 +1,129 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use axum::http::HeaderMap;
+
+use crate::signing::{constant_time_eq, sha256};
+
+const SECRETS_ENV: &str = "GRAPHQL_ORIGIN_SECRET";
+const HEADER: &str = "x-origin-secret";
+/// Nothing has been logged yet.
+const NONE_LOGGED: usize = usize::MAX;
+
+struct OriginKey {
+    /// First 8 hex digits of the secret's SHA-256; safe to log.
+    id: String,
+    hash: [u8; 32],
+}
+
+/// Secrets CloudFront adds to origin requests as `x-origin-secret`. Several
+/// can be valid at once so the secret can be rotated without downtime:
+/// deploy `new,old`, switch CloudFront to the new one, then drop the old.
+#[derive(Clone, Default)]
+pub struct OriginSecrets {
+    keys: Arc<[OriginKey]>,
+    /// Index of the key last reported as matching.
+    last_logged: Arc<AtomicUsize>,
+}
+
+impl std::fmt::Debug for OriginSecrets {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ids: Vec<&str> = self.keys.iter().map(|key| key.id.as_str()).collect();
+        f.debug_struct("OriginSecrets").field("key_ids", &ids).finish()
+    }
+}
+
+/// Short, non-reversible name for `secret`.
+pub fn key_id(secret: &str) -> String {
+    sha256(secret)[..4].iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+impl OriginSecrets {
+    /// `secrets` is comma separated; blank entries are ignored.
+    pub fn new(secrets: &str) -> Self {
+        let keys: Vec<OriginKey> = secrets
+            .split(',')
+            .map(str::trim)
+            .filter(|secret| !secret.is_empty())
+            .map(|secret| OriginKey {
+                id: key_id(secret),
+                hash: sha256(secret),
+            })
+            .collect();
+        Self {
+            keys: keys.into(),
+            last_logged: Arc::new(AtomicUsize::new(NONE_LOGGED)),
+        }
+    }
+
+    /// Reads `GRAPHQL_ORIGIN_SECRET`. Unset lets every request through.
+    pub fn from_env() -> Result<Self, String> {
+        Ok(Self::new(&std::env::var(SECRETS_ENV).unwrap_or_default()))
+    }
+
+    /// Index of the configured key `headers` carry, checking every key so the
+    /// time taken does not depend on which one matched.
+    fn matching_key(&self, headers: &HeaderMap) -> Option<usize> {
+        let provided = headers.get(HEADER).map(|value| sha256(value.to_str().unwrap_or_default()))?;
+        self.keys.iter().enumerate().fold(None, |found, (index, key)| {
+            if constant_time_eq(&key.hash, &provided) {
+                Some(index)
+            } else {
+                found
+            }
+        })
+    }
+
+    /// Whether the request may proceed. Logs the matching key ID whenever it
+    /// differs from the last one logged, which shows when CloudFront has
+    /// moved to a new secret.
+    pub fn check(&self, headers: &HeaderMap) -> bool {
+        if self.keys.is_empty() {
+            return true;
+        }
+        let Some(index) = self.matching_key(headers) else {
+            return false;
+        };
+        if self.last_logged.swap(index, Ordering::Relaxed) != index {
+            let entry = serde_json::json!({
+                "event": "origin_secret_matched",
+                "key_id": self.keys[index].id,
+            });
+            eprintln!("{entry}");
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn with_secret(secret: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(HEADER, HeaderValue::from_static(secret));
+        headers
+    }
+
+    #[test]
+    fn accepts_any_configured_secret() {
+        assert!(OriginSecrets::new("").check(&HeaderMap::new()));
+
+        let secrets = OriginSecrets::new("new-secret, old-secret");
+        assert!(secrets.check(&with_secret("new-secret")));
+        assert!(secrets.check(&with_secret("old-secret")));
+        assert!(!secrets.check(&with_secret("wrong")));
+        assert!(!secrets.check(&with_secret("new-secret, old-secret")));
+        assert!(!secrets.check(&HeaderMap::new()));
+    }
+
+    #[test]
+    fn reports_which_key_matched() {
+        let secrets = OriginSecrets::new("new-secret,old-secret");
+        assert_eq!(secrets.matching_key(&with_secret("old-secret")), Some(1));
+        assert_eq!(key_id("old-secret").len(), 8);
+        assert_ne!(key_id("old-secret"), key_id("new-secret"));
+        assert!(!format!("{secrets:?}").contains("old-secret"), "{secrets:?}");
+    }
+}
@@ -0,0 +1,428 @@
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_graphql::async_trait;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+
+use crate::dynamodb::Table;
+use crate::email::{Email, EmailTransport};
+use crate::signing;
+
+mod dynamodb;
+mod memory;
+mod sqlite;
+
+pub use dynamodb::DynamoOutboxStore;
+pub use memory::MemoryOutboxStore;
+pub use sqlite::SqliteOutboxStore;
+
+const DEFAULT_PATH: &str = "outbox.sqlite3";
+/// How long a claimed message is hidden from other dispatchers. Longer than
+/// any transport takes to give up.
+const LEASE: Duration = Duration::from_secs(5 * 60);
+/// Messages claimed per dispatch run.
+const BATCH: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutboxStatus {
+    Pending,
+    Sent,
+    /// Gave up after `max_attempts`; kept for someone to look at.
+    Dead,
+}
+
+impl OutboxStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Sent => "sent",
+            Self::Dead => "dead",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "pending" => Ok(Self::Pending),
+            "sent" => Ok(Self::Sent),
+            "dead" => Ok(Self::Dead),
+            other => Err(format!("unknown outbox status {other:?}")),
+        }
+    }
+}
+
+/// An email waiting for, or done with, delivery. Times are since the Unix
+/// epoch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutboxMessage {
+    pub id: String,
+    pub email: Email,
+    pub status: OutboxStatus,
+    pub attempts: u32,
+    /// When the next delivery may start. Claiming a message pushes this out
+    /// by the lease.
+    pub next_attempt_at: Duration,
+    pub last_error: Option<String>,
+    pub created_at: Duration,
+}
+
+/// Where the outbox keeps messages. Implementations must have persisted a
+/// message by the time `insert` returns.
+#[async_trait::async_trait]
+pub trait OutboxStore: Send + Sync {
+    async fn insert(&self, message: &OutboxMessage) -> Result<(), String>;
+
+    /// Pending messages due at `now`, oldest first. Their next attempt moves
+    /// to `lease_until` so concurrent dispatchers skip them.
+    async fn claim_due(
+        &self,
+        now: Duration,
+        lease_until: Duration,
+        limit: usize,
+    ) -> Result<Vec<OutboxMessage>, String>;
+
+    /// Claims message `id` the way `claim_due` would, or returns `None` if it
+    /// is not pending and due at `now`.
+    async fn claim(&self, id: &str, now: Duration, lease_until: Duration) -> Result<Option<OutboxMessage>, String>;
+
+    /// Saves the outcome of a delivery attempt.
+    async fn update(&self, message: &OutboxMessage) -> Result<(), String>;
+
+    async fn get(&self, id: &str) -> Result<Option<OutboxMessage>, String>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutboxConfig {
+    /// Attempts before a message is dead-lettered.
+    pub max_attempts: u32,
+    /// Delay after the first failure; each further failure doubles it.
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// How often the background worker looks for due messages.
+    pub poll_interval: Duration,
+}
+
+impl Default for OutboxConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 8,
+            base_delay: Duration::from_secs(30),
+            max_delay: Duration::from_secs(60 * 60),
+            poll_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+impl OutboxConfig {
+    /// Reads `CONTACT_OUTBOX_MAX_ATTEMPTS`, `CONTACT_OUTBOX_RETRY_SECONDS`,
+    /// `CONTACT_OUTBOX_MAX_RETRY_SECONDS` and `CONTACT_OUTBOX_POLL_SECONDS`.
+    pub fn from_env() -> Result<Self, String> {
+        let defaults = Self::default();
+        let number = |name: &str, default: u64| match std::env::var(name) {
+            Ok(value) => value
+                .parse::<u64>()
+                .map_err(|_| format!("{name} must be a number, got {value:?}")),
+            Err(_) => Ok(default),
+        };
+        let max_attempts = number("CONTACT_OUTBOX_MAX_ATTEMPTS", defaults.max_attempts.into())?;
+        if max_attempts == 0 {
+            return Err("CONTACT_OUTBOX_MAX_ATTEMPTS must be at least 1".to_string());
+        }
+        Ok(Self {
+            max_attempts: u32::try_from(max_attempts).unwrap_or(u32::MAX),
+            base_delay: Duration::from_secs(number(
+                "CONTACT_OUTBOX_RETRY_SECONDS",
+                defaults.base_delay.as_secs(),
+            )?),
+            max_delay: Duration::from_secs(number(
+                "CONTACT_OUTBOX_MAX_RETRY_SECONDS",
+                defaults.max_delay.as_secs(),
+            )?),
+            poll_interval: Duration::from_secs(number(
+                "CONTACT_OUTBOX_POLL_SECONDS",
+                defaults.poll_interval.as_secs(),
+            )?),
+        })
+    }
+
+    /// Wait before the next attempt once `attempts` have failed.
+    pub fn backoff(&self, attempts: u32) -> Duration {
+        let doublings = attempts.saturating_sub(1).min(31);
+        self.base_delay
+            .saturating_mul(1 << doublings)
+            .min(self.max_delay)
+    }
+}
+
+fn unix_now() -> Duration {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default()
+}
+
+/// A fresh submission ID.
+pub fn new_id() -> String {
+    URL_SAFE_NO_PAD.encode(signing::random_bytes::<12>())
+}
+
+/// Stores outgoing email before sending it, so a transport outage delays
+/// messages instead of losing them.
+#[derive(Clone)]
+pub struct Outbox {
+    pub store: Arc<dyn OutboxStore>,
+    pub config: OutboxConfig,
+}
+
+impl Default for Outbox {
+    fn default() -> Self {
+        Self {
+            store: Arc::new(MemoryOutboxStore::default()),
+            config: OutboxConfig::default(),
+        }
+    }
+}
+
+impl Outbox {
+    /// Picks the store named by `CONTACT_OUTBOX`: `sqlite` (default, a file
+    /// at `CONTACT_OUTBOX_PATH`), `dynamodb` (the table named by
+    /// `CONTACT_OUTBOX_TABLE`) or `memory`.
+    pub fn from_env() -> Result<Self, String> {
+        let store: Arc<dyn OutboxStore> = match std::env::var("CONTACT_OUTBOX").as_deref() {
+            Err(_) | Ok("sqlite") => {
+                let path = std::env::var("CONTACT_OUTBOX_PATH").unwrap_or_else(|_| DEFAULT_PATH.to_string());
+                Arc::new(SqliteOutboxStore::new(path))
+            }
+            Ok("dynamodb") => Arc::new(DynamoOutboxStore::new(Table::from_env("CONTACT_OUTBOX_TABLE")?)),
+            Ok("memory") => Arc::new(MemoryOutboxStore::default()),
+            Ok(other) => return Err(format!("unknown CONTACT_OUTBOX {other:?}")),
+        };
+        Ok(Self {
+            store,
+            config: OutboxConfig::from_env()?,
+        })
+    }
+
+    /// Persists `email` for delivery and returns its ID.
+    pub async fn enqueue(&self, email: Email) -> Result<String, String> {
+        self.enqueue_at(email, unix_now()).await
+    }
+
+    pub async fn enqueue_at(&self, email: Email, now: Duration) -> Result<String, String> {
+        let message = OutboxMessage {
+            id: new_id(),
+            email,
+            status: OutboxStatus::Pending,
+            attempts: 0,
+            next_attempt_at: now,
+            last_error: None,
+            created_at: now,
+        };
+        self.store.insert(&message).await?;
+        Ok(message.id)
+    }
+
+    /// Tries every due message once and returns how many were sent.
+    pub async fn dispatch_due(&self, transport: &dyn EmailTransport) -> Result<usize, String> {
+        self.dispatch_due_at(transport, unix_now()).await
+    }
+
+    pub async fn dispatch_due_at(&self, transport: &dyn EmailTransport, now: Duration) -> Result<usize, String> {
+        let mut sent = 0;
+        for message in self.store.claim_due(now, now + LEASE, BATCH).await? {
+            if self.deliver(message, transport, now).await? {
+                sent += 1;
+            }
+        }
+        Ok(sent)
+    }
+
+    /// Tries message `id` once if it is due and returns whether it was sent.
+    /// `contactMe` uses this so a request only ever waits on the email it
+    /// queued, leaving older messages to the worker.
+    pub async fn dispatch(&self, id: &str, transport: &dyn EmailTransport) -> Result<bool, String> {
+        self.dispatch_at(id, transport, unix_now()).await
+    }
+
+    pub async fn dispatch_at(&self, id: &str, transport: &dyn EmailTransport, now: Duration) -> Result<bool, String> {
+        match self.store.claim(id, now, now + LEASE).await? {
+            Some(message) => self.deliver(message, transport, now).await,
+            None => Ok(false),
+        }
+    }
+
+    /// Sends a claimed message and records the outcome.
+    async fn deliver(
+        &self,
+        mut message: OutboxMessage,
+        transport: &dyn EmailTransport,
+        now: Duration,
+    ) -> Result<bool, String> {
+        message.attempts += 1;
+        let sent = match transport.send(&message.email).await {
+            Ok(()) => {
+                message.status = OutboxStatus::Sent;
+                message.last_error = None;
+                true
+            }
+            Err(err) if message.attempts >= self.config.max_attempts => {
+                message.status = OutboxStatus::Dead;
+                let entry = serde_json::json!({
+                    "event": "outbox_dead_letter",
+                    "id": message.id,
+                    "attempts": message.attempts,
+                    "error": err,
+                });
+                eprintln!("{entry}");
+                message.last_error = Some(err);
+                false
+            }
+            Err(err) => {
+                message.next_attempt_at = now + self.config.backoff(message.attempts);
+                message.last_error = Some(err);
+                false
+            }
+        };
+        self.store.update(&message).await?;
+        Ok(sent)
+    }
+
+    /// Dispatches due messages every `poll_interval`, forever. This suits a
+    /// long-running server; Lambda freezes idle instances, so there a
+    /// scheduled invocation calls `dispatch_due` instead.
+    pub async fn run(self, transport: Arc<dyn EmailTransport>) {
+        let mut interval = tokio::time::interval(self.config.poll_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            if let Err(err) = self.dispatch_due(transport.as_ref()).await {
+                eprintln!("outbox: dispatch failed: {err}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    const NOW: Duration = Duration::from_secs(1_700_000_000);
+
+    /// Fails the first `failures` sends, then delivers.
+    struct Flaky {
+        failures: Mutex<u32>,
+        sent: Mutex<Vec<Email>>,
+    }
+
+    impl Flaky {
+        fn new(failures: u32) -> Self {
+            Self {
+                failures: Mutex::new(failures),
+                sent: Mutex::default(),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl EmailTransport for Flaky {
+        async fn send(&self, email: &Email) -> Result<(), String> {
+            let mut failures = self.failures.lock().unwrap();
+            if *failures > 0 {
+                *failures -= 1;
+                return Err("throttled".to_string());
+            }
+            self.sent.lock().unwrap().push(email.clone());
+            Ok(())
+        }
+    }
+
+    pub(super) fn email(subject: &str) -> Email {
+        Email {
+            from: "site@example.com".to_string(),
+            to: "me@example.com".to_string(),
+            reply_to: None,
+            subject: subject.to_string(),
+            text: "Nice site.".to_string(),
+            html: None,
+        }
+    }
+
+    fn outbox(max_attempts: u32) -> Outbox {
+        Outbox {
+            config: OutboxConfig {
+                max_attempts,
+                ..OutboxConfig::default()
+            },
+            ..Outbox::default()
+        }
+    }
+
+    #[test]
+    fn backs_off_exponentially_up_to_a_cap() {
+        let config = OutboxConfig::default();
+        assert_eq!(config.backoff(1), Duration::from_secs(30));
+        assert_eq!(config.backoff(2), Duration::from_secs(60));
+        assert_eq!(config.backoff(4), Duration::from_secs(240));
+        assert_eq!(config.backoff(8), Duration::from_secs(3600));
+        assert_eq!(config.backoff(200), Duration::from_secs(3600));
+    }
+
+    #[tokio::test]
+    async fn retries_failed_deliveries_after_a_backoff() {
+        let outbox = outbox(8);
+        let transport = Flaky::new(2);
+        let id = outbox.enqueue_at(email("Hello"), NOW).await.unwrap();
+
+        assert_eq!(outbox.dispatch_due_at(&transport, NOW).await, Ok(0));
+        let message = outbox.store.get(&id).await.unwrap().unwrap();
+        assert_eq!(message.status, OutboxStatus::Pending);
+        assert_eq!(message.last_error.as_deref(), Some("throttled"));
+        assert_eq!(message.next_attempt_at, NOW + Duration::from_secs(30));
+
+        // Not due yet.
+        assert_eq!(outbox.dispatch_due_at(&transport, NOW + Duration::from_secs(29)).await, Ok(0));
+        assert_eq!(outbox.dispatch_due_at(&transport, NOW + Duration::from_secs(30)).await, Ok(0));
+        assert_eq!(
+            outbox.store.get(&id).await.unwrap().unwrap().next_attempt_at,
+            NOW + Duration::from_secs(90)
+        );
+        assert_eq!(outbox.dispatch_due_at(&transport, NOW + Duration::from_secs(90)).await, Ok(1));
+
+        let message = outbox.store.get(&id).await.unwrap().unwrap();
+        assert_eq!(message.status, OutboxStatus::Sent);
+        assert_eq!(message.attempts, 3);
+        assert_eq!(transport.sent.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn dispatches_a_single_message_on_request() {
+        let outbox = outbox(8);
+        let transport = Flaky::new(0);
+        let older = outbox.enqueue_at(email("Older"), NOW).await.unwrap();
+        let id = outbox.enqueue_at(email("Hello"), NOW).await.unwrap();
+
+        assert_eq!(outbox.dispatch_at(&id, &transport, NOW).await, Ok(true));
+        // Already sent, so there is nothing left to claim.
+        assert_eq!(outbox.dispatch_at(&id, &transport, NOW).await, Ok(false));
+        assert_eq!(outbox.dispatch_at("nope", &transport, NOW).await, Ok(false));
+
+        let sent = transport.sent.lock().unwrap().clone();
+        assert_eq!(sent, vec![email("Hello")]);
+        assert_eq!(outbox.store.get(&older).await.unwrap().unwrap().status, OutboxStatus::Pending);
+    }
+
+    #[tokio::test]
+    async fn dead_letters_after_max_attempts() {
+        let outbox = outbox(2);
+        let transport = Flaky::new(u32::MAX);
+        let id = outbox.enqueue_at(email("Hello"), NOW).await.unwrap();
+
+        outbox.dispatch_due_at(&transport, NOW).await.unwrap();
+        outbox.dispatch_due_at(&transport, NOW + Duration::from_secs(30)).await.unwrap();
+        let message = outbox.store.get(&id).await.unwrap().unwrap();
+        assert_eq!(message.status, OutboxStatus::Dead);
+        assert_eq!(message.attempts, 2);
+
+        // Dead messages are never claimed again.
+        assert_eq!(outbox.dispatch_due_at(&transport, NOW + Duration::from_secs(86_400)).await, Ok(0));
+        assert_eq!(outbox.store.get(&id).await.unwrap().unwrap().attempts, 2);
+    }
+}
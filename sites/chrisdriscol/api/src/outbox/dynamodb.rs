@@ -0,0 +1,171 @@
+use std::time::Duration;
+
+use async_graphql::async_trait;
+use serde_json::{json, Value};
+
+use super::{OutboxMessage, OutboxStatus, OutboxStore};
+use crate::dynamodb::{
+    get_number, get_optional_string, get_string, item, items, number, optional_string, string, DynamoError, Item,
+    Table,
+};
+use crate::sqlite::{from_millis, millis};
+
+/// Index on `status` and `next_attempt_at` for finding due messages. It
+/// only projects keys; claiming reads the message back from the table.
+const DUE_INDEX: &str = "due";
+
+/// Only claims a message that is still pending and due, so two dispatchers
+/// racing for it cannot both win.
+const DUE: &str = "#status = :pending AND next_attempt_at <= :now";
+
+/// Keeps the outbox in a DynamoDB table keyed by `id`, shared by every
+/// instance. Times are stored as Unix milliseconds and emails as JSON, as
+/// in SQLite.
+pub struct DynamoOutboxStore {
+    table: Table,
+}
+
+impl DynamoOutboxStore {
+    pub fn new(table: Table) -> Self {
+        Self { table }
+    }
+}
+
+fn to_item(message: &OutboxMessage) -> Result<Value, String> {
+    let email = serde_json::to_string(&message.email).map_err(|err| format!("failed to encode email: {err}"))?;
+    Ok(json!({
+        "id": string(&message.id),
+        "email": string(&email),
+        "status": string(message.status.as_str()),
+        "attempts": number(message.attempts),
+        "next_attempt_at": number(millis(message.next_attempt_at)),
+        "last_error": optional_string(message.last_error.as_deref()),
+        "created_at": number(millis(message.created_at)),
+    }))
+}
+
+fn from_item(item: &Item) -> Result<OutboxMessage, String> {
+    let email = get_string(item, "email")?;
+    Ok(OutboxMessage {
+        id: get_string(item, "id")?,
+        email: serde_json::from_str(&email).map_err(|err| format!("failed to decode email: {err}"))?,
+        status: OutboxStatus::parse(&get_string(item, "status")?)?,
+        attempts: get_number(item, "attempts")?,
+        next_attempt_at: from_millis(get_number(item, "next_attempt_at")?),
+        last_error: get_optional_string(item, "last_error")?,
+        created_at: from_millis(get_number(item, "created_at")?),
+    })
+}
+
+#[async_trait::async_trait]
+impl OutboxStore for DynamoOutboxStore {
+    async fn insert(&self, message: &OutboxMessage) -> Result<(), String> {
+        let request = json!({
+            "Item": to_item(message)?,
+            "ConditionExpression": "attribute_not_exists(id)",
+        });
+        self.table.call("PutItem", request).await?;
+        Ok(())
+    }
+
+    async fn claim_due(
+        &self,
+        now: Duration,
+        lease_until: Duration,
+        limit: usize,
+    ) -> Result<Vec<OutboxMessage>, String> {
+        let request = json!({
+            "IndexName": DUE_INDEX,
+            "KeyConditionExpression": DUE,
+            "ExpressionAttributeNames": { "#status": "status" },
+            "ExpressionAttributeValues": {
+                ":pending": string(OutboxStatus::Pending.as_str()),
+                ":now": number(millis(now)),
+            },
+            "Limit": limit,
+        });
+        // The index lags the table slightly; `claim` checks each message
+        // again before leasing it.
+        let due = items(&self.table.call("Query", request).await?, "Items")?;
+        let mut claimed = Vec::new();
+        for key in due {
+            if let Some(message) = self.claim(&get_string(&key, "id")?, now, lease_until).await? {
+                claimed.push(message);
+            }
+        }
+        claimed.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+        Ok(claimed)
+    }
+
+    async fn claim(&self, id: &str, now: Duration, lease_until: Duration) -> Result<Option<OutboxMessage>, String> {
+        let request = json!({
+            "Key": { "id": string(id) },
+            "UpdateExpression": "SET next_attempt_at = :lease_until",
+            "ConditionExpression": DUE,
+            "ExpressionAttributeNames": { "#status": "status" },
+            "ExpressionAttributeValues": {
+                ":pending": string(OutboxStatus::Pending.as_str()),
+                ":now": number(millis(now)),
+                ":lease_until": number(millis(lease_until)),
+            },
+            "ReturnValues": "ALL_OLD",
+        });
+        match self.table.call("UpdateItem", request).await {
+            Ok(response) => item(&response, "Attributes").as_ref().map(from_item).transpose(),
+            Err(DynamoError::ConditionFailed) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn update(&self, message: &OutboxMessage) -> Result<(), String> {
+        let request = json!({
+            "Item": to_item(message)?,
+            "ConditionExpression": "attribute_exists(id)",
+        });
+        match self.table.call("PutItem", request).await {
+            Ok(_) => Ok(()),
+            Err(DynamoError::ConditionFailed) => Err(format!("outbox message {} not found", message.id)),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<OutboxMessage>, String> {
+        let request = json!({
+            "Key": { "id": string(id) },
+            "ConsistentRead": true,
+        });
+        let response = self.table.call("GetItem", request).await?;
+        item(&response, "Item").as_ref().map(from_item).transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outbox::tests::email;
+
+    #[test]
+    fn round_trips_messages_through_items() {
+        let now = Duration::from_secs(1_700_000_000);
+        let message = OutboxMessage {
+            id: "abc".to_string(),
+            email: email("Hello"),
+            status: OutboxStatus::Dead,
+            attempts: 3,
+            next_attempt_at: now + Duration::from_millis(1500),
+            last_error: Some("throttled".to_string()),
+            created_at: now,
+        };
+        let item = to_item(&message).unwrap();
+        assert_eq!(item["next_attempt_at"], json!({ "N": "1700000001500" }));
+        assert_eq!(from_item(item.as_object().unwrap()), Ok(message.clone()));
+
+        let pending = OutboxMessage {
+            last_error: None,
+            ..message
+        };
+        let item = to_item(&pending).unwrap();
+        assert_eq!(item["last_error"], json!({ "NULL": true }));
+        assert_eq!(from_item(item.as_object().unwrap()), Ok(pending));
+    }
+}
@@ -0,0 +1,67 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_graphql::async_trait;
+
+use super::{OutboxMessage, OutboxStatus, OutboxStore};
+
+/// Keeps the outbox in process memory. Nothing survives a restart, so this
+/// suits tests and local runs only.
+#[derive(Default)]
+pub struct MemoryOutboxStore {
+    messages: Mutex<Vec<OutboxMessage>>,
+}
+
+#[async_trait::async_trait]
+impl OutboxStore for MemoryOutboxStore {
+    async fn insert(&self, message: &OutboxMessage) -> Result<(), String> {
+        self.messages.lock().unwrap().push(message.clone());
+        Ok(())
+    }
+
+    async fn claim_due(
+        &self,
+        now: Duration,
+        lease_until: Duration,
+        limit: usize,
+    ) -> Result<Vec<OutboxMessage>, String> {
+        let mut messages = self.messages.lock().unwrap();
+        let mut claimed = Vec::new();
+        for message in messages.iter_mut() {
+            if claimed.len() == limit {
+                break;
+            }
+            if message.status == OutboxStatus::Pending && message.next_attempt_at <= now {
+                claimed.push(message.clone());
+                message.next_attempt_at = lease_until;
+            }
+        }
+        Ok(claimed)
+    }
+
+    async fn claim(&self, id: &str, now: Duration, lease_until: Duration) -> Result<Option<OutboxMessage>, String> {
+        let mut messages = self.messages.lock().unwrap();
+        let due = messages.iter_mut().find(|message| {
+            message.id == id && message.status == OutboxStatus::Pending && message.next_attempt_at <= now
+        });
+        Ok(due.map(|message| {
+            let claimed = message.clone();
+            message.next_attempt_at = lease_until;
+            claimed
+        }))
+    }
+
+    async fn update(&self, message: &OutboxMessage) -> Result<(), String> {
+        let mut messages = self.messages.lock().unwrap();
+        let stored = messages
+            .iter_mut()
+            .find(|stored| stored.id == message.id)
+            .ok_or_else(|| format!("outbox message {} not found", message.id))?;
+        *stored = message.clone();
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<OutboxMessage>, String> {
+        Ok(self.messages.lock().unwrap().iter().find(|message| message.id == id).cloned())
+    }
+}
@@ -0,0 +1,215 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use async_graphql::async_trait;
+use rusqlite::{params, OptionalExtension, Row, TransactionBehavior};
+
+use super::{OutboxMessage, OutboxStatus, OutboxStore};
+use crate::sqlite::{from_millis, invalid, millis, Database};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS outbox (
+        id TEXT PRIMARY KEY,
+        email TEXT NOT NULL,
+        status TEXT NOT NULL,
+        attempts INTEGER NOT NULL,
+        next_attempt_at INTEGER NOT NULL,
+        last_error TEXT,
+        created_at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS outbox_due ON outbox (status, next_attempt_at);
+";
+
+const COLUMNS: &str = "id, email, status, attempts, next_attempt_at, last_error, created_at";
+
+/// Keeps the outbox in a SQLite file. Times are stored as Unix milliseconds
+/// and emails as JSON.
+pub struct SqliteOutboxStore {
+    db: Database,
+}
+
+fn message(row: &Row<'_>) -> rusqlite::Result<OutboxMessage> {
+    let email: String = row.get(1)?;
+    let status: String = row.get(2)?;
+    Ok(OutboxMessage {
+        id: row.get(0)?,
+        email: serde_json::from_str(&email).map_err(|err| invalid(1, err))?,
+        status: OutboxStatus::parse(&status).map_err(|err| invalid(2, err))?,
+        attempts: row.get(3)?,
+        next_attempt_at: from_millis(row.get(4)?),
+        last_error: row.get(5)?,
+        created_at: from_millis(row.get(6)?),
+    })
+}
+
+impl SqliteOutboxStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            db: Database::new(path, SCHEMA),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl OutboxStore for SqliteOutboxStore {
+    async fn insert(&self, message: &OutboxMessage) -> Result<(), String> {
+        let email = serde_json::to_string(&message.email).map_err(|err| format!("failed to encode email: {err}"))?;
+        let message = message.clone();
+        self.db.with(move |conn| {
+            conn.execute(
+                &format!("INSERT INTO outbox ({COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"),
+                params![
+                    message.id,
+                    email,
+                    message.status.as_str(),
+                    message.attempts,
+                    millis(message.next_attempt_at),
+                    message.last_error,
+                    millis(message.created_at),
+                ],
+            )
+            .map(drop)
+        })
+        .await
+    }
+
+    async fn claim_due(
+        &self,
+        now: Duration,
+        lease_until: Duration,
+        limit: usize,
+    ) -> Result<Vec<OutboxMessage>, String> {
+        self.db.with(move |conn| {
+            // IMMEDIATE takes the write lock up front, so two processes
+            // sharing the file cannot claim the same rows.
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            let due = tx
+                .prepare(&format!(
+                    "SELECT {COLUMNS} FROM outbox
+                     WHERE status = 'pending' AND next_attempt_at <= ?1
+                     ORDER BY created_at, id LIMIT ?2"
+                ))?
+                .query_map(params![millis(now), i64::try_from(limit).unwrap_or(i64::MAX)], message)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            for message in &due {
+                tx.execute(
+                    "UPDATE outbox SET next_attempt_at = ?1 WHERE id = ?2",
+                    params![millis(lease_until), message.id],
+                )?;
+            }
+            tx.commit()?;
+            Ok(due)
+        })
+        .await
+    }
+
+    async fn claim(&self, id: &str, now: Duration, lease_until: Duration) -> Result<Option<OutboxMessage>, String> {
+        let id = id.to_string();
+        self.db.with(move |conn| {
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            let due = tx
+                .query_row(
+                    &format!(
+                        "SELECT {COLUMNS} FROM outbox
+                         WHERE id = ?1 AND status = 'pending' AND next_attempt_at <= ?2"
+                    ),
+                    params![id, millis(now)],
+                    message,
+                )
+                .optional()?;
+            if due.is_some() {
+                tx.execute(
+                    "UPDATE outbox SET next_attempt_at = ?1 WHERE id = ?2",
+                    params![millis(lease_until), id],
+                )?;
+            }
+            tx.commit()?;
+            Ok(due)
+        })
+        .await
+    }
+
+    async fn update(&self, message: &OutboxMessage) -> Result<(), String> {
+        let message = message.clone();
+        let id = message.id.clone();
+        let updated = self
+            .db
+            .with(move |conn| {
+                conn.execute(
+                    "UPDATE outbox SET status = ?1, attempts = ?2, next_attempt_at = ?3, last_error = ?4
+                     WHERE id = ?5",
+                    params![
+                        message.status.as_str(),
+                        message.attempts,
+                        millis(message.next_attempt_at),
+                        message.last_error,
+                        message.id,
+                    ],
+                )
+            })
+            .await?;
+        if updated == 0 {
+            return Err(format!("outbox message {id} not found"));
+        }
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<OutboxMessage>, String> {
+        let id = id.to_string();
+        self.db.with(move |conn| {
+            conn.query_row(&format!("SELECT {COLUMNS} FROM outbox WHERE id = ?1"), [id], message)
+                .optional()
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outbox::tests::email;
+
+    #[tokio::test]
+    async fn keeps_messages_across_reopens() {
+        let path = std::env::temp_dir().join(format!("chris-outbox-{}.sqlite3", std::process::id()));
+        let now = Duration::from_secs(1_700_000_000);
+        let message = OutboxMessage {
+            id: "abc".to_string(),
+            email: email("Hello"),
+            status: OutboxStatus::Pending,
+            attempts: 0,
+            next_attempt_at: now,
+            last_error: None,
+            created_at: now,
+        };
+
+        let store = SqliteOutboxStore::new(&path);
+        store.insert(&message).await.unwrap();
+        let claimed = store.claim_due(now, now + Duration::from_secs(300), 10).await.unwrap();
+        // Leased messages are not handed out twice.
+        let again = store.claim_due(now, now + Duration::from_secs(300), 10).await.unwrap();
+        let claimed_by_id = store.claim("abc", now, now + Duration::from_secs(300)).await.unwrap();
+        drop(store);
+
+        let reopened = SqliteOutboxStore::new(&path);
+        let updated = OutboxMessage {
+            status: OutboxStatus::Dead,
+            attempts: 1,
+            last_error: Some("throttled".to_string()),
+            ..message.clone()
+        };
+        reopened.update(&updated).await.unwrap();
+        let stored = reopened.get("abc").await.unwrap();
+        let missing = reopened.update(&OutboxMessage { id: "nope".to_string(), ..message.clone() }).await;
+        drop(reopened);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+        }
+
+        assert_eq!(claimed, vec![message]);
+        assert!(again.is_empty());
+        assert_eq!(claimed_by_id, None);
+        assert_eq!(stored, Some(updated));
+        assert!(missing.is_err());
+    }
+}
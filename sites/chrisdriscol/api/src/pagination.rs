@@ -0,0 +1,140 @@
+use async_graphql::connection::{query, Connection, Edge};
+use async_graphql::{OutputType, Result, SimpleObject};
+
+use crate::limits::{DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+
+/// Extra fields exposed on every list connection.
+#[derive(SimpleObject)]
+pub struct ConnectionFields {
+    #[graphql(name = "totalCount")]
+    pub total_count: usize,
+}
+
+pub type ListConnection<T> = Connection<usize, T, ConnectionFields>;
+
+fn check_page_size(name: &str, size: Option<i32>) -> Result<()> {
+    if size.is_some_and(|size| size > MAX_PAGE_SIZE as i32) {
+        return Err(format!("{name} must be at most {MAX_PAGE_SIZE}").into());
+    }
+    Ok(())
+}
+
+/// Items on a forward-only page: `first`, up to `MAX_PAGE_SIZE`, or
+/// `DEFAULT_PAGE_SIZE`.
+pub fn page_size(first: Option<i32>) -> Result<usize> {
+    check_page_size("first", first)?;
+    match first {
+        None => Ok(DEFAULT_PAGE_SIZE),
+        Some(first) => usize::try_from(first).map_err(|_| "first must not be negative".into()),
+    }
+}
+
+/// Pages through an in-memory list using Relay connection arguments. The
+/// cursor is the item's position in `items`. Pages hold `DEFAULT_PAGE_SIZE`
+/// items unless `first` or `last` asks for up to `MAX_PAGE_SIZE`, matching
+/// what query complexity charges for.
+pub async fn paginate<T>(
+    items: Vec<T>,
+    after: Option<String>,
+    before: Option<String>,
+    first: Option<i32>,
+    last: Option<i32>,
+) -> Result<ListConnection<T>>
+where
+    T: OutputType,
+{
+    for (name, size) in [("first", first), ("last", last)] {
+        check_page_size(name, size)?;
+    }
+    let first = match (first, last) {
+        (None, None) => Some(DEFAULT_PAGE_SIZE as i32),
+        _ => first,
+    };
+    query(
+        after,
+        before,
+        first,
+        last,
+        |after: Option<usize>, before: Option<usize>, first: Option<usize>, last: Option<usize>| async move {
+            let total_count = items.len();
+            let mut end = before.unwrap_or(total_count).min(total_count);
+            let mut start = after.map(|after| after.saturating_add(1)).unwrap_or(0).min(end);
+            if let Some(first) = first {
+                end = end.min(start + first);
+            }
+            if let Some(last) = last {
+                start = start.max(end.saturating_sub(last));
+            }
+
+            let mut connection = Connection::with_additional_fields(
+                start > 0,
+                end < total_count,
+                ConnectionFields { total_count },
+            );
+            connection.edges.extend(
+                items
+                    .into_iter()
+                    .enumerate()
+                    .skip(start)
+                    .take(end - start)
+                    .map(|(cursor, item)| Edge::new(cursor, item)),
+            );
+            Ok::<_, async_graphql::Error>(connection)
+        },
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_graphql::connection::CursorType;
+
+    async fn page(
+        after: Option<usize>,
+        before: Option<usize>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> (Vec<i32>, bool, bool) {
+        let items: Vec<i32> = (0..10).collect();
+        let connection = paginate(
+            items,
+            after.map(|cursor| cursor.encode_cursor()),
+            before.map(|cursor| cursor.encode_cursor()),
+            first,
+            last,
+        )
+        .await
+        .unwrap();
+        assert_eq!(connection.additional_fields.total_count, 10);
+        let nodes = connection.edges.into_iter().map(|edge| edge.node).collect();
+        (nodes, connection.has_previous_page, connection.has_next_page)
+    }
+
+    #[tokio::test]
+    async fn pages_forward_and_backward() {
+        assert_eq!(page(None, None, Some(3), None).await, (vec![0, 1, 2], false, true));
+        assert_eq!(page(Some(2), None, Some(3), None).await, (vec![3, 4, 5], true, true));
+        assert_eq!(page(None, None, None, Some(2)).await, (vec![8, 9], true, false));
+        assert_eq!(page(None, Some(5), None, Some(2)).await, (vec![3, 4], true, true));
+        assert_eq!(page(Some(7), None, Some(5), None).await, (vec![8, 9], true, false));
+        assert_eq!(page(Some(5), Some(3), None, None).await, (vec![], true, true));
+        // Cursors come from clients; the largest one is past the end, not a
+        // wrap back to the first page.
+        assert_eq!(page(Some(usize::MAX), None, Some(3), None).await, (vec![], true, false));
+    }
+
+    #[tokio::test]
+    async fn limits_page_sizes() {
+        let items: Vec<usize> = (0..MAX_PAGE_SIZE * 2).collect();
+        let default = paginate(items.clone(), None, None, None, None).await.unwrap();
+        assert_eq!(default.edges.len(), DEFAULT_PAGE_SIZE);
+        assert!(default.has_next_page);
+
+        let largest = paginate(items.clone(), None, None, Some(MAX_PAGE_SIZE as i32), None).await.unwrap();
+        assert_eq!(largest.edges.len(), MAX_PAGE_SIZE);
+        let too_large = Some(MAX_PAGE_SIZE as i32 + 1);
+        assert!(paginate(items.clone(), None, None, too_large, None).await.is_err());
+        assert!(paginate(items, None, None, None, too_large).await.is_err());
+    }
+}
@@ -0,0 +1,360 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use async_graphql::extensions::{Extension, ExtensionContext, ExtensionFactory, NextPrepareRequest, NextValidation};
+use async_graphql::{
+    async_trait, from_value, ErrorExtensionValues, Request, ServerError, ServerResult, ValidationResult,
+};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// Allowlist generated from the web app's codegen output by the
+/// `persisted_manifest` binary: a JSON object mapping each document's sha256
+/// hash to its text.
+const MANIFEST_JSON: &str = include_str!("../../../../packages/contracts/persisted-queries.json");
+
+/// `apq` (default) or `persisted-only`.
+const MODE_ENV: &str = "GRAPHQL_PERSISTED_QUERIES";
+/// Path to a manifest that replaces the compiled-in one.
+const MANIFEST_ENV: &str = "GRAPHQL_PERSISTED_MANIFEST";
+/// Number of documents the APQ cache keeps.
+const CACHE_SIZE_ENV: &str = "GRAPHQL_APQ_CACHE_SIZE";
+
+const DEFAULT_CACHE_SIZE: usize = 1000;
+
+pub type Manifest = BTreeMap<String, String>;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PersistedMode {
+    /// Any document runs; documents sent with a hash are cached so later
+    /// requests can send the hash alone.
+    #[default]
+    Automatic,
+    /// Only documents in the manifest run, whether sent by hash or in full.
+    PersistedOnly,
+}
+
+/// Lowercase hex sha256 of a document, as used by the APQ protocol.
+pub fn hash(query: &str) -> String {
+    format!("{:x}", Sha256::digest(query.as_bytes()))
+}
+
+/// Extracts every operation document from codegen's `graphql.ts` (generated
+/// with `documentMode: "string"`) and keys it by hash. Fragment-only documents
+/// are skipped because clients never send them on their own.
+pub fn manifest_from_codegen(source: &str) -> Manifest {
+    const START: &str = "new TypedDocumentString(`";
+    let mut manifest = Manifest::new();
+    let mut rest = source;
+    while let Some(start) = rest.find(START) {
+        rest = &rest[start + START.len()..];
+        let Some(end) = rest.find('`') else {
+            break;
+        };
+        let document = &rest[..end];
+        rest = &rest[end + 1..];
+        let operation = document.trim_start();
+        if ["query", "mutation", "subscription"]
+            .iter()
+            .any(|keyword| operation.starts_with(keyword))
+        {
+            manifest.insert(hash(document), document.to_string());
+        }
+    }
+    manifest
+}
+
+pub fn parse_manifest(json: &str) -> Result<Manifest, String> {
+    let manifest: Manifest =
+        serde_json::from_str(json).map_err(|err| format!("failed to parse manifest: {err}"))?;
+    for (key, document) in &manifest {
+        if hash(document) != *key {
+            return Err(format!("manifest entry {key} does not match its document"));
+        }
+    }
+    Ok(manifest)
+}
+
+/// In-memory store of registered documents that evicts the least recently
+/// used entry once full.
+pub struct LruStore {
+    capacity: usize,
+    inner: Mutex<LruInner>,
+}
+
+#[derive(Default)]
+struct LruInner {
+    tick: u64,
+    entries: HashMap<String, (String, u64)>,
+}
+
+impl LruStore {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            inner: Mutex::default(),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.tick += 1;
+        let tick = inner.tick;
+        let (query, last_used) = inner.entries.get_mut(key)?;
+        *last_used = tick;
+        Some(query.clone())
+    }
+
+    pub fn insert(&self, key: String, query: String) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.tick += 1;
+        let tick = inner.tick;
+        if !inner.entries.contains_key(&key) && inner.entries.len() >= self.capacity {
+            let oldest = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                inner.entries.remove(&oldest);
+            }
+        }
+        inner.entries.insert(key, (query, tick));
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Schema extension implementing Automatic Persisted Queries and the
+/// persisted-only allowlist.
+#[derive(Clone)]
+pub struct PersistedQueries {
+    mode: PersistedMode,
+    manifest: Arc<Manifest>,
+    store: Arc<LruStore>,
+}
+
+impl Default for PersistedQueries {
+    fn default() -> Self {
+        Self::new(PersistedMode::Automatic, Manifest::new(), DEFAULT_CACHE_SIZE)
+    }
+}
+
+impl PersistedQueries {
+    pub fn new(mode: PersistedMode, manifest: Manifest, cache_size: usize) -> Self {
+        Self {
+            mode,
+            manifest: Arc::new(manifest),
+            store: Arc::new(LruStore::new(cache_size)),
+        }
+    }
+
+    pub fn from_env() -> Result<Self, String> {
+        let mode = match std::env::var(MODE_ENV).as_deref() {
+            Err(_) | Ok("apq") => PersistedMode::Automatic,
+            Ok("persisted-only") => PersistedMode::PersistedOnly,
+            Ok(other) => return Err(format!("unknown {MODE_ENV} {other:?}")),
+        };
+        let manifest = match std::env::var_os(MANIFEST_ENV) {
+            Some(path) => {
+                let path = Path::new(&path);
+                let json = std::fs::read_to_string(path)
+                    .map_err(|err| format!("failed to read {}: {err}", path.display()))?;
+                parse_manifest(&json).map_err(|err| format!("{}: {err}", path.display()))?
+            }
+            None => parse_manifest(MANIFEST_JSON)?,
+        };
+        let cache_size = match std::env::var(CACHE_SIZE_ENV) {
+            Ok(value) => value
+                .trim()
+                .parse()
+                .map_err(|err| format!("invalid {CACHE_SIZE_ENV} {value:?}: {err}"))?,
+            Err(_) => DEFAULT_CACHE_SIZE,
+        };
+        Ok(Self::new(mode, manifest, cache_size))
+    }
+
+    fn lookup(&self, key: &str) -> Option<String> {
+        if let Some(document) = self.manifest.get(key) {
+            return Some(document.clone());
+        }
+        match self.mode {
+            PersistedMode::Automatic => self.store.get(key),
+            PersistedMode::PersistedOnly => None,
+        }
+    }
+
+    /// Fills in documents sent by hash and enforces the allowlist. Also
+    /// returns a hash and document to register, which the caller should only
+    /// store once the document has validated.
+    fn resolve(&self, mut request: Request) -> ServerResult<(Request, Option<(String, String)>)> {
+        let persisted = match request.extensions.remove("persistedQuery") {
+            Some(value) => Some(from_value::<PersistedQuery>(value).map_err(|_| {
+                persisted_error(
+                    "Invalid persistedQuery extension.",
+                    "PERSISTED_QUERY_INVALID",
+                )
+            })?),
+            None => None,
+        };
+
+        let Some(persisted) = persisted else {
+            if self.mode == PersistedMode::PersistedOnly && !self.manifest.contains_key(&hash(&request.query)) {
+                return Err(not_allowed());
+            }
+            return Ok((request, None));
+        };
+        if persisted.version != 1 {
+            return Err(persisted_error(
+                format!("Unsupported persistedQuery version {}.", persisted.version),
+                "PERSISTED_QUERY_INVALID",
+            ));
+        }
+
+        if request.query.is_empty() {
+            // Apollo clients look for this exact message before retrying
+            // with the full document.
+            request.query = self
+                .lookup(&persisted.sha256_hash)
+                .ok_or_else(|| persisted_error("PersistedQueryNotFound", "PERSISTED_QUERY_NOT_FOUND"))?;
+            return Ok((request, None));
+        }
+
+        if hash(&request.query) != persisted.sha256_hash {
+            return Err(persisted_error(
+                "provided sha does not match query",
+                "PERSISTED_QUERY_HASH_MISMATCH",
+            ));
+        }
+        match self.mode {
+            PersistedMode::Automatic => {
+                let register = (persisted.sha256_hash, request.query.clone());
+                Ok((request, Some(register)))
+            }
+            PersistedMode::PersistedOnly if !self.manifest.contains_key(&persisted.sha256_hash) => {
+                Err(not_allowed())
+            }
+            PersistedMode::PersistedOnly => Ok((request, None)),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PersistedQuery {
+    version: i32,
+    #[serde(rename = "sha256Hash")]
+    sha256_hash: String,
+}
+
+fn persisted_error(message: impl Into<String>, code: &str) -> ServerError {
+    let mut error = ServerError::new(message, None);
+    let mut extensions = ErrorExtensionValues::default();
+    extensions.set("code", code);
+    error.extensions = Some(extensions);
+    error
+}
+
+fn not_allowed() -> ServerError {
+    persisted_error(
+        "Only persisted queries are accepted by this server.",
+        "PERSISTED_QUERY_NOT_ALLOWED",
+    )
+}
+
+impl ExtensionFactory for PersistedQueries {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(PersistedQueriesExtension {
+            queries: self.clone(),
+            pending: Mutex::default(),
+        })
+    }
+}
+
+/// Per-request state of [`PersistedQueries`].
+struct PersistedQueriesExtension {
+    queries: PersistedQueries,
+    /// A document sent with its hash. It is only cached once it parses and
+    /// passes validation and the query limits, so junk cannot crowd out real
+    /// documents or fill memory.
+    pending: Mutex<Option<(String, String)>>,
+}
+
+#[async_trait::async_trait]
+impl Extension for PersistedQueriesExtension {
+    async fn prepare_request(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        request: Request,
+        next: NextPrepareRequest<'_>,
+    ) -> ServerResult<Request> {
+        let (request, register) = self.queries.resolve(request)?;
+        *self.pending.lock().unwrap() = register;
+        next.run(ctx, request).await
+    }
+
+    async fn validation(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        next: NextValidation<'_>,
+    ) -> Result<ValidationResult, Vec<ServerError>> {
+        let result = next.run(ctx).await?;
+        if let Some((key, query)) = self.pending.lock().unwrap().take() {
+            self.queries.store.insert(key, query);
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lru_store_evicts_least_recently_used() {
+        let store = LruStore::new(2);
+        store.insert("a".to_string(), "A".to_string());
+        store.insert("b".to_string(), "B".to_string());
+        assert_eq!(store.get("a").as_deref(), Some("A"));
+        store.insert("c".to_string(), "C".to_string());
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.get("b"), None);
+        assert_eq!(store.get("a").as_deref(), Some("A"));
+        assert_eq!(store.get("c").as_deref(), Some("C"));
+    }
+
+    #[test]
+    fn extracts_operations_from_codegen_output() {
+        let source = "export const FooFragmentDoc = new TypedDocumentString(`\n    fragment Foo on Chris {\n  title\n}\n    `, {\"fragmentName\":\"Foo\"});\n\
+            export const PingDocument = new TypedDocumentString(`\n    query Ping {\n  ping\n}\n    `);\n";
+        let manifest = manifest_from_codegen(source);
+        assert_eq!(manifest.len(), 1);
+        let (key, document) = manifest.iter().next().unwrap();
+        assert_eq!(document, "\n    query Ping {\n  ping\n}\n    ");
+        assert_eq!(*key, hash(document));
+    }
+
+    #[test]
+    fn compiled_in_manifest_matches_web_codegen() {
+        let codegen = include_str!("../../web/src/graphql/generated/graphql.ts");
+        let manifest = parse_manifest(MANIFEST_JSON).unwrap();
+        assert_eq!(
+            manifest,
+            manifest_from_codegen(codegen),
+            "run `cargo run --bin persisted_manifest` after regenerating the web client"
+        );
+    }
+
+    #[test]
+    fn rejects_manifest_entries_with_wrong_hashes() {
+        assert!(parse_manifest(r#"{"abc": "{ ping }"}"#).is_err());
+        let key = hash("{ ping }");
+        assert!(parse_manifest(&format!(r#"{{"{key}": "{{ ping }}"}}"#)).is_ok());
+    }
+}
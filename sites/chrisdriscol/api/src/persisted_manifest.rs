@@ -0,0 +1,29 @@
+use std::fs;
+use std::path::PathBuf;
+
+use chris_driscol_api::persisted;
+
+fn main() {
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let codegen_path = manifest_dir
+        .join("..")
+        .join("web")
+        .join("src")
+        .join("graphql")
+        .join("generated")
+        .join("graphql.ts");
+    let output_path = manifest_dir
+        .join("..")
+        .join("..")
+        .join("..")
+        .join("packages")
+        .join("contracts")
+        .join("persisted-queries.json");
+
+    let codegen = fs::read_to_string(&codegen_path).expect("failed to read generated graphql.ts");
+    let manifest = persisted::manifest_from_codegen(&codegen);
+    let json = serde_json::to_string_pretty(&manifest).expect("manifest serializes");
+
+    fs::write(&output_path, json + "\n").expect("failed to write persisted-queries.json");
+    println!("Wrote {} documents to {}", manifest.len(), output_path.display());
+}
@@ -0,0 +1,379 @@
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_graphql::{async_trait, ErrorExtensions};
+
+/// `limit` hits allowed in any `window`-long span.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rule {
+    pub limit: u32,
+    pub window: Duration,
+}
+
+impl Rule {
+    pub const fn new(limit: u32, window: Duration) -> Self {
+        Self { limit, window }
+    }
+
+    /// Parses `<limit>/<window>` where the window is a number of seconds with
+    /// an optional `s`, `m`, `h` or `d` unit, e.g. `5/1h`. `off` disables
+    /// the rule.
+    pub fn parse(value: &str) -> Result<Option<Self>, String> {
+        let value = value.trim();
+        if value.eq_ignore_ascii_case("off") {
+            return Ok(None);
+        }
+        let invalid = || format!("invalid rate limit {value:?}, expected e.g. 5/1h or off");
+        let (limit, window) = value.split_once('/').ok_or_else(invalid)?;
+        let limit = limit.trim().parse().map_err(|_| invalid())?;
+        let window = window.trim();
+        let (amount, unit) = match window.char_indices().last() {
+            Some((index, unit)) if unit.is_ascii_alphabetic() => (&window[..index], unit),
+            _ => (window, 's'),
+        };
+        let amount: u64 = amount.parse().map_err(|_| invalid())?;
+        let seconds = match unit {
+            's' => amount,
+            'm' => amount * 60,
+            'h' => amount * 60 * 60,
+            'd' => amount * 60 * 60 * 24,
+            _ => return Err(invalid()),
+        };
+        if seconds == 0 {
+            return Err(invalid());
+        }
+        Ok(Some(Self::new(limit, Duration::from_secs(seconds))))
+    }
+}
+
+/// Records hits per key. Implementations apply the sliding-window check and
+/// the write atomically, so a shared backend can serve several instances.
+#[async_trait::async_trait]
+pub trait RateLimitStore: Send + Sync {
+    /// Records a hit at `now` unless `key` already has `rule.limit` hits in
+    /// the preceding window. A rejected hit returns how long until the oldest
+    /// counted hit leaves the window, and is not recorded.
+    async fn hit(&self, key: &str, rule: Rule, now: Duration) -> Result<Option<Duration>, String>;
+}
+
+/// Sliding-window log kept in process memory. Each Lambda instance counts
+/// separately, so the effective limit scales with concurrency.
+#[derive(Default)]
+pub struct MemoryStore {
+    hits: Mutex<HashMap<String, VecDeque<Duration>>>,
+}
+
+/// Above this many keys, expired entries are swept on the next hit.
+const SWEEP_THRESHOLD: usize = 10_000;
+
+#[async_trait::async_trait]
+impl RateLimitStore for MemoryStore {
+    async fn hit(&self, key: &str, rule: Rule, now: Duration) -> Result<Option<Duration>, String> {
+        let mut hits = self.hits.lock().unwrap();
+        if hits.len() > SWEEP_THRESHOLD {
+            // Windows differ per key; anything older than a day is stale for
+            // every rule we configure.
+            let horizon = now.saturating_sub(Duration::from_secs(24 * 60 * 60).max(rule.window));
+            hits.retain(|_, log| log.back().is_some_and(|last| *last > horizon));
+        }
+        let log = hits.entry(key.to_string()).or_default();
+        let start = now.saturating_sub(rule.window);
+        while log.front().is_some_and(|hit| *hit <= start) {
+            log.pop_front();
+        }
+        if log.len() >= rule.limit as usize {
+            let retry_after = log
+                .front()
+                .map(|oldest| (*oldest + rule.window).saturating_sub(now))
+                .unwrap_or(rule.window);
+            return Ok(Some(retry_after));
+        }
+        log.push_back(now);
+        Ok(None)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitConfig {
+    pub per_ip: Option<Rule>,
+    pub per_email: Option<Rule>,
+    pub global: Option<Rule>,
+    /// Auto-replies to one address, so the confirmation cannot be pointed
+    /// at someone else's inbox over and over.
+    pub auto_reply: Option<Rule>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            per_ip: Some(Rule::new(5, Duration::from_secs(60 * 60))),
+            per_email: Some(Rule::new(3, Duration::from_secs(60 * 60))),
+            global: Some(Rule::new(100, Duration::from_secs(24 * 60 * 60))),
+            auto_reply: Some(Rule::new(2, Duration::from_secs(24 * 60 * 60))),
+        }
+    }
+}
+
+impl RateLimitConfig {
+    /// Reads `CONTACT_RATE_LIMIT_IP`, `CONTACT_RATE_LIMIT_EMAIL`,
+    /// `CONTACT_RATE_LIMIT_GLOBAL` and `CONTACT_RATE_LIMIT_AUTO_REPLY` (see
+    /// [`Rule::parse`]).
+    pub fn from_env() -> Result<Self, String> {
+        let defaults = Self::default();
+        let rule = |name: &str, default: Option<Rule>| match std::env::var(name) {
+            Ok(value) => Rule::parse(&value).map_err(|err| format!("{name}: {err}")),
+            Err(_) => Ok(default),
+        };
+        Ok(Self {
+            per_ip: rule("CONTACT_RATE_LIMIT_IP", defaults.per_ip)?,
+            per_email: rule("CONTACT_RATE_LIMIT_EMAIL", defaults.per_email)?,
+            global: rule("CONTACT_RATE_LIMIT_GLOBAL", defaults.global)?,
+            auto_reply: rule("CONTACT_RATE_LIMIT_AUTO_REPLY", defaults.auto_reply)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RateLimitError {
+    /// Over the named cap (`ip`, `email`, `global` or `auto_reply`).
+    Limited {
+        scope: &'static str,
+        retry_after: Duration,
+    },
+    Store(String),
+}
+
+impl RateLimitError {
+    /// Whole seconds to wait, rounded up so clients never retry early.
+    pub fn retry_after_secs(&self) -> Option<u64> {
+        match self {
+            Self::Limited { retry_after, .. } => {
+                Some(retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0))
+            }
+            Self::Store(_) => None,
+        }
+    }
+
+    pub fn to_error(&self) -> async_graphql::Error {
+        match self {
+            Self::Limited { scope, .. } => {
+                let retry_after = self.retry_after_secs().unwrap_or_default();
+                async_graphql::Error::new(format!(
+                    "Too many messages; try again in {retry_after} seconds"
+                ))
+                .extend_with(|_, extensions| {
+                    extensions.set("code", "RATE_LIMITED");
+                    extensions.set("scope", *scope);
+                    extensions.set("retryAfter", retry_after);
+                })
+            }
+            Self::Store(err) => async_graphql::Error::new(format!("rate limiter unavailable: {err}")),
+        }
+    }
+}
+
+/// Throttles `contactMe` per client IP, per sender address and overall.
+#[derive(Clone)]
+pub struct RateLimiter {
+    pub store: Arc<dyn RateLimitStore>,
+    pub config: RateLimitConfig,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self {
+            store: Arc::new(MemoryStore::default()),
+            config: RateLimitConfig::default(),
+        }
+    }
+}
+
+/// IPv6 clients usually control a whole /64, so they are counted by prefix.
+fn ip_key(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => format!("contact:ip:{ip}"),
+        IpAddr::V6(ip) => {
+            let segments = ip.segments();
+            format!(
+                "contact:ip:{:x}:{:x}:{:x}:{:x}::/64",
+                segments[0], segments[1], segments[2], segments[3]
+            )
+        }
+    }
+}
+
+impl RateLimiter {
+    pub fn from_env() -> Result<Self, String> {
+        Ok(Self {
+            config: RateLimitConfig::from_env()?,
+            ..Self::default()
+        })
+    }
+
+    pub async fn check_contact(&self, ip: Option<IpAddr>, email: &str) -> Result<(), RateLimitError> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        self.check_contact_at(ip, email, now).await
+    }
+
+    /// Checks the narrowest cap first so one noisy client cannot use up the
+    /// global allowance with hits that its own cap rejects. Requests without
+    /// a known IP skip the per-IP cap.
+    pub async fn check_contact_at(
+        &self,
+        ip: Option<IpAddr>,
+        email: &str,
+        now: Duration,
+    ) -> Result<(), RateLimitError> {
+        let email_key = format!("contact:email:{}", email.trim().to_lowercase());
+        let checks = [
+            ("ip", self.config.per_ip, ip.map(ip_key)),
+            ("email", self.config.per_email, Some(email_key)),
+            ("global", self.config.global, Some("contact:global".to_string())),
+        ];
+        for (scope, rule, key) in checks {
+            let (Some(rule), Some(key)) = (rule, key) else {
+                continue;
+            };
+            if let Some(retry_after) = self
+                .store
+                .hit(&key, rule, now)
+                .await
+                .map_err(RateLimitError::Store)?
+            {
+                return Err(RateLimitError::Limited { scope, retry_after });
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn check_auto_reply(&self, email: &str) -> Result<(), RateLimitError> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        self.check_auto_reply_at(email, now).await
+    }
+
+    /// Counts a confirmation to `email`, whoever asked for it.
+    pub async fn check_auto_reply_at(&self, email: &str, now: Duration) -> Result<(), RateLimitError> {
+        let Some(rule) = self.config.auto_reply else {
+            return Ok(());
+        };
+        let key = format!("contact:auto_reply:{}", email.trim().to_lowercase());
+        match self.store.hit(&key, rule, now).await.map_err(RateLimitError::Store)? {
+            Some(retry_after) => Err(RateLimitError::Limited {
+                scope: "auto_reply",
+                retry_after,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    fn limiter(config: RateLimitConfig) -> RateLimiter {
+        RateLimiter {
+            config,
+            ..RateLimiter::default()
+        }
+    }
+
+    fn at(secs: u64) -> Duration {
+        Duration::from_secs(1_700_000_000 + secs)
+    }
+
+    #[test]
+    fn parses_rules() {
+        assert_eq!(Rule::parse("5/1h"), Ok(Some(Rule::new(5, HOUR))));
+        assert_eq!(Rule::parse("10/90"), Ok(Some(Rule::new(10, Duration::from_secs(90)))));
+        assert_eq!(Rule::parse(" 100 / 1d "), Ok(Some(Rule::new(100, 24 * HOUR))));
+        assert_eq!(Rule::parse("OFF"), Ok(None));
+        for invalid in ["5", "5/0", "x/1h", "5/1w", "5/h"] {
+            assert!(Rule::parse(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[tokio::test]
+    async fn window_slides_instead_of_resetting() {
+        let store = MemoryStore::default();
+        let rule = Rule::new(2, HOUR);
+        assert_eq!(store.hit("k", rule, at(0)).await, Ok(None));
+        assert_eq!(store.hit("k", rule, at(1800)).await, Ok(None));
+        assert_eq!(store.hit("k", rule, at(3000)).await, Ok(Some(Duration::from_secs(600))));
+        // The first hit has left the window; the second still counts.
+        assert_eq!(store.hit("k", rule, at(3600)).await, Ok(None));
+        assert_eq!(store.hit("k", rule, at(3601)).await, Ok(Some(Duration::from_secs(1799))));
+    }
+
+    #[tokio::test]
+    async fn caps_per_ip_email_and_globally() {
+        let limiter = limiter(RateLimitConfig {
+            per_ip: Some(Rule::new(2, HOUR)),
+            per_email: Some(Rule::new(3, HOUR)),
+            global: Some(Rule::new(4, HOUR)),
+            auto_reply: None,
+        });
+        let ip = |last: u8| Some(IpAddr::from([203, 0, 113, last]));
+
+        assert!(limiter.check_contact_at(ip(1), "a@example.com", at(0)).await.is_ok());
+        assert!(limiter.check_contact_at(ip(1), "b@example.com", at(1)).await.is_ok());
+        let err = limiter.check_contact_at(ip(1), "c@example.com", at(2)).await.unwrap_err();
+        assert!(matches!(err, RateLimitError::Limited { scope: "ip", .. }), "{err:?}");
+
+        assert!(limiter.check_contact_at(ip(2), "A@Example.com ", at(3)).await.is_ok());
+        assert!(limiter.check_contact_at(ip(3), "a@example.com", at(4)).await.is_ok());
+        let err = limiter.check_contact_at(ip(4), "a@example.com", at(5)).await.unwrap_err();
+        assert!(matches!(err, RateLimitError::Limited { scope: "email", .. }), "{err:?}");
+
+        let err = limiter.check_contact_at(None, "d@example.com", at(6)).await.unwrap_err();
+        assert!(matches!(err, RateLimitError::Limited { scope: "global", .. }), "{err:?}");
+    }
+
+    #[tokio::test]
+    async fn counts_ipv6_clients_by_prefix() {
+        let limiter = limiter(RateLimitConfig {
+            per_ip: Some(Rule::new(1, HOUR)),
+            per_email: None,
+            global: None,
+            auto_reply: None,
+        });
+        let first: IpAddr = "2001:db8:1:2::1".parse().unwrap();
+        let same_prefix: IpAddr = "2001:db8:1:2:ffff::9".parse().unwrap();
+        let other_prefix: IpAddr = "2001:db8:1:3::1".parse().unwrap();
+
+        assert!(limiter.check_contact_at(Some(first), "a@example.com", at(0)).await.is_ok());
+        assert!(limiter.check_contact_at(Some(same_prefix), "a@example.com", at(1)).await.is_err());
+        assert!(limiter.check_contact_at(Some(other_prefix), "a@example.com", at(2)).await.is_ok());
+    }
+
+    #[test]
+    fn rate_limited_errors_carry_code_and_retry_after() {
+        let err = RateLimitError::Limited {
+            scope: "ip",
+            retry_after: Duration::from_millis(1500),
+        };
+        let value = serde_json::to_value(err.to_error().into_server_error(Default::default())).unwrap();
+        assert_eq!(value["extensions"]["code"], "RATE_LIMITED");
+        assert_eq!(value["extensions"]["scope"], "ip");
+        assert_eq!(value["extensions"]["retryAfter"], 2);
+    }
+
+    #[tokio::test]
+    async fn caps_auto_replies_per_recipient() {
+        let limiter = RateLimiter::default();
+        assert_eq!(limiter.check_auto_reply_at("a@example.com", at(0)).await, Ok(()));
+        assert_eq!(limiter.check_auto_reply_at("A@example.com ", at(1)).await, Ok(()));
+        assert_eq!(
+            limiter.check_auto_reply_at("a@example.com", at(2)).await,
+            Err(RateLimitError::Limited {
+                scope: "auto_reply",
+                retry_after: 24 * HOUR - Duration::from_secs(2),
+            })
+        );
+        assert_eq!(limiter.check_auto_reply_at("b@example.com", at(3)).await, Ok(()));
+    }
+}
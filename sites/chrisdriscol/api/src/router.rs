@@ -0,0 +1,567 @@
+use async_graphql::{ErrorExtensionValues, Response, ServerError};
+use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
+use axum::extract::{Query, Request, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Extension, Router};
+use serde::Deserialize;
+
+use crate::auth::Authenticator;
+use crate::caching::{self, GetRequest};
+use crate::client::{ClientConfig, ClientInfo};
+use crate::compression::{self, CompressionConfig};
+use crate::content::Content;
+use crate::cors::CorsConfig;
+use crate::llms;
+use crate::origin::OriginSecrets;
+use crate::schema::AppSchema;
+
+fn forbidden_response() -> Response {
+    let mut error = ServerError::new("Forbidden", None);
+    let mut extensions = ErrorExtensionValues::default();
+    extensions.set("code", "FORBIDDEN");
+    error.extensions = Some(extensions);
+    Response::from_errors(vec![error])
+}
+
+async fn require_origin_secret(
+    State(secrets): State<OriginSecrets>,
+    request: Request,
+    next: Next,
+) -> axum::response::Response {
+    if !secrets.check(request.headers()) {
+        return GraphQLResponse::from(forbidden_response()).into_response();
+    }
+    next.run(request).await
+}
+
+async fn graphql_handler(
+    Extension(schema): Extension<AppSchema>,
+    Extension(client): Extension<ClientConfig>,
+    Extension(auth): Extension<Authenticator>,
+    headers: HeaderMap,
+    request: GraphQLRequest,
+) -> GraphQLResponse {
+    let request = request_data(request.into_inner(), &client, &auth, &headers);
+    schema.execute(request).await.into()
+}
+
+/// Attaches what resolvers need to know about the caller.
+fn request_data(
+    request: async_graphql::Request,
+    client: &ClientConfig,
+    auth: &Authenticator,
+    headers: &HeaderMap,
+) -> async_graphql::Request {
+    let mut request = request.data(ClientInfo::from_headers(headers));
+    if let Some(ip) = client.client_ip(headers) {
+        request = request.data(ip);
+    }
+    match auth.authenticate(headers) {
+        Ok(Some(principal)) => request = request.data(principal),
+        Ok(None) => {}
+        Err(failure) => request = request.data(failure),
+    }
+    request
+}
+
+/// GraphQL-over-HTTP GET parameters; `variables` and `extensions` are JSON
+/// encoded.
+#[derive(Deserialize)]
+struct GetParams {
+    #[serde(default)]
+    query: String,
+    #[serde(rename = "operationName")]
+    operation_name: Option<String>,
+    variables: Option<String>,
+    extensions: Option<String>,
+}
+
+fn bad_request(message: String) -> axum::response::Response {
+    let response = Response::from_errors(vec![ServerError::new(message, None)]);
+    (StatusCode::BAD_REQUEST, GraphQLResponse::from(response)).into_response()
+}
+
+async fn graphql_get_handler(
+    Extension(schema): Extension<AppSchema>,
+    Extension(client): Extension<ClientConfig>,
+    Extension(auth): Extension<Authenticator>,
+    Query(params): Query<GetParams>,
+    headers: HeaderMap,
+) -> axum::response::Response {
+    let mut request = async_graphql::Request::new(params.query);
+    if let Some(variables) = params.variables {
+        match serde_json::from_str(&variables) {
+            Ok(variables) => request.variables = variables,
+            Err(err) => return bad_request(format!("invalid variables: {err}")),
+        }
+    }
+    if let Some(extensions) = params.extensions {
+        match serde_json::from_str(&extensions) {
+            Ok(extensions) => request.extensions = extensions,
+            Err(err) => return bad_request(format!("invalid extensions: {err}")),
+        }
+    }
+    if let Some(operation_name) = &params.operation_name {
+        request = request.operation_name(operation_name);
+    }
+    let request = request.data(GetRequest {
+        operation_name: params.operation_name,
+    });
+    let request = request_data(request, &client, &auth, &headers);
+    caching::get_response(schema.execute(request).await, &headers)
+}
+
+/// Renders llms.txt from the content as currently served, edits included.
+async fn llms_handler(Extension(content): Extension<Content>) -> axum::response::Response {
+    match content.snapshot().await {
+        Ok(snapshot) => (
+            [("content-type", "text/markdown; charset=utf-8")],
+            llms::generate_llms_txt(&snapshot.chris),
+        )
+            .into_response(),
+        Err(err) => (StatusCode::SERVICE_UNAVAILABLE, format!("content unavailable: {err}")).into_response(),
+    }
+}
+
+/// Settings for the HTTP layer, read once at startup.
+#[derive(Debug, Clone, Default)]
+pub struct RouterConfig {
+    pub cors: CorsConfig,
+    pub compression: CompressionConfig,
+    pub client: ClientConfig,
+    pub auth: Authenticator,
+    pub origin: OriginSecrets,
+}
+
+impl RouterConfig {
+    pub fn from_env() -> Result<Self, String> {
+        Ok(Self {
+            cors: CorsConfig::from_env()?,
+            compression: CompressionConfig::from_env()?,
+            client: ClientConfig::from_env()?,
+            auth: Authenticator::from_env()?,
+            origin: OriginSecrets::from_env()?,
+        })
+    }
+}
+
+/// Builds the HTTP app served both locally and on Lambda. `content` must be
+/// the one the schema was built with, so llms.txt shows the same edits.
+pub fn router(schema: AppSchema, content: Content, config: &RouterConfig) -> Router {
+    let graphql = Router::new()
+        .route("/graphql", get(graphql_get_handler).post(graphql_handler))
+        .route_layer(middleware::from_fn_with_state(
+            config.origin.clone(),
+            require_origin_secret,
+        ));
+
+    // CORS sits outside the origin-secret check so preflights are answered
+    // before any route middleware runs. Compression applies to both entrypoints;
+    // on Lambda, `lambda_http` sends bodies with a `Content-Encoding` as binary.
+    Router::new()
+        .merge(graphql)
+        .route("/llms.txt", get(llms_handler))
+        .layer(Extension(schema))
+        .layer(Extension(content))
+        .layer(Extension(config.client))
+        .layer(Extension(config.auth.clone()))
+        .layer(config.compression.layer())
+        .layer(middleware::map_response(compression::tag_encoded_etag))
+        .layer(config.cors.layer())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cors::AllowedOrigins;
+    use crate::schema::build_schema;
+    use std::time::Duration;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{header, HeaderValue, Method, StatusCode};
+    use tower::ServiceExt;
+
+    async fn send(method: Method, uri: &str, body: Body) -> (StatusCode, HeaderMap, String) {
+        let request = axum::http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .unwrap();
+        respond(&RouterConfig::default(), request).await
+    }
+
+    async fn respond(
+        config: &RouterConfig,
+        request: axum::http::Request<Body>,
+    ) -> (StatusCode, HeaderMap, String) {
+        let response = router(build_schema(), Content::default(), config).oneshot(request).await.unwrap();
+        let status = response.status();
+        let headers = response.headers().clone();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, headers, String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn executes_graphql_posts() {
+        let (status, _, body) = send(Method::POST, "/graphql", Body::from(r#"{"query":"{ ping }"}"#)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, r#"{"data":{"ping":"pong"}}"#);
+    }
+
+    #[tokio::test]
+    async fn serves_llms_txt() {
+        let (status, headers, body) = send(Method::GET, "/llms.txt", Body::empty()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[header::CONTENT_TYPE], "text/markdown; charset=utf-8");
+        assert!(body.starts_with("# Chris Driscol"));
+    }
+
+    #[tokio::test]
+    async fn llms_txt_shows_content_edits() {
+        let content = Content::default();
+        content
+            .edit(0, |chris| {
+                chris.title = "Edited Title".to_string();
+                Ok(())
+            })
+            .await
+            .unwrap();
+        let request = axum::http::Request::builder().uri("/llms.txt").body(Body::empty()).unwrap();
+        let response = router(build_schema(), content, &RouterConfig::default())
+            .oneshot(request)
+            .await
+            .unwrap();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        assert!(String::from_utf8(bytes.to_vec()).unwrap().starts_with("# Edited Title"));
+    }
+
+    #[tokio::test]
+    async fn answers_preflight_and_rejects_other_methods() {
+        let (status, _, _) = send(Method::OPTIONS, "/graphql", Body::empty()).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _, _) = send(Method::PUT, "/graphql", Body::empty()).await;
+        assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+        let (status, _, _) = send(Method::POST, "/llms.txt", Body::empty()).await;
+        assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    fn cors_config() -> RouterConfig {
+        RouterConfig {
+            cors: CorsConfig {
+                allowed_origins: AllowedOrigins::List(vec![HeaderValue::from_static(
+                    "https://chrisdriscol.com",
+                )]),
+                max_age: Duration::from_secs(600),
+            },
+            ..RouterConfig::default()
+        }
+    }
+
+    fn preflight(uri: &str, origin: &'static str) -> axum::http::Request<Body> {
+        axum::http::Request::builder()
+            .method(Method::OPTIONS)
+            .uri(uri)
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "content-type,x-origin-secret")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn answers_preflight_for_allowed_origins() {
+        let (status, headers, _) =
+            respond(&cors_config(), preflight("/graphql", "https://chrisdriscol.com")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "https://chrisdriscol.com");
+        assert_eq!(headers[header::ACCESS_CONTROL_MAX_AGE], "600");
+        let allowed = headers[header::ACCESS_CONTROL_ALLOW_HEADERS].to_str().unwrap();
+        assert!(allowed.contains("x-origin-secret"), "{allowed}");
+
+        let (_, headers, _) =
+            respond(&cors_config(), preflight("/llms.txt", "https://evil.example")).await;
+        assert!(!headers.contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[tokio::test]
+    async fn adds_cors_headers_to_responses() {
+        let request = axum::http::Request::builder()
+            .uri("/llms.txt")
+            .header(header::ORIGIN, "https://chrisdriscol.com")
+            .body(Body::empty())
+            .unwrap();
+        let (status, headers, _) = respond(&cors_config(), request).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "https://chrisdriscol.com");
+        assert!(vary(&headers).contains("origin"), "{headers:?}");
+    }
+
+    fn get(uri: &str, if_none_match: Option<&str>) -> axum::http::Request<Body> {
+        let mut request = axum::http::Request::builder().uri(uri);
+        if let Some(etag) = if_none_match {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        request.body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn caches_query_operations_over_get() {
+        let config = RouterConfig::default();
+        let uri = "/graphql?query=%7B%20chris%20%7B%20title%20%7D%20%7D";
+        let (status, headers, body) = respond(&config, get(uri, None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.starts_with(r#"{"data":{"chris":{"title":"#), "{body}");
+        assert_eq!(headers[header::CACHE_CONTROL], "public, max-age=300");
+        let etag = headers[header::ETAG].to_str().unwrap().to_string();
+        assert!(etag.starts_with('"') && etag.ends_with('"'), "{etag}");
+
+        let (status, headers, body) = respond(&config, get(uri, Some(&etag))).await;
+        assert_eq!(status, StatusCode::NOT_MODIFIED);
+        assert_eq!(headers[header::ETAG], etag.as_str());
+        assert!(body.is_empty());
+
+        let (status, _, _) = respond(&config, get(uri, Some("\"stale\""))).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (_, headers, body) = respond(&config, get("/graphql?query=%7Bping%7D", None)).await;
+        assert_eq!(body, r#"{"data":{"ping":"pong"}}"#);
+        assert_eq!(headers[header::CACHE_CONTROL], "no-cache");
+    }
+
+    #[tokio::test]
+    async fn get_honors_operation_name_and_variables() {
+        let uri = "/graphql?query=query%20A%20%7B%20ping%20%7D%20query%20B(%24q%3A%20String!)%20%7B%20search(query%3A%20%24q)%20%7B%20score%20%7D%20%7D\
+            &operationName=B&variables=%7B%22q%22%3A%22drupal%22%7D";
+        let (status, _, body) = respond(&RouterConfig::default(), get(uri, None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.starts_with(r#"{"data":{"search":[{"score":"#), "{body}");
+
+        let (status, _, body) =
+            respond(&RouterConfig::default(), get("/graphql?query=%7Bping%7D&variables=nope", None)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("invalid variables"), "{body}");
+    }
+
+    #[tokio::test]
+    async fn rejects_mutations_over_get() {
+        let uri = "/graphql?query=mutation%20%7B%20contactMe(input%3A%20%7Bfrom%3A%22a%40b.c%22%2C%20name%3A%22a%22%2C%20subject%3A%22s%22%2C%20body%3A%22b%22%7D)%20%7B%20success%20%7D%20%7D";
+        let (status, headers, body) = respond(&RouterConfig::default(), get(uri, None)).await;
+        assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(headers[header::ALLOW], "POST");
+        assert_eq!(headers[header::CACHE_CONTROL], "no-store");
+        assert!(!headers.contains_key(header::ETAG));
+        assert!(body.contains("MUTATION_OVER_GET"), "{body}");
+    }
+
+    fn vary(headers: &HeaderMap) -> String {
+        let values: Vec<&str> = headers
+            .get_all(header::VARY)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .collect();
+        values.join(", ")
+    }
+
+    fn encoded(uri: &str, accept_encoding: &str) -> axum::http::Request<Body> {
+        axum::http::Request::builder()
+            .uri(uri)
+            .header(header::ACCEPT_ENCODING, accept_encoding)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn compresses_large_responses() {
+        let plain = router(build_schema(), Content::default(), &RouterConfig::default())
+            .oneshot(encoded("/llms.txt", "identity"))
+            .await
+            .unwrap();
+        assert!(!plain.headers().contains_key(header::CONTENT_ENCODING));
+        let plain = to_bytes(plain.into_body(), usize::MAX).await.unwrap();
+
+        for encoding in ["gzip", "br", "zstd"] {
+            let response = router(build_schema(), Content::default(), &RouterConfig::default())
+                .oneshot(encoded("/llms.txt", encoding))
+                .await
+                .unwrap();
+            assert_eq!(response.headers()[header::CONTENT_ENCODING], encoding);
+            assert!(vary(response.headers()).contains("accept-encoding"));
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            assert!(body.len() < plain.len() / 2, "{encoding}: {} of {}", body.len(), plain.len());
+        }
+
+        let small = router(build_schema(), Content::default(), &RouterConfig::default())
+            .oneshot(encoded("/graphql?query=%7Bping%7D", "gzip"))
+            .await
+            .unwrap();
+        assert!(!small.headers().contains_key(header::CONTENT_ENCODING));
+        assert!(!vary(small.headers()).contains("accept-encoding"));
+    }
+
+    #[tokio::test]
+    async fn compressed_variants_get_their_own_etags() {
+        let uri = "/graphql?query=%7B%20chris%20%7B%20description%20work%20%7B%20description%20%7D%20%7D%20%7D";
+        let response = router(build_schema(), Content::default(), &RouterConfig::default())
+            .oneshot(encoded(uri, "gzip"))
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+        let etag = response.headers()[header::ETAG].to_str().unwrap().to_string();
+        assert!(etag.ends_with("-gzip\""), "{etag}");
+
+        let (status, headers, _) = respond(&RouterConfig::default(), get(uri, Some(&etag))).await;
+        assert_eq!(status, StatusCode::NOT_MODIFIED);
+        assert_eq!(headers[header::ETAG], etag.as_str());
+    }
+
+    #[tokio::test]
+    async fn lambda_sends_compressed_bodies_as_binary() {
+        let response = router(build_schema(), Content::default(), &RouterConfig::default())
+            .oneshot(encoded("/llms.txt", "gzip"))
+            .await
+            .unwrap();
+        let response = lambda_http::IntoResponse::into_response(response).await;
+        assert!(matches!(response.body(), lambda_http::Body::Binary(_)));
+
+        let response = router(build_schema(), Content::default(), &RouterConfig::default())
+            .oneshot(encoded("/llms.txt", "identity"))
+            .await
+            .unwrap();
+        let response = lambda_http::IntoResponse::into_response(response).await;
+        assert!(matches!(response.body(), lambda_http::Body::Text(_)));
+    }
+
+    #[tokio::test]
+    async fn rejects_requests_without_an_origin_secret() {
+        let config = RouterConfig {
+            origin: OriginSecrets::new("new-secret,old-secret"),
+            ..RouterConfig::default()
+        };
+        let post = |secret: Option<&'static str>| {
+            let mut request = axum::http::Request::builder()
+                .method(Method::POST)
+                .uri("/graphql")
+                .header(header::CONTENT_TYPE, "application/json");
+            if let Some(secret) = secret {
+                request = request.header("x-origin-secret", secret);
+            }
+            request.body(Body::from(r#"{"query":"{ ping }"}"#)).unwrap()
+        };
+
+        for secret in ["new-secret", "old-secret"] {
+            let (_, _, body) = respond(&config, post(Some(secret))).await;
+            assert_eq!(body, r#"{"data":{"ping":"pong"}}"#);
+        }
+        for request in [post(Some("wrong")), post(None)] {
+            let (_, _, body) = respond(&config, request).await;
+            assert!(body.contains("FORBIDDEN"), "{body}");
+        }
+        let (status, _, _) = respond(&config, get("/llms.txt", None)).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[test]
+    fn forbidden_response_has_code() {
+        let json = serde_json::to_value(forbidden_response()).unwrap();
+        assert_eq!(json["errors"][0]["message"], "Forbidden");
+        assert_eq!(json["errors"][0]["extensions"]["code"], "FORBIDDEN");
+    }
+
+    #[tokio::test]
+    async fn guarded_fields_need_a_bearer_token() {
+        use crate::schema::{build_schema_with, SchemaConfig};
+
+        // sha256 of "ops-token-0123456789".
+        let hash = "d51a4891ede9d7c8a89088e5f255d5852a7154ab4b92ad562614e8d0824bfc2b";
+        let config = RouterConfig {
+            auth: Authenticator::default()
+                .with_api_tokens(&format!("ops:admin:{hash}"))
+                .unwrap(),
+            ..RouterConfig::default()
+        };
+        let token = "ops-token-0123456789";
+        let authorizations = [None, Some("Bearer wrong".to_string()), Some(format!("Bearer {token}"))];
+        let mut responses = Vec::new();
+        for authorization in authorizations {
+            let mut request = axum::http::Request::builder()
+                .method(Method::POST)
+                .uri("/graphql")
+                .header(header::CONTENT_TYPE, "application/json");
+            if let Some(authorization) = authorization {
+                request = request.header(header::AUTHORIZATION, authorization);
+            }
+            let request = request
+                .body(Body::from(r#"{"query":"{ contactSubmissions { totalCount } }"}"#))
+                .unwrap();
+            let response = router(build_schema_with(SchemaConfig::default()), Content::default(), &config)
+                .oneshot(request)
+                .await
+                .unwrap();
+            let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            responses.push(String::from_utf8(bytes.to_vec()).unwrap());
+        }
+
+        assert!(responses[0].contains("Authentication required"), "{}", responses[0]);
+        assert!(responses[1].contains("Invalid credentials: unknown token"), "{}", responses[1]);
+        assert!(responses[1].contains("UNAUTHENTICATED"), "{}", responses[1]);
+        assert_eq!(responses[2], r#"{"data":{"contactSubmissions":{"totalCount":0}}}"#);
+    }
+
+    #[tokio::test]
+    async fn rate_limits_contact_me_by_client_ip() {
+        use crate::email::{EmailConfig, Mailer, MemoryTransport};
+        use crate::ratelimit::{RateLimitConfig, RateLimiter, Rule};
+        use crate::schema::{build_schema_with, SchemaConfig};
+
+        let config = SchemaConfig {
+            mailer: Mailer {
+                transport: std::sync::Arc::new(MemoryTransport::default()),
+                config: EmailConfig {
+                    from: Some("site@example.com".to_string()),
+                    to: Some("me@example.com".to_string()),
+                },
+                ..Mailer::default()
+            },
+            rate_limiter: RateLimiter {
+                config: RateLimitConfig {
+                    per_ip: Some(Rule::new(1, Duration::from_secs(60))),
+                    per_email: None,
+                    global: None,
+                    auto_reply: None,
+                },
+                ..RateLimiter::default()
+            },
+            ..SchemaConfig::default()
+        };
+        let content = config.content.clone();
+        let app = router(build_schema_with(config), content, &RouterConfig::default());
+        let contact = |viewer: &'static str| {
+            let query = r#"mutation { contactMe(input: { from: "visitor@example.com", name: "Visitor", subject: "Hello", body: "Hi" }) { success } }"#;
+            axum::http::Request::builder()
+                .method(Method::POST)
+                .uri("/graphql")
+                .header(header::CONTENT_TYPE, "application/json")
+                .header("cloudfront-viewer-address", viewer)
+                .body(Body::from(serde_json::json!({ "query": query }).to_string()))
+                .unwrap()
+        };
+
+        let first = app.clone().oneshot(contact("203.0.113.7:1111")).await.unwrap();
+        assert!(first.headers().get(header::RETRY_AFTER).is_none());
+
+        let limited = app.clone().oneshot(contact("203.0.113.7:2222")).await.unwrap();
+        assert_eq!(limited.headers()[header::RETRY_AFTER], "60");
+        let bytes = to_bytes(limited.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["errors"][0]["extensions"]["code"], "RATE_LIMITED");
+        assert_eq!(body["errors"][0]["extensions"]["retryAfter"], 60);
+
+        let other = app.oneshot(contact("198.51.100.1:3333")).await.unwrap();
+        let bytes = to_bytes(other.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&bytes[..], br#"{"data":{"contactMe":{"success":true}}}"#);
+    }
+}
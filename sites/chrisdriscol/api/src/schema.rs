@@ -1,16 +1,76 @@
 use async_graphql::{EmptySubscription, Schema};
 
+use crate::caching::QueriesOnlyOverGet;
+use crate::content::Content;
+use crate::email::Mailer;
+use crate::inbox::Inbox;
+use crate::limits::QueryLimits;
+use crate::outbox::Outbox;
+use crate::persisted::PersistedQueries;
+use crate::ratelimit::RateLimiter;
+use crate::spam::SpamFilter;
 use crate::types::{MutationRoot, QueryRoot};
+use crate::verification::ContactVerifier;
 
 pub type AppSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
 
+/// Request-processing settings for the schema, read once at startup.
+#[derive(Clone, Default)]
+pub struct SchemaConfig {
+    pub limits: QueryLimits,
+    pub persisted: PersistedQueries,
+    pub content: Content,
+    pub mailer: Mailer,
+    pub outbox: Outbox,
+    pub inbox: Inbox,
+    pub rate_limiter: RateLimiter,
+    pub verifier: ContactVerifier,
+    pub spam: SpamFilter,
+}
+
+impl SchemaConfig {
+    pub fn from_env() -> Result<Self, String> {
+        Ok(Self {
+            limits: QueryLimits::from_env()?,
+            persisted: PersistedQueries::from_env()?,
+            content: Content::from_env()?,
+            mailer: Mailer::from_env()?,
+            outbox: Outbox::from_env()?,
+            inbox: Inbox::from_env()?,
+            rate_limiter: RateLimiter::from_env()?,
+            verifier: ContactVerifier::from_env()?,
+            spam: SpamFilter::from_env()?,
+        })
+    }
+}
+
 pub fn build_schema() -> AppSchema {
-    Schema::build(QueryRoot, MutationRoot, EmptySubscription).finish()
+    build_schema_with(SchemaConfig::from_env().expect("invalid schema config"))
+}
+
+pub fn build_schema_with(config: SchemaConfig) -> AppSchema {
+    // Persisted documents are resolved before the limits see the query text.
+    let builder = Schema::build(QueryRoot, MutationRoot, EmptySubscription)
+        .data(config.content)
+        .data(config.mailer)
+        .data(config.outbox)
+        .data(config.inbox)
+        .data(config.rate_limiter)
+        .data(config.verifier)
+        .data(config.spam)
+        .extension(config.persisted)
+        .extension(QueriesOnlyOverGet);
+    config.limits.apply(builder).finish()
 }
 
 #[cfg(test)]
 mod tests {
-    use super::build_schema;
+    use super::{build_schema, build_schema_with, SchemaConfig};
+    use crate::email::{AutoReplyConfig, Email, EmailConfig, EmailTransport, Mailer, MemoryTransport};
+    use crate::outbox::{Outbox, OutboxStatus};
+    use crate::limits::QueryLimits;
+    use crate::node::{fetch, global_id};
+    use crate::persisted::{self, PersistedMode, PersistedQueries};
     use async_graphql::Value;
 
     #[tokio::test]
@@ -47,6 +107,688 @@ mod tests {
             _ => None,
         })
         .expect("missing id");
-        assert_eq!(id, "guest");
+        assert_eq!(id, global_id("Chris", "guest").as_str());
+    }
+
+    #[tokio::test]
+    async fn node_refetches_by_global_id() {
+        let schema = build_schema();
+        let work_id = global_id("Work", "nuke-notes").to_string();
+        let response = schema
+            .execute(format!(
+                r#"{{
+                  node(id: "{work_id}") {{ id ... on Work {{ title }} }}
+                  nodes(ids: ["{}", "bm9wZTpub3Bl"]) {{ __typename }}
+                }}"#,
+                global_id("Experience", "rally-software").as_str()
+            ))
+            .await;
+        assert!(response.errors.is_empty(), "GraphQL errors: {:?}", response.errors);
+
+        let json = response.data.into_json().unwrap();
+        assert_eq!(json["node"]["id"], work_id);
+        assert_eq!(json["node"]["title"], "Nuke Notes");
+        assert_eq!(json["nodes"][0]["__typename"], "Experience");
+        assert!(json["nodes"][1].is_null());
+    }
+
+    #[tokio::test]
+    async fn every_entity_id_resolves_to_itself() {
+        let schema = build_schema();
+        let response = schema
+            .execute("{ chris { id skills { id } experience { id } work { id } } }")
+            .await;
+        assert!(response.errors.is_empty(), "GraphQL errors: {:?}", response.errors);
+
+        let json = response.data.into_json().unwrap();
+        let chris = &json["chris"];
+        let mut ids = vec![&chris["id"], &chris["skills"]["id"]];
+        for list in ["experience", "work"] {
+            ids.extend(chris[list].as_array().unwrap().iter().map(|item| &item["id"]));
+        }
+
+        let content = crate::data::chris();
+        for id in ids.into_iter().filter_map(|id| id.as_str()) {
+            assert!(fetch(&content, id).is_some(), "{id} did not resolve");
+        }
+    }
+
+    #[tokio::test]
+    async fn work_connection_pages_with_cursors() {
+        let schema = build_schema();
+        let first_page = schema
+            .execute("{ chris { workConnection(first: 2) { totalCount pageInfo { hasNextPage endCursor } edges { node { title } } } } }")
+            .await;
+        assert!(first_page.errors.is_empty(), "GraphQL errors: {:?}", first_page.errors);
+        let json = first_page.data.into_json().unwrap();
+        let connection = &json["chris"]["workConnection"];
+        assert_eq!(connection["totalCount"], crate::data::chris().work.len());
+        assert_eq!(connection["edges"].as_array().unwrap().len(), 2);
+        assert_eq!(connection["pageInfo"]["hasNextPage"], true);
+
+        let end_cursor = connection["pageInfo"]["endCursor"].as_str().unwrap();
+        let next_page = schema
+            .execute(format!(
+                r#"{{ chris {{ workConnection(first: 1, after: "{end_cursor}") {{ pageInfo {{ hasPreviousPage }} edges {{ node {{ title }} }} }} }} }}"#
+            ))
+            .await;
+        assert!(next_page.errors.is_empty(), "GraphQL errors: {:?}", next_page.errors);
+        let json = next_page.data.into_json().unwrap();
+        let connection = &json["chris"]["workConnection"];
+        assert_eq!(connection["pageInfo"]["hasPreviousPage"], true);
+        assert_eq!(
+            connection["edges"][0]["node"]["title"],
+            crate::data::chris().work[2].title.clone().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn search_returns_typed_hits() {
+        let schema = build_schema();
+        let response = schema
+            .execute(
+                r#"{ search(query: "graphql", first: 3) {
+                  score
+                  snippet
+                  item { __typename ... on Work { title } ... on Experience { location } }
+                } }"#,
+            )
+            .await;
+        assert!(response.errors.is_empty(), "GraphQL errors: {:?}", response.errors);
+
+        let json = response.data.into_json().unwrap();
+        let hits = json["search"].as_array().unwrap();
+        assert!(!hits.is_empty() && hits.len() <= 3);
+        assert!(hits[0]["snippet"].as_str().unwrap().contains("<mark>GraphQL</mark>"));
+        assert!(hits.iter().all(|hit| hit["item"]["__typename"] != "About"));
+    }
+
+    /// The web app's page query, as generated in `web/src/graphql/generated`.
+    const APP_QUERY: &str = r#"
+        query AppQuery {
+          chris {
+            id title description
+            about { description imageUrl imageCaption imageTitle tagLine }
+            experience { duration title location description imageUrl }
+            skills { languages technologies tools loves }
+            work {
+              title subTitle location link date imageUrl
+              description video technologies
+            }
+            social { linkedIn github email }
+          }
+        }
+    "#;
+
+    const INTROSPECTION_QUERY: &str = r#"
+        query IntrospectionQuery {
+          __schema {
+            queryType { name } mutationType { name }
+            types { ...FullType }
+            directives { name args { ...InputValue } }
+          }
+        }
+        fragment FullType on __Type {
+          kind name description
+          fields(includeDeprecated: true) { name args { ...InputValue } type { ...TypeRef } }
+          inputFields { ...InputValue }
+          interfaces { ...TypeRef }
+          enumValues(includeDeprecated: true) { name }
+          possibleTypes { ...TypeRef }
+        }
+        fragment InputValue on __InputValue { name type { ...TypeRef } defaultValue }
+        fragment TypeRef on __Type {
+          kind name
+          ofType { kind name ofType { kind name ofType { kind name ofType { kind name
+            ofType { kind name ofType { kind name ofType { kind name } } } } } } }
+        }
+    "#;
+
+    fn limit_extensions(response: &async_graphql::Response) -> serde_json::Value {
+        assert_eq!(response.errors.len(), 1, "{:?}", response.errors);
+        serde_json::to_value(&response.errors[0]).unwrap()["extensions"].clone()
+    }
+
+    #[tokio::test]
+    async fn default_limits_allow_the_app_and_introspection() {
+        let schema = build_schema_with(SchemaConfig::default());
+        for query in [APP_QUERY, INTROSPECTION_QUERY] {
+            let response = schema.execute(query).await;
+            assert!(response.errors.is_empty(), "GraphQL errors: {:?}", response.errors);
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_queries_over_limits_with_codes() {
+        let tight = QueryLimits {
+            depth: 3,
+            complexity: 100,
+            recursive_depth: 5,
+            aliases: 2,
+            fields: 20,
+        };
+        let schema = build_schema_with(SchemaConfig {
+            limits: tight,
+            ..SchemaConfig::default()
+        });
+        let cases = [
+            ("{ chris { work { dates { start { year } } } } }", "depth"),
+            ("{ chris { workConnection(first: 50) { edges { node { title subTitle location } } } } }", "complexity"),
+            ("{ chris { work { dates { start { season { x { y } } } } } } }", "recursiveDepth"),
+            ("{ a: ping b: ping c: ping }", "aliases"),
+            (
+                "{ chris { skills { languages technologies tools loves } social { linkedIn github email } \
+                 about { description imageUrl imageCaption imageTitle tagLine } title description id __typename } ping }",
+                "fields",
+            ),
+        ];
+        for (query, limit) in cases {
+            let extensions = limit_extensions(&schema.execute(query).await);
+            assert_eq!(extensions["code"], "QUERY_LIMIT_EXCEEDED", "{query}");
+            assert_eq!(extensions["limit"], limit, "{query}");
+        }
+
+        let extensions = limit_extensions(&schema.execute("{ a: ping b: ping c: ping }").await);
+        assert_eq!(extensions["max"], 2);
+        assert_eq!(extensions["actual"], 3);
+    }
+
+    #[tokio::test]
+    async fn list_fields_are_charged_per_item() {
+        let limits = QueryLimits {
+            complexity: 30,
+            ..QueryLimits::default()
+        };
+        let schema = build_schema_with(SchemaConfig {
+            limits,
+            ..SchemaConfig::default()
+        });
+        let small = schema
+            .execute("{ chris { workConnection(first: 2) { edges { node { title } } } } }")
+            .await;
+        assert!(small.errors.is_empty(), "GraphQL errors: {:?}", small.errors);
+        let unbounded = schema.execute("{ chris { work { title subTitle } } }").await;
+        assert_eq!(limit_extensions(&unbounded)["limit"], "complexity");
+    }
+
+    fn persisted_request(query: &str, sha256_hash: &str) -> async_graphql::Request {
+        let mut request = async_graphql::Request::new(query);
+        request.extensions.insert(
+            "persistedQuery".to_string(),
+            async_graphql::value!({ "version": 1, "sha256Hash": sha256_hash }),
+        );
+        request
+    }
+
+    fn error_code(response: &async_graphql::Response) -> serde_json::Value {
+        assert_eq!(response.errors.len(), 1, "{:?}", response.errors);
+        serde_json::to_value(&response.errors[0]).unwrap()["extensions"]["code"].clone()
+    }
+
+    #[tokio::test]
+    async fn registers_and_replays_automatic_persisted_queries() {
+        let schema = build_schema_with(SchemaConfig::default());
+        let query = "{ ping }";
+        let key = persisted::hash(query);
+
+        let missing = schema.execute(persisted_request("", &key)).await;
+        assert_eq!(missing.errors[0].message, "PersistedQueryNotFound");
+        assert_eq!(error_code(&missing), "PERSISTED_QUERY_NOT_FOUND");
+
+        let mismatch = schema.execute(persisted_request("{ __typename }", &key)).await;
+        assert_eq!(error_code(&mismatch), "PERSISTED_QUERY_HASH_MISMATCH");
+
+        // Documents that fail to parse or validate are not kept.
+        for invalid in ["{ ping", "{ nope }"] {
+            let invalid_key = persisted::hash(invalid);
+            let rejected = schema.execute(persisted_request(invalid, &invalid_key)).await;
+            assert!(!rejected.errors.is_empty());
+            let replay = schema.execute(persisted_request("", &invalid_key)).await;
+            assert_eq!(error_code(&replay), "PERSISTED_QUERY_NOT_FOUND");
+        }
+
+        let registered = schema.execute(persisted_request(query, &key)).await;
+        assert!(registered.errors.is_empty(), "GraphQL errors: {:?}", registered.errors);
+        let replayed = schema.execute(persisted_request("", &key)).await;
+        assert!(replayed.errors.is_empty(), "GraphQL errors: {:?}", replayed.errors);
+        assert_eq!(replayed.data.into_json().unwrap()["ping"], "pong");
+    }
+
+    #[tokio::test]
+    async fn persisted_only_mode_rejects_unknown_documents() {
+        let manifest = persisted::manifest_from_codegen(include_str!(
+            "../../web/src/graphql/generated/graphql.ts"
+        ));
+        let (key, app_query) = manifest
+            .iter()
+            .find(|(_, document)| document.contains("query AppQuery"))
+            .map(|(key, document)| (key.clone(), document.clone()))
+            .unwrap();
+        let schema = build_schema_with(SchemaConfig {
+            persisted: PersistedQueries::new(PersistedMode::PersistedOnly, manifest, 10),
+            ..SchemaConfig::default()
+        });
+
+        let by_hash = schema.execute(persisted_request("", &key)).await;
+        assert!(by_hash.errors.is_empty(), "GraphQL errors: {:?}", by_hash.errors);
+        let in_full = schema.execute(app_query.as_str()).await;
+        assert!(in_full.errors.is_empty(), "GraphQL errors: {:?}", in_full.errors);
+
+        let unknown = schema.execute("{ ping }").await;
+        assert_eq!(error_code(&unknown), "PERSISTED_QUERY_NOT_ALLOWED");
+        let registration = schema.execute(persisted_request("{ ping }", &persisted::hash("{ ping }"))).await;
+        assert_eq!(error_code(&registration), "PERSISTED_QUERY_NOT_ALLOWED");
+        let replay = schema.execute(persisted_request("", &persisted::hash("{ ping }"))).await;
+        assert_eq!(error_code(&replay), "PERSISTED_QUERY_NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn contact_me_sends_through_the_schema_mailer() {
+        let transport = std::sync::Arc::new(MemoryTransport::default());
+        let schema = build_schema_with(SchemaConfig {
+            mailer: Mailer {
+                transport: transport.clone(),
+                config: EmailConfig {
+                    from: Some("site@example.com".to_string()),
+                    to: Some("me@example.com".to_string()),
+                },
+                ..Mailer::default()
+            },
+            ..SchemaConfig::default()
+        });
+        let response = schema
+            .execute(
+                r#"mutation {
+                  contactMe(input: { from: "visitor@example.com", name: "Visitor", subject: "Hello", body: "Hi" }) {
+                    success
+                  }
+                }"#,
+            )
+            .await;
+
+        assert!(response.errors.is_empty(), "GraphQL errors: {:?}", response.errors);
+        let sent = transport.sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].subject, "Hello");
+    }
+
+    #[tokio::test]
+    async fn contact_me_keeps_messages_the_transport_rejects() {
+        struct Down;
+
+        #[async_graphql::async_trait::async_trait]
+        impl EmailTransport for Down {
+            async fn send(&self, _email: &Email) -> Result<(), String> {
+                Err("throttled".to_string())
+            }
+        }
+
+        let outbox = Outbox::default();
+        let schema = build_schema_with(SchemaConfig {
+            mailer: Mailer {
+                transport: std::sync::Arc::new(Down),
+                config: EmailConfig {
+                    from: Some("site@example.com".to_string()),
+                    to: Some("me@example.com".to_string()),
+                },
+                ..Mailer::default()
+            },
+            outbox: outbox.clone(),
+            ..SchemaConfig::default()
+        });
+        let response = schema
+            .execute(
+                r#"mutation {
+                  contactMe(input: { from: "visitor@example.com", name: "Visitor", subject: "Hello", body: "Hi" }) {
+                    success
+                    submissionId
+                  }
+                }"#,
+            )
+            .await;
+
+        assert!(response.errors.is_empty(), "GraphQL errors: {:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        assert_eq!(data["contactMe"]["success"], true);
+        let id = data["contactMe"]["submissionId"].as_str().unwrap();
+        let message = outbox.store.get(id).await.unwrap().unwrap();
+        assert_eq!(message.status, OutboxStatus::Pending);
+        assert_eq!(message.attempts, 1);
+        assert_eq!(message.last_error.as_deref(), Some("throttled"));
+        assert_eq!(message.email.subject, "Hello");
+    }
+
+    #[tokio::test]
+    async fn contact_me_auto_replies_once_checks_pass() {
+        let transport = std::sync::Arc::new(MemoryTransport::default());
+        let schema = build_schema_with(SchemaConfig {
+            mailer: Mailer {
+                transport: transport.clone(),
+                config: EmailConfig {
+                    from: Some("site@example.com".to_string()),
+                    to: Some("me@example.com".to_string()),
+                },
+                auto_reply: AutoReplyConfig {
+                    enabled: true,
+                    ..AutoReplyConfig::default()
+                },
+                ..Mailer::default()
+            },
+            ..SchemaConfig::default()
+        });
+        let contact = |from: &str, body: &str, extra: &str| {
+            format!(
+                r#"mutation {{
+                  contactMe(input: {{ from: "{from}", name: "Visitor", subject: "Hello", body: "{body}"{extra} }}) {{
+                    success
+                  }}
+                }}"#
+            )
+        };
+
+        // Spam is answered with success but gets no confirmation either.
+        let response = schema.execute(contact("bot@example.com", "Hi", r#", website: "http://spam.example""#)).await;
+        assert!(response.errors.is_empty(), "GraphQL errors: {:?}", response.errors);
+        assert!(transport.sent().is_empty());
+
+        for body in ["First", "Second", "Third"] {
+            let response = schema.execute(contact("visitor@example.com", body, ", sendCopy: true")).await;
+            assert!(response.errors.is_empty(), "GraphQL errors: {:?}", response.errors);
+        }
+        let sent = transport.sent();
+        let replies: Vec<_> = sent.iter().filter(|email| email.to == "visitor@example.com").collect();
+        assert_eq!(sent.len() - replies.len(), 3);
+        // The per-recipient cap allows two confirmations a day.
+        assert_eq!(replies.len(), 2);
+        assert!(replies[0].text.contains("First"), "{}", replies[0].text);
+    }
+
+    #[tokio::test]
+    async fn contact_me_returns_one_error_per_invalid_field() {
+        let transport = std::sync::Arc::new(MemoryTransport::default());
+        let schema = build_schema_with(SchemaConfig {
+            mailer: Mailer {
+                transport: transport.clone(),
+                ..Mailer::default()
+            },
+            ..SchemaConfig::default()
+        });
+        let response = schema
+            .execute(
+                r#"mutation {
+                  contactMe(input: { from: "not-an-email", name: "Visitor", subject: "Hi\r\nBcc: x@example.com", body: "" }) {
+                    success
+                  }
+                }"#,
+            )
+            .await;
+
+        let errors: Vec<_> = response
+            .errors
+            .iter()
+            .map(|error| {
+                let value = serde_json::to_value(error).unwrap();
+                assert_eq!(value["path"][0], "contactMe");
+                (value["extensions"]["field"].clone(), value["extensions"]["code"].clone())
+            })
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                ("from".into(), "INVALID_EMAIL".into()),
+                ("subject".into(), "INVALID_CHARACTERS".into()),
+                ("body".into(), "REQUIRED".into()),
+            ]
+        );
+        assert!(transport.sent().is_empty());
+    }
+
+    #[tokio::test]
+    async fn contact_me_requires_a_solved_challenge_in_pow_mode() {
+        use crate::signing::Signer;
+        use crate::verification::{ContactVerifier, ProofOfWork};
+        use sha2::{Digest, Sha256};
+
+        let transport = std::sync::Arc::new(MemoryTransport::default());
+        let pow = ProofOfWork::new(Signer::new("secret"), 4, std::time::Duration::from_secs(60)).unwrap();
+        let schema = build_schema_with(SchemaConfig {
+            mailer: Mailer {
+                transport: transport.clone(),
+                config: EmailConfig {
+                    from: Some("site@example.com".to_string()),
+                    to: Some("me@example.com".to_string()),
+                },
+                ..Mailer::default()
+            },
+            verifier: ContactVerifier::new(pow),
+            ..SchemaConfig::default()
+        });
+        let contact = |token: &str| {
+            async_graphql::Request::new(
+                "mutation ($token: String) { contactMe(input: { from: \"visitor@example.com\", name: \"Visitor\", subject: \"Hello\", body: \"Hi\", verificationToken: $token }) { success } }",
+            )
+            .variables(async_graphql::Variables::from_json(serde_json::json!({ "token": token })))
+        };
+
+        let missing = schema.execute(contact("")).await;
+        assert_eq!(error_code(&missing), "VERIFICATION_REQUIRED");
+
+        let challenge = schema.execute("{ contactChallenge { challenge difficulty } }").await;
+        let challenge = challenge.data.into_json().unwrap()["contactChallenge"].clone();
+        assert_eq!(challenge["difficulty"], 4);
+        let challenge = challenge["challenge"].as_str().unwrap();
+        let solution = (0u64..)
+            .map(|counter| format!("{challenge}:{counter}"))
+            .find(|token| Sha256::digest(token.as_bytes())[0] < 0x10)
+            .unwrap();
+
+        let sent = schema.execute(contact(&solution)).await;
+        assert!(sent.errors.is_empty(), "GraphQL errors: {:?}", sent.errors);
+        let replayed = schema.execute(contact(&solution)).await;
+        assert_eq!(error_code(&replayed), "VERIFICATION_FAILED");
+        assert_eq!(transport.sent().len(), 1);
+
+        let off = build_schema_with(SchemaConfig::default());
+        let response = off.execute("{ contactChallenge { challenge } }").await;
+        assert_eq!(response.data.into_json().unwrap()["contactChallenge"], serde_json::Value::Null);
+    }
+
+    #[tokio::test]
+    async fn contact_me_quarantines_spam_without_telling_the_sender() {
+        use crate::signing::Signer;
+        use crate::spam::{MemoryQuarantine, SpamConfig, SpamFilter, SpamSignal};
+
+        let transport = std::sync::Arc::new(MemoryTransport::default());
+        let quarantine = std::sync::Arc::new(MemoryQuarantine::default());
+        let schema = build_schema_with(SchemaConfig {
+            mailer: Mailer {
+                transport: transport.clone(),
+                config: EmailConfig {
+                    from: Some("site@example.com".to_string()),
+                    to: Some("me@example.com".to_string()),
+                },
+                ..Mailer::default()
+            },
+            spam: SpamFilter::new(
+                SpamConfig {
+                    min_submit: std::time::Duration::ZERO,
+                    ..SpamConfig::default()
+                },
+                Signer::new("secret"),
+                quarantine.clone(),
+            ),
+            ..SchemaConfig::default()
+        });
+        let token = schema.execute("{ contactFormToken }").await.data.into_json().unwrap()["contactFormToken"].clone();
+        let contact = |subject: &str, website: &str| {
+            async_graphql::Request::new(
+                "mutation ($subject: String!, $website: String, $token: String) { contactMe(input: { from: \"visitor@example.com\", name: \"Visitor\", subject: $subject, body: $subject, website: $website, formToken: $token }) { success } }",
+            )
+            .variables(async_graphql::Variables::from_json(
+                serde_json::json!({ "subject": subject, "website": website, "token": token }),
+            ))
+        };
+
+        let human = schema.execute(contact("Hello", "")).await;
+        let bot = schema.execute(contact("Buy now", "https://spam.example")).await;
+        for response in [human, bot] {
+            assert!(response.errors.is_empty(), "GraphQL errors: {:?}", response.errors);
+            assert_eq!(response.data.into_json().unwrap()["contactMe"]["success"], true);
+        }
+
+        let sent = transport.sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].subject, "Hello");
+        let held = quarantine.held();
+        assert_eq!(held.len(), 1);
+        assert_eq!(held[0].0, "Buy now");
+        assert_eq!(held[0].1.signals, vec![SpamSignal::Honeypot]);
+    }
+
+    #[tokio::test]
+    async fn admins_manage_the_contact_inbox() {
+        use crate::auth::{Principal, Role};
+
+        let schema = build_schema_with(SchemaConfig {
+            mailer: Mailer {
+                config: EmailConfig {
+                    from: Some("site@example.com".to_string()),
+                    to: Some("me@example.com".to_string()),
+                },
+                ..Mailer::default()
+            },
+            ..SchemaConfig::default()
+        });
+        for (subject, website) in [("Hello", ""), ("Buy now", "https://spam.example")] {
+            let response = schema
+                .execute(
+                    async_graphql::Request::new(
+                        "mutation ($subject: String!, $website: String) { contactMe(input: { from: \"visitor@example.com\", name: \"Visitor\", subject: $subject, body: $subject, website: $website }) { success } }",
+                    )
+                    .variables(async_graphql::Variables::from_json(
+                        serde_json::json!({ "subject": subject, "website": website }),
+                    )),
+                )
+                .await;
+            assert!(response.errors.is_empty(), "GraphQL errors: {:?}", response.errors);
+        }
+
+        let list = "query ($filter: ContactSubmissionFilter) { contactSubmissions(filter: $filter) { totalCount nodes { id subject quarantined read deliveryStatus spamSignals } } }";
+        let admin = |query: &str, variables: serde_json::Value| {
+            async_graphql::Request::new(query)
+                .variables(async_graphql::Variables::from_json(variables))
+                .data(Principal {
+                    subject: "chris".to_string(),
+                    roles: vec![Role::Admin],
+                })
+        };
+
+        let anonymous = schema.execute(list).await;
+        assert_eq!(error_code(&anonymous), "UNAUTHENTICATED");
+        let editor = schema
+            .execute(async_graphql::Request::new(list).data(Principal {
+                subject: "ci".to_string(),
+                roles: vec![Role::Editor],
+            }))
+            .await;
+        assert_eq!(error_code(&editor), "FORBIDDEN");
+
+        let response = schema
+            .execute(admin(list, serde_json::json!({ "filter": { "quarantined": false } })))
+            .await;
+        assert!(response.errors.is_empty(), "GraphQL errors: {:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        let human = &data["contactSubmissions"]["nodes"][0];
+        assert_eq!(data["contactSubmissions"]["totalCount"], 1);
+        assert_eq!(human["subject"], "Hello");
+        assert_eq!(human["deliveryStatus"], "SENT");
+        let id = human["id"].as_str().unwrap().to_string();
+
+        let response = schema
+            .execute(admin(list, serde_json::json!({ "filter": { "quarantined": true } })))
+            .await;
+        let data = response.data.into_json().unwrap();
+        let bot = &data["contactSubmissions"]["nodes"][0];
+        assert_eq!(bot["subject"], "Buy now");
+        assert_eq!(bot["deliveryStatus"], "QUARANTINED");
+        assert_eq!(bot["spamSignals"][0], "honeypot");
+
+        let mark = "mutation ($id: ID!) { markSubmissionRead(id: $id) { read } }";
+        let response = schema.execute(admin(mark, serde_json::json!({ "id": id }))).await;
+        assert!(response.errors.is_empty(), "GraphQL errors: {:?}", response.errors);
+        assert_eq!(response.data.into_json().unwrap()["markSubmissionRead"]["read"], true);
+
+        let delete = "mutation ($id: ID!) { deleteSubmission(id: $id) { deleted } }";
+        let anonymous = schema
+            .execute(async_graphql::Request::new(delete).variables(async_graphql::Variables::from_json(
+                serde_json::json!({ "id": id }),
+            )))
+            .await;
+        assert_eq!(error_code(&anonymous), "UNAUTHENTICATED");
+        let response = schema.execute(admin(delete, serde_json::json!({ "id": id }))).await;
+        assert_eq!(response.data.into_json().unwrap()["deleteSubmission"]["deleted"], true);
+
+        let response = schema.execute(admin(list, serde_json::json!({}))).await;
+        assert_eq!(response.data.into_json().unwrap()["contactSubmissions"]["totalCount"], 1);
+    }
+
+    #[tokio::test]
+    async fn editors_edit_content_with_optimistic_versions() {
+        use crate::auth::{Principal, Role};
+
+        let schema = build_schema_with(SchemaConfig::default());
+        let as_role = |role: Role| {
+            move |query: &str, variables: serde_json::Value| {
+                async_graphql::Request::new(query)
+                    .variables(async_graphql::Variables::from_json(variables))
+                    .data(Principal {
+                        subject: "chris".to_string(),
+                        roles: vec![role],
+                    })
+            }
+        };
+        let (admin, editor) = (as_role(Role::Admin), as_role(Role::Editor));
+        let add = "mutation ($version: Int!) { addWork(version: $version, slug: \"new-site\", input: { title: \"Quokka tracker\", technologies: [\"Rust\"] }, position: 0) { version work { id title } } }";
+
+        let anonymous = schema
+            .execute(async_graphql::Request::new(add).variables(async_graphql::Variables::from_json(
+                serde_json::json!({ "version": 0 }),
+            )))
+            .await;
+        assert_eq!(error_code(&anonymous), "UNAUTHENTICATED");
+
+        let response = schema.execute(admin(add, serde_json::json!({ "version": 0 }))).await;
+        assert!(response.errors.is_empty(), "GraphQL errors: {:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        assert_eq!(data["addWork"]["version"], 1);
+        assert_eq!(data["addWork"]["work"][0]["title"], "Quokka tracker");
+        let id = data["addWork"]["work"][0]["id"].as_str().unwrap().to_string();
+
+        // Reads and search see the edit straight away.
+        let response = schema.execute("{ chris { version } search(query: \"quokka\") { item { ... on Work { id } } } }").await;
+        let data = response.data.into_json().unwrap();
+        assert_eq!(data["chris"]["version"], 1);
+        assert_eq!(data["search"][0]["item"]["id"], id.as_str());
+
+        let stale = schema.execute(admin(add, serde_json::json!({ "version": 0 }))).await;
+        assert_eq!(error_code(&stale), "VERSION_CONFLICT");
+        let extensions = serde_json::to_value(&stale.errors[0]).unwrap()["extensions"].clone();
+        assert_eq!(extensions["currentVersion"], 1);
+
+        let duplicate = schema.execute(admin(add, serde_json::json!({ "version": 1 }))).await;
+        assert_eq!(error_code(&duplicate), "INVALID_CONTENT");
+
+        let remove = "mutation ($version: Int!, $id: ID!) { removeWork(version: $version, id: $id) { version work { id } } }";
+        // Editors may change content; admins pass every role check.
+        let response = schema
+            .execute(editor(remove, serde_json::json!({ "version": 1, "id": id })))
+            .await;
+        let data = response.data.into_json().unwrap();
+        assert_eq!(data["removeWork"]["version"], 2);
+        assert_eq!(data["removeWork"]["work"].as_array().unwrap().len(), crate::data::chris().work.len());
+        let missing = schema
+            .execute(editor(remove, serde_json::json!({ "version": 2, "id": id })))
+            .await;
+        assert_eq!(error_code(&missing), "NOT_FOUND");
     }
 }
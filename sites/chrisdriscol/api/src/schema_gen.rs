@@ -1,16 +1,14 @@
 use std::fs;
 use std::path::PathBuf;
 
-mod schema;
-mod types;
-mod data;
-mod email;
+use chris_driscol_api::schema;
 
 fn main() {
     let schema = schema::build_schema();
     let sdl = schema.sdl();
 
     let output_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("..")
         .join("..")
         .join("..")
         .join("packages")
@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+
+use async_graphql::{SimpleObject, Union};
+
+use crate::types::{About, Chris, Experience, Work};
+
+/// Default and maximum number of hits returned by `search`.
+const DEFAULT_HITS: usize = 10;
+const MAX_HITS: usize = 50;
+
+/// Characters of context kept on either side of the first match in a snippet.
+const SNIPPET_CONTEXT: usize = 80;
+
+#[derive(Union, Clone)]
+pub enum SearchResult {
+    Work(Work),
+    Experience(Experience),
+    About(About),
+}
+
+#[derive(SimpleObject)]
+pub struct SearchHit {
+    /// Relevance of the hit; higher is better. Only comparable within one query.
+    pub score: f64,
+    /// Plain-text excerpt around the best match, with matched terms wrapped in
+    /// `<mark>` tags.
+    pub snippet: String,
+    pub item: SearchResult,
+}
+
+struct Field {
+    /// Field text with HTML tags removed.
+    text: String,
+    weight: f64,
+}
+
+struct Document {
+    item: SearchResult,
+    fields: Vec<Field>,
+}
+
+struct Posting {
+    doc: usize,
+    field: usize,
+    count: usize,
+}
+
+/// Inverted index over the portfolio, built once when the schema is built.
+pub struct SearchIndex {
+    documents: Vec<Document>,
+    terms: HashMap<String, Vec<Posting>>,
+}
+
+impl SearchIndex {
+    pub fn new(chris: &Chris) -> Self {
+        let mut documents = Vec::new();
+        for work in &chris.work {
+            let mut fields = vec![
+                field(work.title.as_deref(), 3.0),
+                field(work.sub_title.as_deref(), 2.0),
+                field(Some(&work.technologies.join(", ")), 2.0),
+            ];
+            fields.extend(work.description.iter().map(|paragraph| field(Some(paragraph), 1.0)));
+            documents.push(Document {
+                item: SearchResult::Work(work.clone()),
+                fields,
+            });
+        }
+        for exp in &chris.experience {
+            documents.push(Document {
+                item: SearchResult::Experience(exp.clone()),
+                fields: vec![
+                    field(exp.title.as_deref(), 3.0),
+                    field(exp.location.as_deref(), 2.0),
+                    field(exp.description.as_deref(), 1.0),
+                ],
+            });
+        }
+        let mut about_fields = vec![field(chris.about.tag_line.as_deref(), 2.0)];
+        about_fields.extend(chris.about.description.iter().map(|paragraph| field(Some(paragraph), 1.0)));
+        documents.push(Document {
+            item: SearchResult::About(chris.about.clone()),
+            fields: about_fields,
+        });
+
+        let mut terms: HashMap<String, Vec<Posting>> = HashMap::new();
+        for (doc, document) in documents.iter().enumerate() {
+            for (field, value) in document.fields.iter().enumerate() {
+                let mut counts: HashMap<String, usize> = HashMap::new();
+                for token in tokenize(&value.text) {
+                    *counts.entry(token).or_default() += 1;
+                }
+                for (token, count) in counts {
+                    terms.entry(token).or_default().push(Posting { doc, field, count });
+                }
+            }
+        }
+
+        Self { documents, terms }
+    }
+
+    /// Returns up to `first` hits for `query`, best first. Every query term
+    /// must match a term in the document, either exactly or as a prefix.
+    pub fn search(&self, query: &str, first: Option<usize>) -> Vec<SearchHit> {
+        let query_terms = tokenize(query);
+        if query_terms.is_empty() {
+            return Vec::new();
+        }
+
+        let total = self.documents.len() as f64;
+        let mut scores: HashMap<usize, (f64, usize)> = HashMap::new();
+        for query_term in &query_terms {
+            let mut term_scores: HashMap<usize, f64> = HashMap::new();
+            for (term, postings) in &self.terms {
+                let exactness = if term == query_term {
+                    1.0
+                } else if query_term.len() >= 3 && term.starts_with(query_term.as_str()) {
+                    0.5
+                } else {
+                    continue;
+                };
+                let documents_with_term = {
+                    let mut docs: Vec<usize> = postings.iter().map(|posting| posting.doc).collect();
+                    docs.dedup();
+                    docs.len() as f64
+                };
+                let idf = (1.0 + total / documents_with_term).ln();
+                for posting in postings {
+                    let weight = self.documents[posting.doc].fields[posting.field].weight;
+                    let tf = 1.0 + (posting.count as f64).ln();
+                    *term_scores.entry(posting.doc).or_default() += exactness * weight * tf * idf;
+                }
+            }
+            for (doc, score) in term_scores {
+                let entry = scores.entry(doc).or_default();
+                entry.0 += score;
+                entry.1 += 1;
+            }
+        }
+
+        let mut ranked: Vec<(usize, f64)> = scores
+            .into_iter()
+            .filter(|(_, (_, matched))| *matched == query_terms.len())
+            .map(|(doc, (score, _))| (doc, score))
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        let limit = first.unwrap_or(DEFAULT_HITS).min(MAX_HITS);
+        ranked
+            .into_iter()
+            .take(limit)
+            .map(|(doc, score)| {
+                let document = &self.documents[doc];
+                SearchHit {
+                    score: (score * 1000.0).round() / 1000.0,
+                    snippet: snippet(document, &query_terms),
+                    item: document.item.clone(),
+                }
+            })
+            .collect()
+    }
+}
+
+fn field(text: Option<&str>, weight: f64) -> Field {
+    Field {
+        text: strip_tags(text.unwrap_or_default()),
+        weight,
+    }
+}
+
+fn strip_tags(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => output.push(c),
+            _ => {}
+        }
+    }
+    output
+}
+
+fn is_token_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '#' || c == '+'
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !is_token_char(c))
+        .filter(|token| !token.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Whether `word` counts as a match for any of the query terms.
+fn matches(word: &str, query_terms: &[String]) -> bool {
+    let word = word.to_lowercase();
+    query_terms
+        .iter()
+        .any(|term| word == *term || (term.len() >= 3 && word.starts_with(term.as_str())))
+}
+
+/// Picks the highest-weighted field containing a match (the earliest one on
+/// ties) and cuts a window around the first matching word.
+fn snippet(document: &Document, query_terms: &[String]) -> String {
+    let best = document
+        .fields
+        .iter()
+        .filter(|field| tokenize(&field.text).iter().any(|token| matches(token, query_terms)))
+        .rev()
+        .max_by(|a, b| a.weight.total_cmp(&b.weight))
+        .or_else(|| document.fields.iter().find(|field| !field.text.is_empty()));
+    let Some(field) = best else {
+        return String::new();
+    };
+    let text = field.text.as_str();
+
+    // Split into alternating runs of word and non-word characters, keeping
+    // byte offsets so the window can be cut on char boundaries.
+    let mut runs: Vec<(usize, &str, bool)> = Vec::new();
+    let mut start = 0;
+    let mut kind = None;
+    for (index, c) in text.char_indices() {
+        let is_word = is_token_char(c);
+        if let Some(previous) = kind.filter(|previous| *previous != is_word) {
+            runs.push((start, &text[start..index], previous));
+            start = index;
+        }
+        kind = Some(is_word);
+    }
+    if let Some(kind) = kind {
+        runs.push((start, &text[start..], kind));
+    }
+
+    let first_match = runs
+        .iter()
+        .find(|(_, run, is_word)| *is_word && matches(run, query_terms))
+        .map(|(offset, _, _)| *offset)
+        .unwrap_or(0);
+    let window_start = first_match.saturating_sub(SNIPPET_CONTEXT);
+    let window_end = first_match + SNIPPET_CONTEXT * 2;
+
+    let mut output = String::new();
+    if window_start > 0 {
+        output.push('…');
+    }
+    for (offset, run, is_word) in &runs {
+        if *offset + run.len() <= window_start || *offset >= window_end {
+            continue;
+        }
+        if *is_word && matches(run, query_terms) {
+            output.push_str(&format!("<mark>{run}</mark>"));
+        } else {
+            output.push_str(run);
+        }
+    }
+    if runs.last().is_some_and(|(offset, _, _)| *offset >= window_end) {
+        output.push('…');
+    }
+    output.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data;
+
+    fn index() -> SearchIndex {
+        SearchIndex::new(&data::load(None).unwrap())
+    }
+
+    fn slug(item: &SearchResult) -> &str {
+        match item {
+            SearchResult::Work(work) => &work.slug,
+            SearchResult::Experience(exp) => &exp.slug,
+            SearchResult::About(_) => "about",
+        }
+    }
+
+    #[test]
+    fn ranks_titles_above_descriptions() {
+        let hits = index().search("drupal", None);
+        let slugs: Vec<&str> = hits.iter().map(|hit| slug(&hit.item)).collect();
+        assert_eq!(slugs.first(), Some(&"agileu-ondemand-lms"), "{slugs:?}");
+        assert!(slugs.contains(&"rally-software"), "{slugs:?}");
+        assert!(hits.windows(2).all(|pair| pair[0].score >= pair[1].score));
+    }
+
+    #[test]
+    fn requires_every_term_and_limits_results() {
+        let index = index();
+        let hits = index.search("nuclear medicine", Some(2));
+        assert!(hits.len() <= 2);
+        assert!(hits.iter().all(|hit| hit.snippet.to_lowercase().contains("<mark>nuclear</mark>")
+            || hit.snippet.to_lowercase().contains("<mark>medicine</mark>")));
+        assert!(index.search("drupal kubernetes", None).is_empty());
+        assert!(index.search("  ", None).is_empty());
+    }
+
+    #[test]
+    fn highlights_prefix_matches() {
+        let hits = index().search("snowboard", None);
+        assert_eq!(hits.len(), 1);
+        assert!(matches!(hits[0].item, SearchResult::About(_)));
+        assert!(hits[0].snippet.contains("<mark>snowboarding</mark>"), "{}", hits[0].snippet);
+    }
+
+    #[test]
+    fn strips_tags_before_indexing() {
+        assert_eq!(strip_tags("I <i class=\"fa\"></i> <strong>Agile</strong>"), "I  Agile");
+        assert!(index().search("strong", None).is_empty());
+    }
+}
@@ -0,0 +1,115 @@
+use std::sync::Arc;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Signs short tokens handed to clients (challenges, form timestamps) so the
+/// API can trust them when they come back without storing them.
+#[derive(Clone)]
+pub struct Signer {
+    key: Arc<[u8]>,
+}
+
+impl Signer {
+    pub fn new(key: impl AsRef<[u8]>) -> Self {
+        Self {
+            key: Arc::from(key.as_ref()),
+        }
+    }
+
+    /// A key that only this process knows.
+    pub fn random() -> Self {
+        Self::new(random_bytes::<32>())
+    }
+
+    /// Reads the key from `name`. Without one, a random key is used and tokens
+    /// only verify on the instance that issued them; that is fine for a local
+    /// server but not on Lambda, where it fails instead.
+    pub fn from_env(name: &str) -> Result<Self, String> {
+        match std::env::var(name) {
+            Ok(key) if !key.is_empty() => Ok(Self::new(key)),
+            _ if std::env::var_os("AWS_LAMBDA_FUNCTION_NAME").is_some() => {
+                Err(format!("{name} must be set on Lambda"))
+            }
+            _ => {
+                eprintln!("{name} is not set; signing with a per-process key");
+                Ok(Self::random())
+            }
+        }
+    }
+
+    /// Reads the key from `name`, which must be set so every instance signs
+    /// and verifies with the same key.
+    pub fn require_env(name: &str) -> Result<Self, String> {
+        match std::env::var(name) {
+            Ok(key) if !key.is_empty() => Ok(Self::new(key)),
+            _ => Err(format!("{name} must be set")),
+        }
+    }
+
+    fn mac(&self, payload: &str) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts any key length");
+        mac.update(payload.as_bytes());
+        mac
+    }
+
+    /// Keyed hash of `value` as unpadded URL-safe base64. Equal inputs hash
+    /// alike, but without the key the input cannot be recovered or guessed.
+    pub fn digest(&self, value: &str) -> String {
+        URL_SAFE_NO_PAD.encode(self.mac(value).finalize().into_bytes())
+    }
+
+    /// Returns `<payload>.<signature>`. The signature is a [`Signer::digest`],
+    /// which never contains `.`, so the payload may.
+    pub fn sign(&self, payload: &str) -> String {
+        format!("{payload}.{}", self.digest(payload))
+    }
+
+    /// Returns the payload of a token produced by [`Signer::sign`] with the
+    /// same key, comparing signatures in constant time.
+    pub fn verify<'a>(&self, token: &'a str) -> Option<&'a str> {
+        let (payload, signature) = token.rsplit_once('.')?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+        self.mac(payload).verify_slice(&signature).ok()?;
+        Some(payload)
+    }
+}
+
+pub fn sha256(value: &str) -> [u8; 32] {
+    Sha256::digest(value.as_bytes()).into()
+}
+
+/// Compares two digests without stopping at the first difference. Hash
+/// secrets before comparing so their lengths do not leak either.
+pub fn constant_time_eq(a: &[u8; 32], b: &[u8; 32]) -> bool {
+    a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Bytes from the operating system's CSPRNG.
+pub fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0; N];
+    getrandom::getrandom(&mut bytes).expect("operating system RNG unavailable");
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verifies_only_its_own_signatures() {
+        let signer = Signer::new("secret");
+        let token = signer.sign("1700000000.18.abc");
+        assert_eq!(signer.verify(&token), Some("1700000000.18.abc"));
+
+        assert_eq!(Signer::new("other").verify(&token), None);
+        let tampered = token.replacen("18", "1", 1);
+        assert_eq!(signer.verify(&tampered), None);
+        assert_eq!(signer.verify("no-signature"), None);
+        assert_eq!(signer.verify("payload.!!!"), None);
+    }
+}
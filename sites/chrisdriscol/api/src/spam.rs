@@ -0,0 +1,448 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_graphql::async_trait;
+use sha2::{Digest, Sha256};
+
+use crate::signing::Signer;
+use crate::types::ContactMeInput;
+
+/// Scores at or above this are quarantined.
+const DEFAULT_THRESHOLD: u32 = 50;
+/// People need a few seconds to fill in the form; scripts do not.
+const DEFAULT_MIN_SUBMIT: Duration = Duration::from_secs(3);
+/// Form tokens older than this are treated as missing.
+const MAX_FORM_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+/// How long a message body counts as seen for duplicate detection.
+const DUPLICATE_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+const DEFAULT_BLOCKED_KEYWORDS: &[&str] = &[
+    "backlinks",
+    "casino",
+    "crypto investment",
+    "seo services",
+    "viagra",
+];
+
+const HONEYPOT_POINTS: u32 = 100;
+const TOO_FAST_POINTS: u32 = 60;
+const MISSING_FORM_TOKEN_POINTS: u32 = 30;
+const POINTS_PER_LINK: u32 = 10;
+const MAX_LINK_POINTS: u32 = 40;
+const LINK_HEAVY_POINTS: u32 = 30;
+const KEYWORD_POINTS: u32 = 60;
+const BLOCKED_DOMAIN_POINTS: u32 = 100;
+const DUPLICATE_POINTS: u32 = 40;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpamConfig {
+    pub threshold: u32,
+    pub min_submit: Duration,
+    /// Lowercase phrases matched anywhere in the name, subject or body.
+    pub blocked_keywords: Vec<String>,
+    /// Lowercase domains matched against the sender and linked hosts,
+    /// including their subdomains.
+    pub blocked_domains: Vec<String>,
+}
+
+impl Default for SpamConfig {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_THRESHOLD,
+            min_submit: DEFAULT_MIN_SUBMIT,
+            blocked_keywords: DEFAULT_BLOCKED_KEYWORDS.iter().map(|k| k.to_string()).collect(),
+            blocked_domains: Vec::new(),
+        }
+    }
+}
+
+fn list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().to_lowercase())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+impl SpamConfig {
+    /// Reads `CONTACT_SPAM_THRESHOLD`, `CONTACT_SPAM_MIN_SUBMIT_SECONDS`,
+    /// `CONTACT_BLOCKED_KEYWORDS` and `CONTACT_BLOCKED_DOMAINS` (comma
+    /// separated; keywords replace the built-in list).
+    pub fn from_env() -> Result<Self, String> {
+        let defaults = Self::default();
+        let number = |name: &str, default: u64| match std::env::var(name) {
+            Ok(value) => value
+                .parse::<u64>()
+                .map_err(|_| format!("{name} must be a number, got {value:?}")),
+            Err(_) => Ok(default),
+        };
+        Ok(Self {
+            threshold: u32::try_from(number("CONTACT_SPAM_THRESHOLD", defaults.threshold.into())?)
+                .map_err(|_| "CONTACT_SPAM_THRESHOLD is too large".to_string())?,
+            min_submit: Duration::from_secs(number(
+                "CONTACT_SPAM_MIN_SUBMIT_SECONDS",
+                defaults.min_submit.as_secs(),
+            )?),
+            blocked_keywords: std::env::var("CONTACT_BLOCKED_KEYWORDS")
+                .map(|value| list(&value))
+                .unwrap_or(defaults.blocked_keywords),
+            blocked_domains: std::env::var("CONTACT_BLOCKED_DOMAINS")
+                .map(|value| list(&value))
+                .unwrap_or_default(),
+        })
+    }
+}
+
+/// Why a submission scored what it did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpamSignal {
+    Honeypot,
+    TooFast,
+    MissingFormToken,
+    Links,
+    LinkHeavy,
+    BlockedKeyword,
+    BlockedDomain,
+    Duplicate,
+}
+
+impl SpamSignal {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Honeypot => "honeypot",
+            Self::TooFast => "too_fast",
+            Self::MissingFormToken => "missing_form_token",
+            Self::Links => "links",
+            Self::LinkHeavy => "link_heavy",
+            Self::BlockedKeyword => "blocked_keyword",
+            Self::BlockedDomain => "blocked_domain",
+            Self::Duplicate => "duplicate",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SpamVerdict {
+    pub score: u32,
+    pub signals: Vec<SpamSignal>,
+}
+
+impl SpamVerdict {
+    fn add(&mut self, signal: SpamSignal, points: u32) {
+        self.score += points;
+        self.signals.push(signal);
+    }
+}
+
+/// Holds submissions that scored as spam instead of emailing them.
+#[async_trait::async_trait]
+pub trait Quarantine: Send + Sync {
+    async fn quarantine(&self, input: &ContactMeInput, verdict: &SpamVerdict) -> Result<(), String>;
+}
+
+/// Writes quarantined submissions to stderr, which ends up in CloudWatch on
+/// Lambda.
+#[derive(Default)]
+pub struct LogQuarantine;
+
+#[async_trait::async_trait]
+impl Quarantine for LogQuarantine {
+    async fn quarantine(&self, input: &ContactMeInput, verdict: &SpamVerdict) -> Result<(), String> {
+        let signals: Vec<&str> = verdict.signals.iter().map(|signal| signal.as_str()).collect();
+        let entry = serde_json::json!({
+            "event": "contact_quarantined",
+            "score": verdict.score,
+            "signals": signals,
+            "from": input.from,
+            "name": input.name,
+            "subject": input.subject,
+            "body": input.body,
+        });
+        eprintln!("{entry}");
+        Ok(())
+    }
+}
+
+/// Keeps quarantined submissions in memory; used by tests.
+#[derive(Default)]
+pub struct MemoryQuarantine {
+    held: Mutex<Vec<(String, SpamVerdict)>>,
+}
+
+impl MemoryQuarantine {
+    /// Subjects and verdicts of quarantined submissions, oldest first.
+    pub fn held(&self) -> Vec<(String, SpamVerdict)> {
+        self.held.lock().unwrap().clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl Quarantine for MemoryQuarantine {
+    async fn quarantine(&self, input: &ContactMeInput, verdict: &SpamVerdict) -> Result<(), String> {
+        self.held.lock().unwrap().push((input.subject.clone(), verdict.clone()));
+        Ok(())
+    }
+}
+
+/// Hosts linked from `text`, lowercased, for bare `www.` links as well as
+/// `http(s)://` URLs.
+fn linked_hosts(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .filter_map(|word| {
+            let lower = word.to_lowercase();
+            let rest = lower
+                .strip_prefix("https://")
+                .or_else(|| lower.strip_prefix("http://"))
+                .or_else(|| lower.starts_with("www.").then_some(lower.as_str()))?;
+            let host = rest
+                .split(['/', '?', '#', ':'])
+                .next()
+                .unwrap_or_default()
+                .trim_end_matches(|c: char| !c.is_ascii_alphanumeric());
+            (!host.is_empty()).then(|| host.to_string())
+        })
+        .collect()
+}
+
+fn domain_matches(host: &str, blocked: &str) -> bool {
+    host == blocked || host.ends_with(&format!(".{blocked}"))
+}
+
+/// Lowercased body with whitespace collapsed, hashed, so trivially edited
+/// resends still match.
+fn body_fingerprint(body: &str) -> String {
+    let normalized = body.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    format!("{:x}", Sha256::digest(normalized.as_bytes()))
+}
+
+/// Scores `contactMe` submissions. Anything over the threshold is reported
+/// as sent but handed to the quarantine instead of the mailer.
+#[derive(Clone)]
+pub struct SpamFilter {
+    pub config: SpamConfig,
+    pub signer: Signer,
+    pub quarantine: Arc<dyn Quarantine>,
+    /// Body fingerprints by when they were last seen.
+    seen: Arc<Mutex<HashMap<String, Duration>>>,
+}
+
+impl Default for SpamFilter {
+    fn default() -> Self {
+        Self::new(SpamConfig::default(), Signer::random(), Arc::new(LogQuarantine))
+    }
+}
+
+fn unix_now() -> Duration {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default()
+}
+
+impl SpamFilter {
+    pub fn new(config: SpamConfig, signer: Signer, quarantine: Arc<dyn Quarantine>) -> Self {
+        Self {
+            config,
+            signer,
+            quarantine,
+            seen: Arc::default(),
+        }
+    }
+
+    /// Signs form tokens with `CONTACT_FORM_SECRET`.
+    pub fn from_env() -> Result<Self, String> {
+        Ok(Self::new(
+            SpamConfig::from_env()?,
+            Signer::from_env("CONTACT_FORM_SECRET")?,
+            Arc::new(LogQuarantine),
+        ))
+    }
+
+    /// A signed timestamp for `contactFormToken`, sent back with the form.
+    pub fn form_token(&self) -> String {
+        self.form_token_at(unix_now())
+    }
+
+    fn form_token_at(&self, now: Duration) -> String {
+        self.signer.sign(&now.as_millis().to_string())
+    }
+
+    pub fn score(&self, input: &ContactMeInput) -> SpamVerdict {
+        self.score_at(input, unix_now())
+    }
+
+    fn score_at(&self, input: &ContactMeInput, now: Duration) -> SpamVerdict {
+        let mut verdict = SpamVerdict::default();
+
+        if input.website.as_deref().is_some_and(|value| !value.trim().is_empty()) {
+            verdict.add(SpamSignal::Honeypot, HONEYPOT_POINTS);
+        }
+
+        let issued = input
+            .form_token
+            .as_deref()
+            .and_then(|token| self.signer.verify(token))
+            .and_then(|millis| millis.parse::<u64>().ok())
+            .map(Duration::from_millis)
+            .filter(|issued| now.saturating_sub(*issued) <= MAX_FORM_AGE);
+        match issued {
+            Some(issued) if now.saturating_sub(issued) < self.config.min_submit => {
+                verdict.add(SpamSignal::TooFast, TOO_FAST_POINTS)
+            }
+            Some(_) => {}
+            None => verdict.add(SpamSignal::MissingFormToken, MISSING_FORM_TOKEN_POINTS),
+        }
+
+        let hosts = linked_hosts(&input.body);
+        if !hosts.is_empty() {
+            let points = (hosts.len() as u32 * POINTS_PER_LINK).min(MAX_LINK_POINTS);
+            verdict.add(SpamSignal::Links, points);
+            // Mostly links and little prose.
+            let words = input.body.split_whitespace().count();
+            if hosts.len() * 2 >= words {
+                verdict.add(SpamSignal::LinkHeavy, LINK_HEAVY_POINTS);
+            }
+        }
+
+        let text = format!("{}\n{}\n{}", input.name, input.subject, input.body).to_lowercase();
+        if self.config.blocked_keywords.iter().any(|keyword| text.contains(keyword.as_str())) {
+            verdict.add(SpamSignal::BlockedKeyword, KEYWORD_POINTS);
+        }
+
+        let sender = input
+            .from
+            .rsplit_once('@')
+            .map(|(_, domain)| domain.to_lowercase());
+        let blocked_domain = sender.iter().chain(hosts.iter()).any(|host| {
+            self.config
+                .blocked_domains
+                .iter()
+                .any(|blocked| domain_matches(host, blocked))
+        });
+        if blocked_domain {
+            verdict.add(SpamSignal::BlockedDomain, BLOCKED_DOMAIN_POINTS);
+        }
+
+        let fingerprint = body_fingerprint(&input.body);
+        let mut seen = self.seen.lock().unwrap();
+        seen.retain(|_, at| now.saturating_sub(*at) < DUPLICATE_WINDOW);
+        if seen.insert(fingerprint, now).is_some() {
+            verdict.add(SpamSignal::Duplicate, DUPLICATE_POINTS);
+        }
+
+        verdict
+    }
+
+    pub fn is_spam(&self, verdict: &SpamVerdict) -> bool {
+        verdict.score >= self.config.threshold
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: Duration = Duration::from_secs(1_700_000_000);
+
+    fn input(body: &str) -> ContactMeInput {
+        ContactMeInput {
+            from: "visitor@example.com".to_string(),
+            name: "Visitor".to_string(),
+            subject: "Hello".to_string(),
+            body: body.to_string(),
+            verification_token: None,
+            website: None,
+            form_token: None,
+            send_copy: None,
+        }
+    }
+
+    fn filter(config: SpamConfig) -> SpamFilter {
+        SpamFilter::new(config, Signer::new("secret"), Arc::new(MemoryQuarantine::default()))
+    }
+
+    /// Scores `input` as if the form had been open for a minute.
+    fn score(filter: &SpamFilter, input: ContactMeInput, now: Duration) -> SpamVerdict {
+        let input = ContactMeInput {
+            form_token: Some(filter.form_token_at(now - Duration::from_secs(60))),
+            ..input
+        };
+        filter.score_at(&input, now)
+    }
+
+    #[test]
+    fn a_normal_message_scores_zero() {
+        let filter = filter(SpamConfig::default());
+        let verdict = score(&filter, input("Loved the portfolio, want to chat?"), NOW);
+        assert_eq!(verdict, SpamVerdict::default());
+        assert!(!filter.is_spam(&verdict));
+    }
+
+    #[test]
+    fn honeypot_and_fast_submissions_are_spam() {
+        let filter = filter(SpamConfig::default());
+        let bot = ContactMeInput {
+            website: Some("https://spam.example".to_string()),
+            ..input("Hi")
+        };
+        let verdict = score(&filter, bot, NOW);
+        assert_eq!(verdict.signals, vec![SpamSignal::Honeypot]);
+        assert!(filter.is_spam(&verdict));
+
+        let fast = ContactMeInput {
+            form_token: Some(filter.form_token_at(NOW - Duration::from_secs(1))),
+            ..input("Hi again")
+        };
+        let verdict = filter.score_at(&fast, NOW);
+        assert_eq!(verdict.signals, vec![SpamSignal::TooFast]);
+        assert!(filter.is_spam(&verdict));
+    }
+
+    #[test]
+    fn missing_forged_or_stale_form_tokens_count_against_the_message() {
+        let filter = filter(SpamConfig::default());
+        let forged = Signer::new("other").sign(&(NOW - Duration::from_secs(60)).as_millis().to_string());
+        let stale = filter.form_token_at(NOW - MAX_FORM_AGE - Duration::from_secs(1));
+        for (body, form_token) in [("one", None), ("two", Some(forged)), ("three", Some(stale))] {
+            let verdict = filter.score_at(&ContactMeInput { form_token, ..input(body) }, NOW);
+            assert_eq!(verdict.signals, vec![SpamSignal::MissingFormToken], "{body}");
+            assert!(!filter.is_spam(&verdict), "{body}");
+        }
+    }
+
+    #[test]
+    fn scores_links_keywords_and_domains() {
+        let filter = filter(SpamConfig {
+            blocked_domains: vec!["spam.example".to_string()],
+            ..SpamConfig::default()
+        });
+
+        let one_link = score(&filter, input("My work is at https://me.example/portfolio, have a look."), NOW);
+        assert_eq!(one_link.signals, vec![SpamSignal::Links]);
+        assert_eq!(one_link.score, POINTS_PER_LINK);
+
+        let link_dump = score(&filter, input("www.a.example http://b.example/x great https://c.example"), NOW);
+        assert_eq!(link_dump.signals, vec![SpamSignal::Links, SpamSignal::LinkHeavy]);
+        assert!(filter.is_spam(&link_dump));
+
+        let keyword = score(&filter, input("Cheap SEO Services for your site"), NOW);
+        assert_eq!(keyword.signals, vec![SpamSignal::BlockedKeyword]);
+
+        let linked = score(&filter, input("Visit https://shop.spam.example/deal today"), NOW);
+        assert!(linked.signals.contains(&SpamSignal::BlockedDomain), "{linked:?}");
+        let sender = ContactMeInput {
+            from: "bot@Spam.Example".to_string(),
+            ..input("Plain message")
+        };
+        assert_eq!(score(&filter, sender, NOW).signals, vec![SpamSignal::BlockedDomain]);
+        assert!(!domain_matches("notspam.example", "spam.example"));
+    }
+
+    #[test]
+    fn detects_duplicate_bodies_within_a_day() {
+        let filter = filter(SpamConfig::default());
+        assert!(score(&filter, input("Same  message"), NOW).signals.is_empty());
+        let again = score(&filter, input("same message\n"), NOW + Duration::from_secs(60));
+        assert_eq!(again.signals, vec![SpamSignal::Duplicate]);
+
+        let later = NOW + DUPLICATE_WINDOW + Duration::from_secs(120);
+        assert!(score(&filter, input("Same message"), later).signals.is_empty());
+    }
+}
@@ -0,0 +1,71 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rusqlite::Connection;
+
+/// A SQLite file shared by the stores that keep contact submissions. The
+/// file is opened, and `schema` applied, on first use, so building the
+/// schema never touches the disk.
+pub struct Database {
+    path: PathBuf,
+    schema: &'static str,
+    conn: Arc<Mutex<Option<Connection>>>,
+}
+
+/// Opens or creates the database at `path`.
+fn open(path: &Path, schema: &str) -> Result<Connection, String> {
+    let failed = |err: rusqlite::Error| format!("failed to open {}: {err}", path.display());
+    let conn = Connection::open(path).map_err(failed)?;
+    // WAL lets readers carry on while a write is in flight; FULL syncs every
+    // commit so stored rows survive a crash.
+    conn.pragma_update(None, "journal_mode", "WAL").map_err(failed)?;
+    conn.pragma_update(None, "synchronous", "FULL").map_err(failed)?;
+    conn.busy_timeout(Duration::from_secs(5)).map_err(failed)?;
+    conn.execute_batch(schema).map_err(failed)?;
+    Ok(conn)
+}
+
+impl Database {
+    pub fn new(path: impl Into<PathBuf>, schema: &'static str) -> Self {
+        Self {
+            path: path.into(),
+            schema,
+            conn: Arc::default(),
+        }
+    }
+
+    /// Runs `f` on a blocking thread; SQLite calls block on disk.
+    pub async fn with<T, F>(&self, f: F) -> Result<T, String>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> rusqlite::Result<T> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+        let path = self.path.clone();
+        let schema = self.schema;
+        tokio::task::spawn_blocking(move || {
+            let mut conn = conn.lock().unwrap();
+            if conn.is_none() {
+                *conn = Some(open(&path, schema)?);
+            }
+            let conn = conn.as_mut().expect("opened above");
+            f(conn).map_err(|err| format!("query on {} failed: {err}", path.display()))
+        })
+        .await
+        .map_err(|err| format!("database task failed: {err}"))?
+    }
+}
+
+pub fn millis(time: Duration) -> i64 {
+    i64::try_from(time.as_millis()).unwrap_or(i64::MAX)
+}
+
+pub fn from_millis(value: i64) -> Duration {
+    Duration::from_millis(u64::try_from(value).unwrap_or_default())
+}
+
+/// Wraps a conversion failure so it surfaces as a row error.
+pub fn invalid(column: usize, err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(column, rusqlite::types::Type::Text, err.into())
+}
@@ -1,7 +1,26 @@
-use async_graphql::{InputObject, Object, SimpleObject};
+use std::time::SystemTime;
 
-use crate::data;
-use crate::email;
+use async_graphql::connection::{Connection, CursorType, Edge};
+use async_graphql::{ComplexObject, Context, InputObject, Object, SimpleObject, ID};
+use axum::http::header::RETRY_AFTER;
+use serde::{Deserialize, Serialize};
+
+use crate::auth::{Role, RoleGuard};
+use crate::client::{ClientInfo, ClientIp};
+use crate::content::{self, AboutInput, Content, ExperienceInput, SkillsInput, WorkInput};
+use crate::dates::DateRange;
+use crate::email::{ContactMeta, Mailer};
+use crate::filter::{self, ExperienceFilter, PortfolioSort, WorkFilter};
+use crate::inbox::{ContactSubmission, ContactSubmissionFilter, Inbox, InboxCursor, SubmissionCount};
+use crate::limits;
+use crate::node::{self, Node};
+use crate::outbox::{self, Outbox};
+use crate::pagination::{self, ListConnection};
+use crate::ratelimit::{RateLimitError, RateLimiter};
+use crate::search::SearchHit;
+use crate::spam::SpamFilter;
+use crate::validation;
+use crate::verification::{ContactChallenge, ContactVerifier};
 
 pub struct QueryRoot;
 pub struct MutationRoot;
@@ -12,23 +31,55 @@ pub struct ContactMeInput {
     pub name: String,
     pub subject: String,
     pub body: String,
+    /// Bot-check token: a CAPTCHA response or a solved `contactChallenge`,
+    /// depending on how the API is configured.
+    pub verification_token: Option<String>,
+    /// Honeypot: hidden from people and must be left empty.
+    pub website: Option<String>,
+    /// The `contactFormToken` fetched when the form was shown.
+    pub form_token: Option<String>,
+    /// Include a copy of the message in the confirmation email, when the
+    /// site sends one.
+    pub send_copy: Option<bool>,
 }
 
 #[derive(SimpleObject)]
 pub struct ContactMePayload {
     pub success: bool,
+    /// Reference for the stored message, which is delivered from the outbox.
+    pub submission_id: ID,
 }
 
-#[derive(SimpleObject, Clone)]
+#[derive(SimpleObject)]
+pub struct DeleteSubmissionPayload {
+    pub id: ID,
+    /// False when there was no such submission.
+    pub deleted: bool,
+}
+
+#[derive(SimpleObject, Clone, Deserialize, Serialize)]
+#[graphql(complex, cache_control(max_age = 300))]
+#[serde(rename_all = "camelCase")]
 pub struct Skills {
+    /// Slug of the portfolio these skills belong to, filled in on load.
+    #[graphql(skip)]
+    #[serde(skip)]
+    pub slug: String,
+    #[serde(default)]
     pub languages: Vec<String>,
+    #[serde(default)]
     pub technologies: Vec<String>,
+    #[serde(default)]
     pub tools: Vec<String>,
+    #[serde(default)]
     pub loves: Vec<String>,
 }
 
-#[derive(SimpleObject, Clone)]
+#[derive(SimpleObject, Clone, Deserialize, Serialize)]
+#[graphql(cache_control(max_age = 300))]
+#[serde(rename_all = "camelCase")]
 pub struct About {
+    #[serde(default)]
     pub description: Vec<String>,
     #[graphql(name = "imageUrl")]
     pub image_url: Option<String>,
@@ -40,9 +91,13 @@ pub struct About {
     pub tag_line: Option<String>,
 }
 
-#[derive(SimpleObject, Clone)]
+#[derive(SimpleObject, Clone, Deserialize, Serialize)]
+#[graphql(complex, cache_control(max_age = 300))]
+#[serde(rename_all = "camelCase")]
 pub struct Experience {
-    pub duration: Option<String>,
+    #[graphql(skip)]
+    pub slug: String,
+    pub dates: Option<DateRange>,
     pub title: Option<String>,
     pub location: Option<String>,
     pub description: Option<String>,
@@ -50,22 +105,60 @@ pub struct Experience {
     pub image_url: Option<String>,
 }
 
-#[derive(SimpleObject, Clone)]
+#[derive(SimpleObject, Clone, Deserialize, Serialize)]
+#[graphql(complex, cache_control(max_age = 300))]
+#[serde(rename_all = "camelCase")]
 pub struct Work {
+    #[graphql(skip)]
+    pub slug: String,
     pub title: Option<String>,
     #[graphql(name = "subTitle")]
     pub sub_title: Option<String>,
+    #[serde(default)]
     pub description: Vec<String>,
     pub location: Option<String>,
     pub link: Option<String>,
     pub video: Option<String>,
-    pub date: Option<String>,
+    pub dates: Option<DateRange>,
     #[graphql(name = "imageUrl")]
     pub image_url: Option<String>,
+    #[serde(default)]
     pub technologies: Vec<String>,
 }
 
-#[derive(SimpleObject, Clone)]
+#[ComplexObject]
+impl Skills {
+    pub async fn id(&self) -> ID {
+        node::global_id("Skills", &self.slug)
+    }
+}
+
+#[ComplexObject]
+impl Experience {
+    pub async fn id(&self) -> ID {
+        node::global_id("Experience", &self.slug)
+    }
+
+    /// Display form of `dates`, kept for clients that predate `DateRange`.
+    async fn duration(&self) -> Option<String> {
+        self.dates.as_ref().map(DateRange::legacy_label)
+    }
+}
+
+#[ComplexObject]
+impl Work {
+    pub async fn id(&self) -> ID {
+        node::global_id("Work", &self.slug)
+    }
+
+    /// Display form of `dates`, kept for clients that predate `DateRange`.
+    async fn date(&self) -> Option<String> {
+        self.dates.as_ref().map(DateRange::legacy_label)
+    }
+}
+
+#[derive(SimpleObject, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Social {
     #[graphql(name = "linkedIn")]
     pub linked_in: String,
@@ -73,26 +166,158 @@ pub struct Social {
     pub email: String,
 }
 
-#[derive(SimpleObject, Clone)]
+// Portfolio content changes rarely, on deploy or through the content
+// mutations. It and the types reachable from it are cacheable for five
+// minutes, so edits reach readers through CloudFront soon after.
+#[derive(SimpleObject, Clone, Deserialize, Serialize)]
+#[graphql(complex, cache_control(max_age = 300))]
+#[serde(rename_all = "camelCase")]
 pub struct Chris {
-    pub id: String,
+    #[graphql(skip)]
+    pub slug: String,
+    /// Content revision, bumped by every edit. Content mutations take the
+    /// version they were based on and fail with `VERSION_CONFLICT` when it
+    /// is out of date.
+    pub version: i32,
     pub title: String,
     pub description: String,
     pub skills: Skills,
+    #[graphql(skip)]
     pub experience: Vec<Experience>,
     pub about: About,
+    #[graphql(skip)]
     pub work: Vec<Work>,
     pub social: Social,
 }
 
+#[ComplexObject]
+impl Chris {
+    pub async fn id(&self) -> ID {
+        node::global_id("Chris", &self.slug)
+    }
+
+    #[graphql(complexity = "limits::UNBOUNDED_LIST_SIZE * child_complexity")]
+    async fn experience(
+        &self,
+        filter: Option<ExperienceFilter>,
+        sort: Option<PortfolioSort>,
+    ) -> Vec<Experience> {
+        filter::experience(&self.experience, &filter.unwrap_or_default(), sort)
+    }
+
+    #[graphql(complexity = "limits::UNBOUNDED_LIST_SIZE * child_complexity")]
+    async fn work(&self, filter: Option<WorkFilter>, sort: Option<PortfolioSort>) -> Vec<Work> {
+        filter::work(&self.work, &filter.unwrap_or_default(), sort)
+    }
+
+    /// Cursor-paginated alternative to `work`.
+    #[graphql(
+        name = "workConnection",
+        complexity = "limits::page_complexity(first, last, child_complexity)"
+    )]
+    async fn work_connection(
+        &self,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> async_graphql::Result<ListConnection<Work>> {
+        pagination::paginate(self.work.clone(), after, before, first, last).await
+    }
+
+    /// Cursor-paginated alternative to `experience`.
+    #[graphql(
+        name = "experienceConnection",
+        complexity = "limits::page_complexity(first, last, child_complexity)"
+    )]
+    async fn experience_connection(
+        &self,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> async_graphql::Result<ListConnection<Experience>> {
+        pagination::paginate(self.experience.clone(), after, before, first, last).await
+    }
+}
+
 #[Object]
 impl QueryRoot {
+    #[graphql(cache_control(no_cache))]
     async fn ping(&self) -> &str {
         "pong"
     }
 
-    async fn chris(&self) -> Chris {
-        data::chris()
+    async fn chris(&self, ctx: &Context<'_>) -> async_graphql::Result<Chris> {
+        Ok(ctx.data::<Content>()?.snapshot().await?.chris.clone())
+    }
+
+    /// A proof-of-work puzzle to solve before `contactMe`, or null when the
+    /// API does not use proof of work.
+    #[graphql(cache_control(no_cache))]
+    async fn contact_challenge(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<ContactChallenge>> {
+        Ok(ctx.data::<ContactVerifier>()?.challenge())
+    }
+
+    /// Signed timestamp to send back as `formToken`; fetch it when the
+    /// contact form is shown.
+    #[graphql(cache_control(no_cache))]
+    async fn contact_form_token(&self, ctx: &Context<'_>) -> async_graphql::Result<String> {
+        Ok(ctx.data::<SpamFilter>()?.form_token())
+    }
+
+    /// Messages sent through the contact form, newest first, including
+    /// quarantined spam. Admin only.
+    #[graphql(
+        guard = "RoleGuard::new(Role::Admin)",
+        cache_control(no_cache),
+        complexity = "limits::page_complexity(first, None, child_complexity)"
+    )]
+    async fn contact_submissions(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<ContactSubmissionFilter>,
+        after: Option<String>,
+        first: Option<i32>,
+    ) -> async_graphql::Result<Connection<InboxCursor, ContactSubmission, SubmissionCount>> {
+        let limit = pagination::page_size(first)?;
+        let after = after
+            .map(|after| InboxCursor::decode_cursor(&after))
+            .transpose()
+            .map_err(|_| async_graphql::Error::new("after is not a valid cursor"))?;
+        let filter = filter.unwrap_or_default();
+        let page = ctx.data::<Inbox>()?.store.list(&filter, after.as_ref(), limit).await?;
+        let count = SubmissionCount { filter };
+        let mut connection = Connection::with_additional_fields(after.is_some(), page.has_more, count);
+        let edges = page.submissions.into_iter();
+        connection.edges.extend(edges.map(|submission| Edge::new(InboxCursor::of(&submission), submission)));
+        Ok(connection)
+    }
+
+    /// Full-text search over work, experience and about content.
+    #[graphql(complexity = "limits::page_complexity(first, None, child_complexity)")]
+    async fn search(
+        &self,
+        ctx: &Context<'_>,
+        query: String,
+        first: Option<i32>,
+    ) -> async_graphql::Result<Vec<SearchHit>> {
+        let first = first
+            .map(usize::try_from)
+            .transpose()
+            .map_err(|_| async_graphql::Error::new("first must not be negative"))?;
+        Ok(ctx.data::<Content>()?.snapshot().await?.index.search(&query, first))
+    }
+
+    /// Refetches any object implementing `Node` by its global ID.
+    async fn node(&self, ctx: &Context<'_>, id: ID) -> async_graphql::Result<Option<Node>> {
+        Ok(node::fetch(&ctx.data::<Content>()?.snapshot().await?.chris, &id))
+    }
+
+    #[graphql(complexity = "ids.len().max(1) * child_complexity")]
+    async fn nodes(&self, ctx: &Context<'_>, ids: Vec<ID>) -> async_graphql::Result<Vec<Option<Node>>> {
+        let snapshot = ctx.data::<Content>()?.snapshot().await?;
+        Ok(ids.iter().map(|id| node::fetch(&snapshot.chris, id)).collect())
     }
 }
 
@@ -100,11 +325,215 @@ impl QueryRoot {
 impl MutationRoot {
     async fn contact_me(
         &self,
+        ctx: &Context<'_>,
         input: ContactMeInput,
     ) -> Result<ContactMePayload, async_graphql::Error> {
-        email::send_contact_email(&input)
+        validation::check_contact(ctx, &input)?;
+        let client_ip = ctx.data_opt::<ClientIp>().map(|ip| ip.0);
+        // Only verified submissions count towards the rate limits, so bots
+        // cannot use up the global cap without passing the check.
+        ctx.data::<ContactVerifier>()?
+            .check(input.verification_token.as_deref(), client_ip)
             .await
-            .map_err(|err| async_graphql::Error::new(err))?;
-        Ok(ContactMePayload { success: true })
+            .map_err(|err| err.to_error())?;
+        if let Err(err) = ctx.data::<RateLimiter>()?.check_contact(client_ip, &input.from).await {
+            if let Some(retry_after) = err.retry_after_secs() {
+                ctx.insert_http_header(RETRY_AFTER, retry_after.to_string());
+            }
+            return Err(err.to_error());
+        }
+        let received_at = SystemTime::now();
+        let client = ctx.data_opt::<ClientInfo>().cloned().unwrap_or_default();
+        let meta = ContactMeta::new(received_at, &client);
+        let inbox = ctx.data::<Inbox>()?;
+        // Spam gets the same answer as real mail so bots learn nothing.
+        let spam = ctx.data::<SpamFilter>()?;
+        let verdict = spam.score(&input);
+        if spam.is_spam(&verdict) {
+            if let Err(err) = spam.quarantine.quarantine(&input, &verdict).await {
+                eprintln!("contactMe: failed to quarantine spam: {err}");
+            }
+            let submission_id = outbox::new_id();
+            let submission = ContactSubmission::new(submission_id.clone(), &input, &meta, &verdict, true);
+            if let Err(err) = inbox.record(submission, client_ip).await {
+                eprintln!("contactMe: failed to record submission: {err}");
+            }
+            return Ok(ContactMePayload {
+                success: true,
+                submission_id: ID(submission_id),
+            });
+        }
+        let mailer = ctx.data::<Mailer>()?;
+        let outbox = ctx.data::<Outbox>()?;
+        let email = mailer.contact_email(&input, &meta).map_err(async_graphql::Error::new)?;
+        let submission_id = outbox.enqueue(email).await.map_err(async_graphql::Error::new)?;
+        // The inbox shares the outbox ID so it can report delivery status.
+        let submission = ContactSubmission::new(submission_id.clone(), &input, &meta, &verdict, false);
+        if let Err(err) = inbox.record(submission, client_ip).await {
+            eprintln!("contactMe: failed to record submission: {err}");
+        }
+        let mut queued = vec![submission_id.clone()];
+        // The message is stored by now; a missing confirmation only gets
+        // logged.
+        if mailer.auto_reply.enabled {
+            match ctx.data::<RateLimiter>()?.check_auto_reply(&input.from).await {
+                Ok(()) => {
+                    let reply = match mailer.auto_reply_email(&input, received_at) {
+                        Ok(email) => outbox.enqueue(email).await,
+                        Err(err) => Err(err),
+                    };
+                    match reply {
+                        Ok(id) => queued.push(id),
+                        Err(err) => eprintln!("contactMe: failed to queue auto-reply: {err}"),
+                    }
+                }
+                Err(RateLimitError::Limited { .. }) => {}
+                Err(RateLimitError::Store(err)) => {
+                    eprintln!("contactMe: skipping auto-reply, rate limiter unavailable: {err}");
+                }
+            }
+        }
+        // Deliver what this request queued rather than waiting for the
+        // worker; failures stay queued for a retry.
+        for id in &queued {
+            if let Err(err) = outbox.dispatch(id, mailer.transport.as_ref()).await {
+                eprintln!("contactMe: outbox dispatch failed: {err}");
+            }
+        }
+        Ok(ContactMePayload {
+            success: true,
+            submission_id: ID(submission_id),
+        })
+    }
+
+    /// Marks an inbox message as read, or unread with `read: false`.
+    /// Returns null when there is no such submission. Admin only.
+    #[graphql(guard = "RoleGuard::new(Role::Admin)")]
+    async fn mark_submission_read(
+        &self,
+        ctx: &Context<'_>,
+        id: ID,
+        #[graphql(default = true)] read: bool,
+    ) -> async_graphql::Result<Option<ContactSubmission>> {
+        Ok(ctx.data::<Inbox>()?.store.set_read(&id, read).await?)
+    }
+
+    /// Removes a message from the inbox. Its email, if any, is unaffected.
+    /// Admin only.
+    #[graphql(guard = "RoleGuard::new(Role::Admin)")]
+    async fn delete_submission(&self, ctx: &Context<'_>, id: ID) -> async_graphql::Result<DeleteSubmissionPayload> {
+        let deleted = ctx.data::<Inbox>()?.store.delete(&id).await?;
+        Ok(DeleteSubmissionPayload { id, deleted })
+    }
+
+    /// Adds a work item with the given slug, at `position` or at the end.
+    /// Every content mutation takes the `Chris.version` it was based on and
+    /// returns the updated content. Editor or admin only.
+    #[graphql(guard = "RoleGuard::new(Role::Editor)")]
+    async fn add_work(
+        &self,
+        ctx: &Context<'_>,
+        version: i32,
+        slug: String,
+        input: WorkInput,
+        position: Option<i32>,
+    ) -> async_graphql::Result<Chris> {
+        edit(ctx, version, |chris| content::add(&mut chris.work, input.into_work(slug), position)).await
+    }
+
+    /// Replaces every field of a work item except its slug. Editor or admin
+    /// only.
+    #[graphql(guard = "RoleGuard::new(Role::Editor)")]
+    async fn update_work(&self, ctx: &Context<'_>, version: i32, id: ID, input: WorkInput) -> async_graphql::Result<Chris> {
+        edit(ctx, version, |chris| content::update(&mut chris.work, &id, |slug| input.into_work(slug))).await
+    }
+
+    /// Editor or admin only.
+    #[graphql(guard = "RoleGuard::new(Role::Editor)")]
+    async fn remove_work(&self, ctx: &Context<'_>, version: i32, id: ID) -> async_graphql::Result<Chris> {
+        edit(ctx, version, |chris| content::remove(&mut chris.work, &id)).await
     }
+
+    /// Puts work items in the order of `ids`, which must list each of them
+    /// once. Editor or admin only.
+    #[graphql(guard = "RoleGuard::new(Role::Editor)")]
+    async fn reorder_work(&self, ctx: &Context<'_>, version: i32, ids: Vec<ID>) -> async_graphql::Result<Chris> {
+        edit(ctx, version, |chris| content::reorder(&mut chris.work, &ids)).await
+    }
+
+    /// Adds an experience item with the given slug, at `position` or at the
+    /// end. Editor or admin only.
+    #[graphql(guard = "RoleGuard::new(Role::Editor)")]
+    async fn add_experience(
+        &self,
+        ctx: &Context<'_>,
+        version: i32,
+        slug: String,
+        input: ExperienceInput,
+        position: Option<i32>,
+    ) -> async_graphql::Result<Chris> {
+        edit(ctx, version, |chris| {
+            content::add(&mut chris.experience, input.into_experience(slug), position)
+        })
+        .await
+    }
+
+    /// Replaces every field of an experience item except its slug. Editor
+    /// or admin only.
+    #[graphql(guard = "RoleGuard::new(Role::Editor)")]
+    async fn update_experience(
+        &self,
+        ctx: &Context<'_>,
+        version: i32,
+        id: ID,
+        input: ExperienceInput,
+    ) -> async_graphql::Result<Chris> {
+        edit(ctx, version, |chris| {
+            content::update(&mut chris.experience, &id, |slug| input.into_experience(slug))
+        })
+        .await
+    }
+
+    /// Editor or admin only.
+    #[graphql(guard = "RoleGuard::new(Role::Editor)")]
+    async fn remove_experience(&self, ctx: &Context<'_>, version: i32, id: ID) -> async_graphql::Result<Chris> {
+        edit(ctx, version, |chris| content::remove(&mut chris.experience, &id)).await
+    }
+
+    /// Puts experience items in the order of `ids`, which must list each of
+    /// them once. Editor or admin only.
+    #[graphql(guard = "RoleGuard::new(Role::Editor)")]
+    async fn reorder_experience(&self, ctx: &Context<'_>, version: i32, ids: Vec<ID>) -> async_graphql::Result<Chris> {
+        edit(ctx, version, |chris| content::reorder(&mut chris.experience, &ids)).await
+    }
+
+    /// Editor or admin only.
+    #[graphql(guard = "RoleGuard::new(Role::Editor)")]
+    async fn update_about(&self, ctx: &Context<'_>, version: i32, input: AboutInput) -> async_graphql::Result<Chris> {
+        edit(ctx, version, |chris| {
+            chris.about = input.into();
+            Ok(())
+        })
+        .await
+    }
+
+    /// Editor or admin only.
+    #[graphql(guard = "RoleGuard::new(Role::Editor)")]
+    async fn update_skills(&self, ctx: &Context<'_>, version: i32, input: SkillsInput) -> async_graphql::Result<Chris> {
+        edit(ctx, version, |chris| {
+            chris.skills = input.into_skills(chris.slug.clone());
+            Ok(())
+        })
+        .await
+    }
+}
+
+async fn edit<F>(ctx: &Context<'_>, version: i32, f: F) -> async_graphql::Result<Chris>
+where
+    F: FnOnce(&mut Chris) -> Result<(), content::ContentError>,
+{
+    ctx.data::<Content>()?
+        .edit(version, f)
+        .await
+        .map_err(|err| err.to_error())
 }
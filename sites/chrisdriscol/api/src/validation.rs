@@ -0,0 +1,227 @@
+use async_graphql::{Context, ErrorExtensions};
+use lettre::Address;
+
+use crate::types::ContactMeInput;
+
+/// RFC 5321 caps a forward path at 254 octets.
+pub const MAX_EMAIL_LENGTH: usize = 254;
+pub const MAX_NAME_LENGTH: usize = 100;
+pub const MAX_SUBJECT_LENGTH: usize = 200;
+pub const MAX_BODY_LENGTH: usize = 5000;
+/// Turnstile and hCaptcha tokens stay under 2048 characters.
+pub const MAX_TOKEN_LENGTH: usize = 2048;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldErrorCode {
+    Required,
+    TooLong,
+    InvalidEmail,
+    InvalidCharacters,
+}
+
+impl FieldErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Required => "REQUIRED",
+            Self::TooLong => "TOO_LONG",
+            Self::InvalidEmail => "INVALID_EMAIL",
+            Self::InvalidCharacters => "INVALID_CHARACTERS",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldError {
+    /// Input field name as spelled in the schema.
+    pub field: &'static str,
+    pub code: FieldErrorCode,
+    /// Length limit, set for `TOO_LONG`.
+    pub max: Option<usize>,
+}
+
+impl FieldError {
+    fn new(field: &'static str, code: FieldErrorCode) -> Self {
+        Self { field, code, max: None }
+    }
+
+    pub fn message(&self) -> String {
+        match self.code {
+            FieldErrorCode::Required => format!("{} is required", self.field),
+            FieldErrorCode::TooLong => format!(
+                "{} must be at most {} characters",
+                self.field,
+                self.max.unwrap_or_default()
+            ),
+            FieldErrorCode::InvalidEmail => format!("{} is not a valid email address", self.field),
+            FieldErrorCode::InvalidCharacters => {
+                format!("{} must not contain line breaks or control characters", self.field)
+            }
+        }
+    }
+
+    pub fn to_error(&self) -> async_graphql::Error {
+        async_graphql::Error::new(self.message()).extend_with(|_, extensions| {
+            extensions.set("code", self.code.as_str());
+            extensions.set("field", self.field);
+            if let Some(max) = self.max {
+                extensions.set("max", max);
+            }
+        })
+    }
+}
+
+/// Checks a single text field: present, within `max` characters and, for
+/// single-line fields, free of CR/LF and other control characters.
+fn check_text(field: &'static str, value: &str, max: usize, single_line: bool) -> Option<FieldError> {
+    if value.trim().is_empty() {
+        return Some(FieldError::new(field, FieldErrorCode::Required));
+    }
+    if value.chars().count() > max {
+        return Some(FieldError {
+            max: Some(max),
+            ..FieldError::new(field, FieldErrorCode::TooLong)
+        });
+    }
+    if single_line && value.chars().any(char::is_control) {
+        return Some(FieldError::new(field, FieldErrorCode::InvalidCharacters));
+    }
+    None
+}
+
+/// Accepts a bare RFC 5322 addr-spec (`local@domain`), not a display name.
+fn check_email(field: &'static str, value: &str) -> Option<FieldError> {
+    if let Some(error) = check_text(field, value, MAX_EMAIL_LENGTH, true) {
+        return Some(error);
+    }
+    match value.parse::<Address>() {
+        Ok(address) if address.domain().contains('.') => None,
+        _ => Some(FieldError::new(field, FieldErrorCode::InvalidEmail)),
+    }
+}
+
+/// Returns every failing field, in schema order.
+pub fn validate_contact(input: &ContactMeInput) -> Vec<FieldError> {
+    [
+        check_email("from", &input.from),
+        check_text("name", &input.name, MAX_NAME_LENGTH, true),
+        check_text("subject", &input.subject, MAX_SUBJECT_LENGTH, true),
+        check_text("body", &input.body, MAX_BODY_LENGTH, false),
+        input
+            .verification_token
+            .as_deref()
+            .filter(|token| token.len() > MAX_TOKEN_LENGTH)
+            .map(|_| FieldError {
+                max: Some(MAX_TOKEN_LENGTH),
+                ..FieldError::new("verificationToken", FieldErrorCode::TooLong)
+            }),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Fails `contactMe` with one GraphQL error per invalid field. Each error's
+/// extensions carry the input `field` and a machine-readable `code` so the
+/// contact form can show it next to the matching input.
+pub fn check_contact(ctx: &Context<'_>, input: &ContactMeInput) -> Result<(), async_graphql::Error> {
+    let errors = validate_contact(input);
+    let Some((first, rest)) = errors.split_first() else {
+        return Ok(());
+    };
+    // The resolver's own error is listed before ones added to the context.
+    for error in rest {
+        ctx.add_error(ctx.set_error_path(error.to_error().into_server_error(ctx.item.pos)));
+    }
+    Err(first.to_error())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input() -> ContactMeInput {
+        ContactMeInput {
+            from: "visitor@example.com".to_string(),
+            name: "Visitor".to_string(),
+            subject: "Hello".to_string(),
+            body: "Nice site.\r\nThanks!".to_string(),
+            verification_token: None,
+            website: None,
+            form_token: None,
+            send_copy: None,
+        }
+    }
+
+    fn codes(input: &ContactMeInput) -> Vec<(&'static str, &'static str)> {
+        validate_contact(input)
+            .into_iter()
+            .map(|error| (error.field, error.code.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn accepts_a_well_formed_message() {
+        assert!(validate_contact(&input()).is_empty());
+        let quoted = ContactMeInput {
+            from: "first.o'last+tag@mail.example.co.uk".to_string(),
+            ..input()
+        };
+        assert!(validate_contact(&quoted).is_empty());
+    }
+
+    #[test]
+    fn rejects_malformed_email_addresses() {
+        for from in ["not-an-email", "a@b", "two@@example.com", "Name <a@example.com>", "a@example.com\r\nBcc: x@example.com"] {
+            let input = ContactMeInput { from: from.to_string(), ..input() };
+            let errors = validate_contact(&input);
+            assert_eq!(errors.len(), 1, "{from}");
+            assert_eq!(errors[0].field, "from");
+            assert_ne!(errors[0].code, FieldErrorCode::Required, "{from}");
+        }
+    }
+
+    #[test]
+    fn reports_every_failing_field() {
+        let input = ContactMeInput {
+            from: " ".to_string(),
+            name: "x".repeat(MAX_NAME_LENGTH + 1),
+            subject: "Hello\r\nBcc: victim@example.com".to_string(),
+            body: "x".repeat(MAX_BODY_LENGTH + 1),
+            verification_token: Some("x".repeat(MAX_TOKEN_LENGTH + 1)),
+            website: None,
+            form_token: None,
+            send_copy: None,
+        };
+        assert_eq!(
+            codes(&input),
+            vec![
+                ("from", "REQUIRED"),
+                ("name", "TOO_LONG"),
+                ("subject", "INVALID_CHARACTERS"),
+                ("body", "TOO_LONG"),
+                ("verificationToken", "TOO_LONG"),
+            ]
+        );
+    }
+
+    #[test]
+    fn counts_characters_not_bytes() {
+        let input = ContactMeInput {
+            name: "é".repeat(MAX_NAME_LENGTH),
+            ..input()
+        };
+        assert!(validate_contact(&input).is_empty());
+    }
+
+    #[test]
+    fn errors_carry_code_field_and_max() {
+        let error = FieldError {
+            max: Some(MAX_SUBJECT_LENGTH),
+            ..FieldError::new("subject", FieldErrorCode::TooLong)
+        };
+        let value = serde_json::to_value(error.to_error().into_server_error(Default::default())).unwrap();
+        assert_eq!(value["extensions"]["code"], "TOO_LONG");
+        assert_eq!(value["extensions"]["field"], "subject");
+        assert_eq!(value["extensions"]["max"], MAX_SUBJECT_LENGTH);
+    }
+}
@@ -0,0 +1,145 @@
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use async_graphql::{async_trait, ErrorExtensions, SimpleObject};
+
+mod pow;
+mod siteverify;
+
+pub use pow::ProofOfWork;
+pub use siteverify::SiteVerifier;
+
+const TURNSTILE_URL: &str = "https://challenges.cloudflare.com/turnstile/v0/siteverify";
+const HCAPTCHA_URL: &str = "https://api.hcaptcha.com/siteverify";
+
+/// A proof-of-work puzzle for the contact form. Clients find a decimal
+/// `counter` such that SHA-256 of `<challenge>:<counter>` starts with
+/// `difficulty` zero bits, then send `<challenge>:<counter>` as the
+/// `verificationToken`.
+#[derive(SimpleObject, Debug, Clone, PartialEq)]
+pub struct ContactChallenge {
+    pub challenge: String,
+    pub difficulty: i32,
+    pub expires_in_seconds: i32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerificationError {
+    /// No `verificationToken` was sent.
+    Missing,
+    /// The token was checked and rejected.
+    Failed(String),
+    /// The token could not be checked, e.g. the siteverify API is down.
+    Unavailable(String),
+}
+
+impl VerificationError {
+    pub fn to_error(&self) -> async_graphql::Error {
+        let (code, message) = match self {
+            Self::Missing => ("VERIFICATION_REQUIRED", "verificationToken is required".to_string()),
+            Self::Failed(reason) => ("VERIFICATION_FAILED", format!("verification failed: {reason}")),
+            Self::Unavailable(reason) => {
+                ("VERIFICATION_UNAVAILABLE", format!("verification unavailable: {reason}"))
+            }
+        };
+        async_graphql::Error::new(message).extend_with(|_, extensions| {
+            extensions.set("code", code);
+        })
+    }
+}
+
+/// Checks the bot-verification token sent with `contactMe`.
+#[async_trait::async_trait]
+pub trait Verifier: Send + Sync {
+    async fn verify(&self, token: &str, client_ip: Option<IpAddr>) -> Result<(), VerificationError>;
+
+    /// A puzzle to hand out through `contactChallenge`, for verifiers that
+    /// issue their own.
+    fn challenge(&self) -> Option<ContactChallenge> {
+        None
+    }
+}
+
+/// The verifier configured for `contactMe`, if any. Without one every
+/// submission passes.
+#[derive(Clone, Default)]
+pub struct ContactVerifier {
+    pub verifier: Option<Arc<dyn Verifier>>,
+}
+
+impl ContactVerifier {
+    pub fn new(verifier: impl Verifier + 'static) -> Self {
+        Self {
+            verifier: Some(Arc::new(verifier)),
+        }
+    }
+
+    /// Picks the verifier named by `CONTACT_VERIFICATION`: `off` (default),
+    /// `turnstile`, `hcaptcha` or `pow`.
+    pub fn from_env() -> Result<Self, String> {
+        match std::env::var("CONTACT_VERIFICATION").as_deref() {
+            Err(_) | Ok("off") => Ok(Self::default()),
+            Ok("turnstile") => Ok(Self::new(SiteVerifier::from_env(TURNSTILE_URL)?)),
+            Ok("hcaptcha") => Ok(Self::new(SiteVerifier::from_env(HCAPTCHA_URL)?)),
+            Ok("pow") => Ok(Self::new(ProofOfWork::from_env()?)),
+            Ok(other) => Err(format!("unknown CONTACT_VERIFICATION {other:?}")),
+        }
+    }
+
+    pub async fn check(&self, token: Option<&str>, client_ip: Option<IpAddr>) -> Result<(), VerificationError> {
+        let Some(verifier) = &self.verifier else {
+            return Ok(());
+        };
+        match token.map(str::trim) {
+            Some(token) if !token.is_empty() => verifier.verify(token, client_ip).await,
+            _ => Err(VerificationError::Missing),
+        }
+    }
+
+    pub fn challenge(&self) -> Option<ContactChallenge> {
+        self.verifier.as_ref().and_then(|verifier| verifier.challenge())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct AcceptGood;
+
+    #[async_trait::async_trait]
+    impl Verifier for AcceptGood {
+        async fn verify(&self, token: &str, _: Option<IpAddr>) -> Result<(), VerificationError> {
+            if token == "good" {
+                Ok(())
+            } else {
+                Err(VerificationError::Failed("bad token".to_string()))
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn requires_a_token_only_when_configured() {
+        assert_eq!(ContactVerifier::default().check(None, None).await, Ok(()));
+        assert_eq!(ContactVerifier::default().challenge(), None);
+
+        let verifier = ContactVerifier::new(AcceptGood);
+        assert_eq!(verifier.check(None, None).await, Err(VerificationError::Missing));
+        assert_eq!(verifier.check(Some("  "), None).await, Err(VerificationError::Missing));
+        assert!(verifier.check(Some("bad"), None).await.is_err());
+        assert_eq!(verifier.check(Some("good"), None).await, Ok(()));
+        assert_eq!(verifier.challenge(), None);
+    }
+
+    #[test]
+    fn errors_carry_codes() {
+        let code = |error: VerificationError| {
+            serde_json::to_value(error.to_error().into_server_error(Default::default())).unwrap()
+                ["extensions"]["code"]
+                .clone()
+        };
+        assert_eq!(code(VerificationError::Missing), "VERIFICATION_REQUIRED");
+        assert_eq!(code(VerificationError::Failed(String::new())), "VERIFICATION_FAILED");
+        assert_eq!(code(VerificationError::Unavailable(String::new())), "VERIFICATION_UNAVAILABLE");
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_graphql::async_trait;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use sha2::{Digest, Sha256};
+
+use super::{ContactChallenge, VerificationError, Verifier};
+use crate::signing::{self, Signer};
+
+/// Roughly 65k hashes on average, a second or two in a browser.
+const DEFAULT_DIFFICULTY: u32 = 16;
+const DEFAULT_TTL: Duration = Duration::from_secs(10 * 60);
+/// Each extra bit doubles the work; past this a browser would take hours.
+const MAX_DIFFICULTY: u32 = 32;
+
+/// Offline hashcash-style verifier. Challenges are signed rather than stored,
+/// and each one is accepted once per process until it expires: the spent list
+/// is not shared, so with several instances a solved challenge can be
+/// replayed once on each of them within its TTL.
+pub struct ProofOfWork {
+    signer: Signer,
+    difficulty: u32,
+    ttl: Duration,
+    /// Solved challenges by expiry, to reject replays.
+    spent: Mutex<HashMap<String, u64>>,
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn leading_zero_bits(hash: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in hash {
+        bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+impl ProofOfWork {
+    pub fn new(signer: Signer, difficulty: u32, ttl: Duration) -> Result<Self, String> {
+        if difficulty > MAX_DIFFICULTY {
+            return Err(format!("proof-of-work difficulty must be at most {MAX_DIFFICULTY}"));
+        }
+        Ok(Self {
+            signer,
+            difficulty,
+            ttl,
+            spent: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Reads `CONTACT_POW_SECRET` (required, so challenges verify on every
+    /// instance), `CONTACT_POW_DIFFICULTY` (leading zero bits, default 16)
+    /// and `CONTACT_POW_TTL_SECONDS` (default 600).
+    pub fn from_env() -> Result<Self, String> {
+        let number = |name: &str, default: u64| match std::env::var(name) {
+            Ok(value) => value
+                .parse::<u64>()
+                .map_err(|_| format!("{name} must be a number, got {value:?}")),
+            Err(_) => Ok(default),
+        };
+        let difficulty = number("CONTACT_POW_DIFFICULTY", DEFAULT_DIFFICULTY.into())?;
+        let ttl = number("CONTACT_POW_TTL_SECONDS", DEFAULT_TTL.as_secs())?;
+        Self::new(
+            Signer::require_env("CONTACT_POW_SECRET")?,
+            u32::try_from(difficulty).unwrap_or(u32::MAX),
+            Duration::from_secs(ttl),
+        )
+    }
+
+    fn challenge_at(&self, now: u64) -> ContactChallenge {
+        let nonce = URL_SAFE_NO_PAD.encode(signing::random_bytes::<12>());
+        let expires = now + self.ttl.as_secs();
+        ContactChallenge {
+            challenge: self.signer.sign(&format!("{expires}.{}.{nonce}", self.difficulty)),
+            difficulty: self.difficulty as i32,
+            expires_in_seconds: i32::try_from(self.ttl.as_secs()).unwrap_or(i32::MAX),
+        }
+    }
+
+    fn verify_at(&self, token: &str, now: u64) -> Result<(), VerificationError> {
+        let failed = |reason: &str| Err(VerificationError::Failed(reason.to_string()));
+        let Some((challenge, counter)) = token.rsplit_once(':') else {
+            return failed("malformed proof of work");
+        };
+        if counter.is_empty() || counter.len() > 20 || !counter.bytes().all(|b| b.is_ascii_digit()) {
+            return failed("malformed proof of work");
+        }
+        let Some(payload) = self.signer.verify(challenge) else {
+            return failed("unknown challenge");
+        };
+        let mut parts = payload.splitn(3, '.');
+        let expires = parts.next().and_then(|value| value.parse::<u64>().ok());
+        let difficulty = parts.next().and_then(|value| value.parse::<u32>().ok());
+        let (Some(expires), Some(difficulty)) = (expires, difficulty) else {
+            return failed("unknown challenge");
+        };
+        if expires < now {
+            return failed("challenge expired");
+        }
+        if leading_zero_bits(&Sha256::digest(token.as_bytes())) < difficulty {
+            return failed("insufficient proof of work");
+        }
+
+        let mut spent = self.spent.lock().unwrap();
+        spent.retain(|_, expiry| *expiry >= now);
+        if spent.insert(challenge.to_string(), expires).is_some() {
+            return failed("challenge already used");
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl Verifier for ProofOfWork {
+    async fn verify(&self, token: &str, _client_ip: Option<IpAddr>) -> Result<(), VerificationError> {
+        self.verify_at(token, unix_now())
+    }
+
+    fn challenge(&self) -> Option<ContactChallenge> {
+        Some(self.challenge_at(unix_now()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn solve(challenge: &ContactChallenge) -> String {
+        (0u64..)
+            .map(|counter| format!("{}:{counter}", challenge.challenge))
+            .find(|token| {
+                leading_zero_bits(&Sha256::digest(token.as_bytes())) >= challenge.difficulty as u32
+            })
+            .unwrap()
+    }
+
+    fn verifier(difficulty: u32) -> ProofOfWork {
+        ProofOfWork::new(Signer::new("secret"), difficulty, Duration::from_secs(60)).unwrap()
+    }
+
+    fn reason(result: Result<(), VerificationError>) -> String {
+        match result {
+            Err(VerificationError::Failed(reason)) => reason,
+            other => panic!("expected failure, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn counts_leading_zero_bits() {
+        assert_eq!(leading_zero_bits(&[0xff]), 0);
+        assert_eq!(leading_zero_bits(&[0x00, 0x1f]), 11);
+        assert_eq!(leading_zero_bits(&[0x00, 0x00]), 16);
+    }
+
+    #[test]
+    fn accepts_a_solution_once() {
+        let pow = verifier(8);
+        let challenge = pow.challenge_at(NOW);
+        assert_eq!(challenge.difficulty, 8);
+        let token = solve(&challenge);
+
+        assert_eq!(pow.verify_at(&token, NOW + 1), Ok(()));
+        assert_eq!(reason(pow.verify_at(&token, NOW + 2)), "challenge already used");
+    }
+
+    #[test]
+    fn rejects_bad_and_expired_solutions() {
+        let pow = verifier(8);
+        let challenge = pow.challenge_at(NOW);
+        let token = solve(&challenge);
+
+        assert_eq!(reason(pow.verify_at(&token, NOW + 61)), "challenge expired");
+        assert_eq!(reason(pow.verify_at(&challenge.challenge, NOW)), "malformed proof of work");
+        assert_eq!(reason(pow.verify_at(&format!("{}:x1", challenge.challenge), NOW)), "malformed proof of work");
+
+        let forged = verifier(0).challenge_at(NOW);
+        let other_key = ProofOfWork::new(Signer::new("other"), 0, Duration::from_secs(60)).unwrap();
+        assert_eq!(reason(other_key.verify_at(&solve(&forged), NOW)), "unknown challenge");
+
+        // Find a counter that misses the target rather than hits it.
+        let unsolved = (0u64..)
+            .map(|counter| format!("{}:{counter}", challenge.challenge))
+            .find(|token| leading_zero_bits(&Sha256::digest(token.as_bytes())) < 8)
+            .unwrap();
+        assert_eq!(reason(pow.verify_at(&unsolved, NOW)), "insufficient proof of work");
+    }
+
+    #[test]
+    fn rejects_unsolvable_difficulties() {
+        assert!(ProofOfWork::new(Signer::new("secret"), 33, DEFAULT_TTL).is_err());
+    }
+}
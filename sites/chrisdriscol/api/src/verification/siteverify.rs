@@ -0,0 +1,119 @@
+use std::net::IpAddr;
+use std::time::Duration;
+
+use async_graphql::async_trait;
+use serde::Deserialize;
+
+use super::{VerificationError, Verifier};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Checks tokens against a Turnstile- or hCaptcha-style `siteverify`
+/// endpoint, which both take the same form fields and answer alike.
+pub struct SiteVerifier {
+    client: reqwest::Client,
+    url: String,
+    secret: String,
+}
+
+#[derive(Deserialize)]
+struct SiteVerifyResponse {
+    success: bool,
+    #[serde(default, rename = "error-codes")]
+    error_codes: Vec<String>,
+}
+
+impl SiteVerifier {
+    pub fn new(url: impl Into<String>, secret: impl Into<String>) -> Result<Self, String> {
+        let client = reqwest::Client::builder()
+            .timeout(TIMEOUT)
+            .build()
+            .map_err(|err| format!("failed to build siteverify client: {err}"))?;
+        Ok(Self {
+            client,
+            url: url.into(),
+            secret: secret.into(),
+        })
+    }
+
+    /// Requires `CONTACT_VERIFY_SECRET`. `CONTACT_VERIFY_URL` overrides the
+    /// provider's endpoint, e.g. to point at a local mock.
+    pub fn from_env(default_url: &str) -> Result<Self, String> {
+        let secret = std::env::var("CONTACT_VERIFY_SECRET")
+            .map_err(|_| "CONTACT_VERIFY_SECRET is required for siteverify".to_string())?;
+        let url = std::env::var("CONTACT_VERIFY_URL").unwrap_or_else(|_| default_url.to_string());
+        Self::new(url, secret)
+    }
+}
+
+#[async_trait::async_trait]
+impl Verifier for SiteVerifier {
+    async fn verify(&self, token: &str, client_ip: Option<IpAddr>) -> Result<(), VerificationError> {
+        let mut form = vec![("secret", self.secret.clone()), ("response", token.to_string())];
+        if let Some(ip) = client_ip {
+            form.push(("remoteip", ip.to_string()));
+        }
+        let unavailable = |err: reqwest::Error| VerificationError::Unavailable(err.to_string());
+        let response: SiteVerifyResponse = self
+            .client
+            .post(&self.url)
+            .form(&form)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(unavailable)?
+            .json()
+            .await
+            .map_err(unavailable)?;
+        if response.success {
+            Ok(())
+        } else if response.error_codes.is_empty() {
+            Err(VerificationError::Failed("token rejected".to_string()))
+        } else {
+            Err(VerificationError::Failed(response.error_codes.join(", ")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::post;
+    use axum::{Form, Json, Router};
+    use std::collections::HashMap;
+
+    /// Mock siteverify endpoint that accepts the token `good` for secret `s3cret`.
+    async fn mock_server() -> String {
+        async fn siteverify(Form(form): Form<HashMap<String, String>>) -> Json<serde_json::Value> {
+            let success = form.get("secret").map(String::as_str) == Some("s3cret")
+                && form.get("response").map(String::as_str) == Some("good")
+                && form.get("remoteip").map(String::as_str) == Some("203.0.113.7");
+            let codes: Vec<&str> = if success { vec![] } else { vec!["invalid-input-response"] };
+            Json(serde_json::json!({ "success": success, "error-codes": codes }))
+        }
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new().route("/siteverify", post(siteverify));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}/siteverify")
+    }
+
+    #[tokio::test]
+    async fn verifies_against_a_siteverify_endpoint() {
+        let url = mock_server().await;
+        let ip = Some(IpAddr::from([203, 0, 113, 7]));
+
+        let verifier = SiteVerifier::new(&url, "s3cret").unwrap();
+        assert_eq!(verifier.verify("good", ip).await, Ok(()));
+        assert_eq!(
+            verifier.verify("bad", ip).await,
+            Err(VerificationError::Failed("invalid-input-response".to_string()))
+        );
+
+        let unreachable = SiteVerifier::new(format!("{url}/missing"), "s3cret").unwrap();
+        assert!(matches!(
+            unreachable.verify("good", ip).await,
+            Err(VerificationError::Unavailable(_))
+        ));
+    }
+}
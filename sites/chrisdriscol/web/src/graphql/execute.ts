@@ -1,33 +1,98 @@
 import { graphqlUrl } from "./api";
 import type { TypedDocumentString } from "../graphql/generated/graphql";
 
-export async function execute<TResult, TVariables>(
-  query: TypedDocumentString<TResult, TVariables>,
-  ...[variables]: TVariables extends Record<string, never> ? [] : [TVariables]
-): Promise<TResult> {
-  const response = await fetch(graphqlUrl, {
+export type GraphQLErrorExtensions = {
+  code?: string;
+  field?: string;
+  max?: number;
+  retryAfter?: number;
+};
+
+export type GraphQLError = {
+  message: string;
+  extensions?: GraphQLErrorExtensions;
+};
+
+type GraphQLResponse<TResult> = {
+  data?: TResult;
+  errors?: GraphQLError[];
+};
+
+// Keeps every error from the response so callers can map field-level codes.
+export class GraphQLRequestError extends Error {
+  readonly errors: GraphQLError[];
+
+  constructor(errors: GraphQLError[]) {
+    super(errors[0]?.message ?? "GraphQL request failed.");
+    this.name = "GraphQLRequestError";
+    this.errors = errors;
+  }
+}
+
+async function sha256(text: string): Promise<string> {
+  const digest = await crypto.subtle.digest(
+    "SHA-256",
+    new TextEncoder().encode(text),
+  );
+  return Array.from(new Uint8Array(digest), (byte) =>
+    byte.toString(16).padStart(2, "0"),
+  ).join("");
+}
+
+const accept = "application/graphql-response+json";
+
+function post(body: Record<string, unknown>): Promise<Response> {
+  return fetch(graphqlUrl, {
     method: "POST",
     headers: {
       "Content-Type": "application/json",
-      Accept: "application/graphql-response+json",
+      Accept: accept,
     },
-    body: JSON.stringify({
-      query,
-      variables,
-    }),
+    body: JSON.stringify(body),
   });
+}
+
+// Queries go over GET so CloudFront and the browser can cache them.
+function get(params: Record<string, unknown>): Promise<Response> {
+  const search = new URLSearchParams();
+  for (const [key, value] of Object.entries(params)) {
+    if (value !== undefined) {
+      search.set(key, JSON.stringify(value));
+    }
+  }
+  return fetch(`${graphqlUrl}?${search}`, { headers: { Accept: accept } });
+}
+
+// Sends the document's hash first (Automatic Persisted Queries) and only
+// falls back to the full text when the server has not seen it yet.
+export async function execute<TResult, TVariables>(
+  query: TypedDocumentString<TResult, TVariables>,
+  ...[variables]: TVariables extends Record<string, never> ? [] : [TVariables]
+): Promise<TResult> {
+  const document = query.toString();
+  const extensions = {
+    persistedQuery: { version: 1, sha256Hash: await sha256(document) },
+  };
+
+  const isQuery = document.trimStart().startsWith("query");
+  let response = isQuery
+    ? await get({ variables, extensions })
+    : await post({ variables, extensions });
+  if (response.ok) {
+    const json = (await response.clone().json()) as GraphQLResponse<TResult>;
+    if (json.errors?.[0]?.extensions?.code === "PERSISTED_QUERY_NOT_FOUND") {
+      response = await post({ query: document, variables, extensions });
+    }
+  }
 
   if (!response.ok) {
     throw new Error("Network response was not ok");
   }
 
-  const json = (await response.json()) as {
-    data?: TResult;
-    errors?: Array<{ message: string }>;
-  };
+  const json = (await response.json()) as GraphQLResponse<TResult>;
 
   if (json.errors?.length) {
-    throw new Error(json.errors[0]?.message ?? "GraphQL request failed.");
+    throw new GraphQLRequestError(json.errors);
   }
 
   if (!json.data) {
@@ -17,6 +17,8 @@ import * as types from './graphql';
 type Documents = {
     "\n  fragment AboutSection on About {\n    description\n    imageUrl\n    imageCaption\n    imageTitle\n    tagLine\n  }\n": typeof types.AboutSectionFragmentDoc,
     "\n  mutation ContactMe($input: ContactMeInput!) {\n    contactMe(input: $input) {\n      success\n    }\n  }\n": typeof types.ContactMeDocument,
+    "\n  query ContactChallenge {\n    contactChallenge {\n      challenge\n      difficulty\n    }\n  }\n": typeof types.ContactChallengeDocument,
+    "\n  query ContactFormToken {\n    contactFormToken\n  }\n": typeof types.ContactFormTokenDocument,
     "\n  fragment ExperienceItem on Experience {\n    duration\n    title\n    location\n    description\n    imageUrl\n  }\n": typeof types.ExperienceItemFragmentDoc,
     "\n  fragment FooterSocial on Social {\n    linkedIn\n    github\n    email\n  }\n": typeof types.FooterSocialFragmentDoc,
     "\n  fragment PortfolioCard on Work {\n    title\n    subTitle\n    location\n    link\n    date\n    imageUrl\n  }\n": typeof types.PortfolioCardFragmentDoc,
@@ -28,6 +30,8 @@ type Documents = {
 const documents: Documents = {
     "\n  fragment AboutSection on About {\n    description\n    imageUrl\n    imageCaption\n    imageTitle\n    tagLine\n  }\n": types.AboutSectionFragmentDoc,
     "\n  mutation ContactMe($input: ContactMeInput!) {\n    contactMe(input: $input) {\n      success\n    }\n  }\n": types.ContactMeDocument,
+    "\n  query ContactChallenge {\n    contactChallenge {\n      challenge\n      difficulty\n    }\n  }\n": types.ContactChallengeDocument,
+    "\n  query ContactFormToken {\n    contactFormToken\n  }\n": types.ContactFormTokenDocument,
     "\n  fragment ExperienceItem on Experience {\n    duration\n    title\n    location\n    description\n    imageUrl\n  }\n": types.ExperienceItemFragmentDoc,
     "\n  fragment FooterSocial on Social {\n    linkedIn\n    github\n    email\n  }\n": types.FooterSocialFragmentDoc,
     "\n  fragment PortfolioCard on Work {\n    title\n    subTitle\n    location\n    link\n    date\n    imageUrl\n  }\n": types.PortfolioCardFragmentDoc,
@@ -45,6 +49,14 @@ export function graphql(source: "\n  fragment AboutSection on About {\n    descr
  * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
  */
 export function graphql(source: "\n  mutation ContactMe($input: ContactMeInput!) {\n    contactMe(input: $input) {\n      success\n    }\n  }\n"): typeof import('./graphql').ContactMeDocument;
+/**
+ * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
+ */
+export function graphql(source: "\n  query ContactChallenge {\n    contactChallenge {\n      challenge\n      difficulty\n    }\n  }\n"): typeof import('./graphql').ContactChallengeDocument;
+/**
+ * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
+ */
+export function graphql(source: "\n  query ContactFormToken {\n    contactFormToken\n  }\n"): typeof import('./graphql').ContactFormTokenDocument;
 /**
  * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
  */
@@ -37,15 +37,27 @@ export type Chris = {
   work: Array<Work>;
 };
 
+export type ContactChallenge = {
+  __typename?: 'ContactChallenge';
+  challenge: Scalars['String']['output'];
+  difficulty: Scalars['Int']['output'];
+  expiresInSeconds: Scalars['Int']['output'];
+};
+
 export type ContactMeInput = {
   body: Scalars['String']['input'];
+  formToken?: InputMaybe<Scalars['String']['input']>;
   from: Scalars['String']['input'];
   name: Scalars['String']['input'];
+  sendCopy?: InputMaybe<Scalars['Boolean']['input']>;
   subject: Scalars['String']['input'];
+  verificationToken?: InputMaybe<Scalars['String']['input']>;
+  website?: InputMaybe<Scalars['String']['input']>;
 };
 
 export type ContactMePayload = {
   __typename?: 'ContactMePayload';
+  submissionId: Scalars['ID']['output'];
   success: Scalars['Boolean']['output'];
 };
 
@@ -71,6 +83,8 @@ export type MutationRootContactMeArgs = {
 export type QueryRoot = {
   __typename?: 'QueryRoot';
   chris: Chris;
+  contactChallenge?: Maybe<ContactChallenge>;
+  contactFormToken: Scalars['String']['output'];
   ping: Scalars['String']['output'];
 };
 
@@ -111,6 +125,16 @@ export type ContactMeMutationVariables = Exact<{
 
 export type ContactMeMutation = { __typename?: 'MutationRoot', contactMe: { __typename?: 'ContactMePayload', success: boolean } };
 
+export type ContactChallengeQueryVariables = Exact<{ [key: string]: never; }>;
+
+
+export type ContactChallengeQuery = { __typename?: 'QueryRoot', contactChallenge?: { __typename?: 'ContactChallenge', challenge: string, difficulty: number } | null };
+
+export type ContactFormTokenQueryVariables = Exact<{ [key: string]: never; }>;
+
+
+export type ContactFormTokenQuery = { __typename?: 'QueryRoot', contactFormToken: string };
+
 export type ExperienceItemFragment = { __typename?: 'Experience', duration?: string | null, title?: string | null, location?: string | null, description?: string | null, imageUrl?: string | null } & { ' $fragmentName'?: 'ExperienceItemFragment' };
 
 export type FooterSocialFragment = { __typename?: 'Social', linkedIn: string, github: string, email: string } & { ' $fragmentName'?: 'FooterSocialFragment' };
@@ -230,6 +254,19 @@ export const ContactMeDocument = new TypedDocumentString(`
   }
 }
     `) as unknown as TypedDocumentString<ContactMeMutation, ContactMeMutationVariables>;
+export const ContactChallengeDocument = new TypedDocumentString(`
+    query ContactChallenge {
+  contactChallenge {
+    challenge
+    difficulty
+  }
+}
+    `) as unknown as TypedDocumentString<ContactChallengeQuery, ContactChallengeQueryVariables>;
+export const ContactFormTokenDocument = new TypedDocumentString(`
+    query ContactFormToken {
+  contactFormToken
+}
+    `) as unknown as TypedDocumentString<ContactFormTokenQuery, ContactFormTokenQueryVariables>;
 export const AppQueryDocument = new TypedDocumentString(`
     query AppQuery {
   chris {
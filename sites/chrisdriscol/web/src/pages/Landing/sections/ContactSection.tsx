@@ -1,8 +1,9 @@
-import { useState, type FormEvent } from "react";
+import { useRef, useState, type FormEvent } from "react";
 import { graphql } from "@/graphql/generated";
-import { execute } from "@/graphql/execute";
+import { execute, GraphQLRequestError, type GraphQLError } from "@/graphql/execute";
 import { SectionHeader, Section, SectionTagline, SectionTitle, SiteContainer } from "@/layout";
 import { Button } from "@/ui";
+import { solveChallenge } from "@/utils/solveChallenge";
 
 const formControlClass =
   "w-full rounded-[3px] border border-[#ccc] bg-white p-5 text-ink text-sm font-heading normal-case font-normal outline-none transition-[border] duration-200 ease-in-out focus:border-accent placeholder:text-[#bbb] placeholder:font-normal";
@@ -14,6 +15,52 @@ type ContactErrors = {
   body?: string;
 };
 
+type ContactField = keyof ContactErrors;
+
+// Mirrors the API's limits in src/validation.rs.
+const maxLengths: Record<ContactField, number> = {
+  from: 254,
+  name: 100,
+  subject: 200,
+  body: 5000,
+};
+
+const requiredMessages: Record<ContactField, string> = {
+  from: "Please enter your email.",
+  name: "Please enter your name.",
+  subject: "Please enter a subject.",
+  body: "Please enter a message.",
+};
+
+const isContactField = (field: string | undefined): field is ContactField =>
+  field !== undefined && field in maxLengths;
+
+const fieldErrorMessage = (field: ContactField, error: GraphQLError) => {
+  switch (error.extensions?.code) {
+    case "REQUIRED":
+      return requiredMessages[field];
+    case "TOO_LONG":
+      return `Please keep this to ${error.extensions?.max ?? maxLengths[field]} characters or fewer.`;
+    case "INVALID_EMAIL":
+      return "Please enter a valid email.";
+    case "INVALID_CHARACTERS":
+      return "Please remove line breaks and special characters.";
+    default:
+      return error.message;
+  }
+};
+
+// Returns null when the errors are not all tied to form inputs.
+const contactFieldErrors = (errors: GraphQLError[]): ContactErrors | null => {
+  const fieldErrors: ContactErrors = {};
+  for (const error of errors) {
+    const field = error.extensions?.field;
+    if (!isContactField(field)) return null;
+    fieldErrors[field] ??= fieldErrorMessage(field, error);
+  }
+  return fieldErrors;
+};
+
 const ContactMutationDocument = graphql(/* GraphQL */ `
   mutation ContactMe($input: ContactMeInput!) {
     contactMe(input: $input) {
@@ -22,6 +69,29 @@ const ContactMutationDocument = graphql(/* GraphQL */ `
   }
 `);
 
+const ContactChallengeDocument = graphql(/* GraphQL */ `
+  query ContactChallenge {
+    contactChallenge {
+      challenge
+      difficulty
+    }
+  }
+`);
+
+const ContactFormTokenDocument = graphql(/* GraphQL */ `
+  query ContactFormToken {
+    contactFormToken
+  }
+`);
+
+// Returns the proof-of-work token when the API asks for one.
+const verificationToken = async () => {
+  const { contactChallenge } = await execute(ContactChallengeDocument);
+  return contactChallenge
+    ? solveChallenge(contactChallenge.challenge, contactChallenge.difficulty)
+    : undefined;
+};
+
 export const ContactSection = () => {
   const [contactSent, setContactSent] = useState(false);
   const [contactError, setContactError] = useState<string | null>(null);
@@ -32,19 +102,32 @@ export const ContactSection = () => {
     body: "",
   });
   const [contactErrors, setContactErrors] = useState<ContactErrors>({});
+  // Honeypot: hidden from people, so only bots fill it in.
+  const [website, setWebsite] = useState("");
+  const [sendCopy, setSendCopy] = useState(false);
+  // Fetched when someone first focuses the form, so the API can tell how
+  // long they took to write the message.
+  const formToken = useRef<Promise<string | undefined> | undefined>(undefined);
+
+  const requestFormToken = () => {
+    formToken.current ??= execute(ContactFormTokenDocument).then(
+      (result) => result.contactFormToken,
+      () => undefined,
+    );
+  };
 
   const validateContact = () => {
     const nextErrors: ContactErrors = {};
-    if (!contactForm.name.trim()) nextErrors.name = "Please enter your name.";
-    if (!contactForm.from.trim()) nextErrors.from = "Please enter your email.";
+    if (!contactForm.name.trim()) nextErrors.name = requiredMessages.name;
+    if (!contactForm.from.trim()) nextErrors.from = requiredMessages.from;
     if (
       contactForm.from.trim() &&
       !/^[^\s@]+@[^\s@]+\.[^\s@]+$/.test(contactForm.from)
     ) {
       nextErrors.from = "Please enter a valid email.";
     }
-    if (!contactForm.subject.trim()) nextErrors.subject = "Please enter a subject.";
-    if (!contactForm.body.trim()) nextErrors.body = "Please enter a message.";
+    if (!contactForm.subject.trim()) nextErrors.subject = requiredMessages.subject;
+    if (!contactForm.body.trim()) nextErrors.body = requiredMessages.body;
     setContactErrors(nextErrors);
     return Object.keys(nextErrors).length === 0;
   };
@@ -61,6 +144,10 @@ export const ContactSection = () => {
           from: contactForm.from,
           subject: contactForm.subject,
           body: contactForm.body,
+          verificationToken: await verificationToken(),
+          website,
+          formToken: await formToken.current,
+          sendCopy,
         },
       });
       if (!result.contactMe?.success) {
@@ -68,7 +155,24 @@ export const ContactSection = () => {
       }
       setContactSent(true);
       setContactForm({ name: "", from: "", subject: "", body: "" });
+      setSendCopy(false);
     } catch (err) {
+      const fieldErrors =
+        err instanceof GraphQLRequestError ? contactFieldErrors(err.errors) : null;
+      if (fieldErrors) {
+        setContactErrors(fieldErrors);
+        return;
+      }
+      const rateLimited =
+        err instanceof GraphQLRequestError &&
+        err.errors.find((error) => error.extensions?.code === "RATE_LIMITED");
+      if (rateLimited) {
+        const minutes = Math.ceil((rateLimited.extensions?.retryAfter ?? 60) / 60);
+        setContactError(
+          `You've sent a few messages already. Please try again in ${minutes} minute${minutes === 1 ? "" : "s"}.`,
+        );
+        return;
+      }
       setContactError(err instanceof Error ? err.message : "There was an error sending your email.");
     }
   };
@@ -89,7 +193,22 @@ export const ContactSection = () => {
         </SectionHeader>
         <div className="mt-8">
           {!contactSent ? (
-            <form onSubmit={handleContactSubmit} className="grid gap-[25px] md:grid-cols-2">
+            <form
+              onSubmit={handleContactSubmit}
+              onFocusCapture={requestFormToken}
+              className="relative grid gap-[25px] md:grid-cols-2"
+            >
+              <div className="absolute -left-[10000px] h-px w-px overflow-hidden" aria-hidden="true">
+                <label htmlFor="contact-website">Website</label>
+                <input
+                  id="contact-website"
+                  type="text"
+                  tabIndex={-1}
+                  autoComplete="off"
+                  value={website}
+                  onChange={(event) => setWebsite(event.target.value)}
+                />
+              </div>
               <div className="flex flex-col gap-[25px]">
                 <div className="mb-0">
                   <label htmlFor="contact-email" className="sr-only">
@@ -100,6 +219,7 @@ export const ContactSection = () => {
                     type="email"
                     className={formControlClass}
                     placeholder="your email *"
+                    maxLength={maxLengths.from}
                     value={contactForm.from}
                     onChange={(event) =>
                       setContactForm((prev) => ({ ...prev, from: event.target.value }))
@@ -118,6 +238,7 @@ export const ContactSection = () => {
                     type="text"
                     className={formControlClass}
                     placeholder="your name *"
+                    maxLength={maxLengths.name}
                     value={contactForm.name}
                     onChange={(event) =>
                       setContactForm((prev) => ({ ...prev, name: event.target.value }))
@@ -136,6 +257,7 @@ export const ContactSection = () => {
                     type="text"
                     className={formControlClass}
                     placeholder="subject *"
+                    maxLength={maxLengths.subject}
                     value={contactForm.subject}
                     onChange={(event) =>
                       setContactForm((prev) => ({ ...prev, subject: event.target.value }))
@@ -156,6 +278,7 @@ export const ContactSection = () => {
                     rows={9}
                     className={`${formControlClass} h-[236px]`}
                     placeholder="this is where you say something.. *"
+                    maxLength={maxLengths.body}
                     value={contactForm.body}
                     onChange={(event) =>
                       setContactForm((prev) => ({ ...prev, body: event.target.value }))
@@ -166,6 +289,18 @@ export const ContactSection = () => {
                   ) : null}
                 </div>
               </div>
+              <div className="col-span-full -mt-[10px] text-right">
+                <label htmlFor="contact-send-copy" className="inline-flex cursor-pointer items-center gap-2 text-sm">
+                  <input
+                    id="contact-send-copy"
+                    type="checkbox"
+                    className="accent-accent"
+                    checked={sendCopy}
+                    onChange={(event) => setSendCopy(event.target.checked)}
+                  />
+                  Email me a copy of my message
+                </label>
+              </div>
               <div className="col-span-full mt-[10px] text-center">
                 <Button type="submit">Send Message</Button>
                 {contactError ? (
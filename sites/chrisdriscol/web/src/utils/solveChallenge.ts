@@ -0,0 +1,25 @@
+const leadingZeroBits = (bytes: Uint8Array) => {
+  let bits = 0;
+  for (const byte of bytes) {
+    if (byte === 0) {
+      bits += 8;
+      continue;
+    }
+    return bits + Math.clz32(byte) - 24;
+  }
+  return bits;
+};
+
+// Solves a `contactChallenge`: finds a counter whose SHA-256 of
+// `<challenge>:<counter>` starts with `difficulty` zero bits and returns the
+// token `contactMe` expects.
+export const solveChallenge = async (challenge: string, difficulty: number) => {
+  const encoder = new TextEncoder();
+  for (let counter = 0; ; counter += 1) {
+    const token = `${challenge}:${counter}`;
+    const digest = await crypto.subtle.digest("SHA-256", encoder.encode(token));
+    if (leadingZeroBits(new Uint8Array(digest)) >= difficulty) {
+      return token;
+    }
+  }
+};